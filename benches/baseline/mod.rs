@@ -0,0 +1,382 @@
+//! Copy of the original two pass obj loader, kept as the baseline of the obj
+//! loading benchmarks. The first pass copies the vertex lines and the face
+//! lines of every object into strings with `format!`, the second pass parses
+//! them. It only supports triangles with normals and panics on invalid files.
+
+use std::{collections::HashMap, path::Path};
+
+use glam::{Vec2, Vec3A};
+
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_till},
+    character::{
+        complete::{self, digit0, line_ending, space0, space1},
+        is_newline,
+    },
+    multi::{count, separated_list1},
+    number::complete::float,
+    sequence::{preceded, terminated},
+    IResult,
+};
+
+use rusticvision::{
+    material::Material,
+    primitives::{TriangleIndex, TriangleMesh},
+};
+
+/// Load the triangle mesh and the triangles of every object of an obj file.
+///
+/// ### Panics
+/// If the obj file or its material file can't be read or parsed.
+pub fn load_obj(path: &Path) -> (TriangleMesh, HashMap<String, Vec<TriangleIndex>>) {
+    let obj_file = std::fs::read_to_string(path).expect("Unable to read obj file");
+    let (_, (v_data, material_file, object_map)) =
+        extract_parts_obj(&obj_file).expect("Failed to extract parts from obj file");
+    let (_, (vp, vn, _)) = parse_vertex_data(&v_data).expect("Failed to parse vertex data");
+
+    let mat_path = path.with_file_name(material_file.trim_end());
+    let mat_file = std::fs::read_to_string(mat_path).expect("Unable to read material file");
+    let (_, (materials, material_map)) = materials(&mat_file).unwrap();
+    let triangle_mesh = TriangleMesh::new(vp, vn, materials);
+
+    let objects = object_map
+        .into_iter()
+        .map(|(name, faces)| {
+            let (_, indices) = parse_triangle_indices(&faces, &material_map)
+                .expect("Failed to parse triangle faces");
+            (name, indices)
+        })
+        .collect();
+    (triangle_mesh, objects)
+}
+
+#[derive(Debug)]
+enum ParseLineResult<'a> {
+    VertexPosition(&'a str),
+    VertexNormal(&'a str),
+    VertexTexture(&'a str),
+    ObjectName(&'a str),
+    UseMaterial(&'a str),
+    Face(&'a str),
+    MaterialFile(&'a str),
+    Other,
+}
+
+/// Parse a line of the input string slice.
+fn line(input: &str) -> IResult<&str, &str> {
+    take_till(|c| is_newline(c as u8))(input)
+}
+
+/// Parse a line of an obj file. Returns the line as ParseLineResult.
+fn parse_obj_line(input: &str) -> IResult<&str, ParseLineResult<'_>> {
+    use ParseLineResult::*;
+    alt((
+        |i| preceded(tag("v "), line)(i).map(|(i, l)| (i, VertexPosition(l))),
+        |i| preceded(tag("vn "), line)(i).map(|(i, l)| (i, VertexNormal(l))),
+        |i| preceded(tag("vt "), line)(i).map(|(i, l)| (i, VertexTexture(l))),
+        |i| preceded(tag("o "), line)(i).map(|(i, l)| (i, ObjectName(l))),
+        |i| preceded(tag("usemtl "), line)(i).map(|(i, l)| (i, UseMaterial(l))),
+        |i| preceded(tag("f "), line)(i).map(|(i, l)| (i, Face(l))),
+        |i| preceded(tag("mtllib "), line)(i).map(|(i, l)| (i, MaterialFile(l))),
+        |i| line(i).map(|(i, _)| (i, Other)),
+    ))(input)
+}
+
+/// Extract the vertex data, the material file name and the faces of every
+/// object of the obj file.
+fn extract_parts_obj(input: &str) -> IResult<&str, (String, String, HashMap<String, String>)> {
+    let mut vertex_data = String::new();
+    let mut material_file = String::new();
+    let mut object_map = HashMap::new();
+
+    let (input, lines) = separated_list1(line_ending, parse_obj_line)(input)?;
+
+    let mut current_object = "";
+    lines.into_iter().for_each(|l| {
+        use ParseLineResult::*;
+        match l {
+            VertexPosition(pos) => vertex_data.push_str(&format!("v {}\n", pos)),
+            VertexNormal(pos) => vertex_data.push_str(&format!("vn {}\n", pos)),
+            VertexTexture(pos) => vertex_data.push_str(&format!("vt {}\n", pos)),
+            ObjectName(name) => {
+                current_object = name;
+                object_map.insert(current_object.to_owned(), String::new());
+            }
+            UseMaterial(material) => {
+                object_map
+                    .get_mut(current_object)
+                    .unwrap()
+                    .push_str(&format!("usemtl {}\n", material));
+            }
+            Face(face) => {
+                object_map
+                    .get_mut(current_object)
+                    .unwrap()
+                    .push_str(&format!("f {}\n", face));
+            }
+            MaterialFile(file) => material_file.push_str(&format!("{}\n", file)),
+            Other => (),
+        }
+    });
+    Ok((input, (vertex_data, material_file, object_map)))
+}
+
+enum VertexParseResult {
+    Position(Vec3A),
+    Normal(Vec3A),
+    TexCoord(Vec2),
+}
+
+type VertexData = (Vec<Vec3A>, Vec<Vec3A>, Vec<Vec2>);
+
+/// Parse a line separated list of vertex positions, normals and texture
+/// coordinates.
+fn parse_vertex_data(input: &str) -> IResult<&str, VertexData> {
+    let (input, parsed_vertex_data) =
+        separated_list1(line_ending, parse_single_vertex_data)(input)?;
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut tex_coords = Vec::new();
+    parsed_vertex_data
+        .into_iter()
+        .for_each(|vertex_data| match vertex_data {
+            VertexParseResult::Position(position) => positions.push(position),
+            VertexParseResult::Normal(normal) => normals.push(normal),
+            VertexParseResult::TexCoord(tex_coord) => tex_coords.push(tex_coord),
+        });
+    Ok((input, (positions, normals, tex_coords)))
+}
+
+/// Parse a vertex position `v x y z`, normal `vn x y z` or texture
+/// coordinate `vt x y`.
+fn parse_single_vertex_data(input: &str) -> IResult<&str, VertexParseResult> {
+    alt((
+        |i| {
+            let (i, a) = preceded(tag("v "), separated_list1(space1, float))(i)?;
+            Ok((i, VertexParseResult::Position(Vec3A::from_slice(&a))))
+        },
+        |i| {
+            let (i, a) = preceded(tag("vn "), separated_list1(space1, float))(i)?;
+            Ok((
+                i,
+                VertexParseResult::Normal(Vec3A::from_slice(&a).normalize()),
+            ))
+        },
+        |i| {
+            let (i, a) = preceded(tag("vt "), separated_list1(space1, float))(i)?;
+            Ok((i, VertexParseResult::TexCoord(Vec2::from_slice(&a))))
+        },
+    ))(input)
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum VertexIndexGroup {
+    PosNormTex(usize, usize, usize),
+    PosNorm(usize, usize),
+    PosTex(usize, usize),
+    Pos(usize),
+}
+
+#[derive(Debug)]
+enum TriangleIndexData<'a> {
+    Face([VertexIndexGroup; 3]),
+    MaterialName(&'a str),
+}
+
+/// Parse the `usemtl` lines and the triangles of an object.
+fn parse_triangle_indices<'a>(
+    input: &'a str,
+    material_map: &'a HashMap<String, usize>,
+) -> IResult<&'a str, Vec<TriangleIndex>> {
+    use TriangleIndexData::*;
+    let (input, data) =
+        separated_list1(line_ending, alt((parse_triangle_face, parse_material_name)))(input)?;
+
+    let mut triangle_indices = Vec::new();
+    let mut current_material_index = 0;
+    data.into_iter().for_each(|d| match d {
+        MaterialName(name) => {
+            current_material_index = *material_map.get(name).expect("Material not found");
+        }
+        Face([v1, v2, v3]) => {
+            triangle_indices.push(convert_to_triangle_index(
+                &v1,
+                &v2,
+                &v3,
+                current_material_index,
+            ));
+        }
+    });
+    Ok((input, triangle_indices))
+}
+
+/// Parse an index and decrement it by 1.
+fn usize_decrement(input: &str) -> IResult<&str, usize> {
+    let (input, num) = complete::u32(input)?;
+    Ok((input, (num - 1) as usize))
+}
+
+/// Parse a face vertex index group of the form `v/vt/vn`, `v//vn`, `v/vt` or
+/// `v`.
+fn parse_vertex_index_group(input: &str) -> IResult<&str, VertexIndexGroup> {
+    use VertexIndexGroup::*;
+    alt((
+        |i| {
+            let (i, pos) = terminated(usize_decrement, tag("/"))(i)?;
+            let (i, tex) = terminated(usize_decrement, tag("/"))(i)?;
+            let (i, norm) = usize_decrement(i)?;
+            Ok((i, PosNormTex(pos, tex, norm)))
+        },
+        |i| {
+            let (i, pos) = terminated(usize_decrement, tag("//"))(i)?;
+            let (i, norm) = usize_decrement(i)?;
+            Ok((i, PosNorm(pos, norm)))
+        },
+        |i| {
+            let (i, pos) = terminated(usize_decrement, tag("/"))(i)?;
+            let (i, tex) = usize_decrement(i)?;
+            Ok((i, PosTex(pos, tex)))
+        },
+        |i| usize_decrement(i).map(|(i, pos)| (i, Pos(pos))),
+    ))(input)
+}
+
+/// Parse a triangle face of the form `f a/b/c d/e/f g/h/i`.
+fn parse_triangle_face(input: &str) -> IResult<&str, TriangleIndexData<'_>> {
+    let (input, _) = tag("f ")(input)?;
+    let (input, groups) = count(terminated(parse_vertex_index_group, space0), 3)(input)?;
+    Ok((
+        input,
+        TriangleIndexData::Face([groups[0], groups[1], groups[2]]),
+    ))
+}
+
+/// Parse a material name of the form `usemtl name`.
+fn parse_material_name(input: &str) -> IResult<&str, TriangleIndexData<'_>> {
+    let (input, material) = preceded(tag("usemtl "), line)(input)?;
+    Ok((input, TriangleIndexData::MaterialName(material)))
+}
+
+/// Convert three vertex index groups and a material index into a triangle
+/// index.
+///
+/// ### Panics
+/// If the vertex index groups have no normals.
+fn convert_to_triangle_index(
+    v1: &VertexIndexGroup,
+    v2: &VertexIndexGroup,
+    v3: &VertexIndexGroup,
+    material_index: usize,
+) -> TriangleIndex {
+    use VertexIndexGroup::*;
+    let m = material_index;
+    match (v1, v2, v3) {
+        (PosNormTex(p1, _, n), PosNormTex(p2, _, _), PosNormTex(p3, _, _)) => {
+            TriangleIndex::new((*p1, *p2, *p3), *n, m)
+        }
+        (PosNorm(p1, n), PosNorm(p2, _), PosNorm(p3, _)) => {
+            TriangleIndex::new((*p1, *p2, *p3), *n, m)
+        }
+        _ => panic!("Invalid vertex index group"),
+    }
+}
+
+enum MaterialProperty {
+    AmbientColor(Vec3A),
+    DiffuseColor(Vec3A),
+    SpecularColor(Vec3A),
+    EmissiveColor(Vec3A),
+    SpecularHighlight(f32),
+    Transparency(f32),
+    IndexOfRefraction(f32),
+    IlluminationModel,
+}
+
+/// Parse the materials of an mtl file, and a map from material name to the
+/// index of the material.
+fn materials(input: &str) -> IResult<&str, (Vec<Material>, HashMap<String, usize>)> {
+    let mut materials = Vec::new();
+    let mut material_index_map = HashMap::new();
+    let mut input = input;
+    loop {
+        if input.starts_with("newmtl ") {
+            let (remaining, (name, properties)) = parse_material(input)?;
+            material_index_map.insert(name.to_string(), materials.len());
+            materials.push(material_from_properties(&properties));
+            input = remaining;
+        }
+        if input.is_empty() {
+            break;
+        }
+        let (remaining, _) = take_till(|c| c == '\n')(input)?;
+        let (remaining, _) = line_ending(remaining)?;
+        input = remaining;
+    }
+    Ok((input, (materials, material_index_map)))
+}
+
+/// Construct a material from a list of material properties. Missing
+/// properties are set to their default values.
+fn material_from_properties(properties: &[MaterialProperty]) -> Material {
+    use MaterialProperty::*;
+    let mut material = Material::default();
+    for property in properties {
+        match *property {
+            AmbientColor(c) => material.ambient_color = c,
+            DiffuseColor(c) => material.diffuse_color = c,
+            SpecularColor(c) => material.specular_color = c,
+            EmissiveColor(c) => material.emissive_color = c,
+            SpecularHighlight(h) => material.specular_highlight = h,
+            Transparency(t) => material.transparency = t,
+            IndexOfRefraction(i) => material.index_of_refraction = i,
+            IlluminationModel => (),
+        }
+    }
+    material
+}
+
+fn parse_material(input: &str) -> IResult<&str, (&str, Vec<MaterialProperty>)> {
+    let (input, name) = preceded(tag("newmtl "), take_till(|c| c == '\n'))(input)?;
+    let (input, _) = line_ending(input)?;
+    let (input, properties) = separated_list1(line_ending, parse_material_property)(input)?;
+    Ok((input, (name, properties)))
+}
+
+/// Parse a color property, for example `Kd r g b`.
+fn color_property<'a>(
+    keyword: &'static str,
+    property: fn(Vec3A) -> MaterialProperty,
+) -> impl FnMut(&'a str) -> IResult<&'a str, MaterialProperty> {
+    move |input| {
+        let (input, a) = preceded(tag(keyword), separated_list1(space1, float))(input)?;
+        Ok((input, property(Vec3A::from_slice(&a))))
+    }
+}
+
+/// Parse a scalar property, for example `Ns exponent`.
+fn scalar_property<'a>(
+    keyword: &'static str,
+    property: fn(f32) -> MaterialProperty,
+) -> impl FnMut(&'a str) -> IResult<&'a str, MaterialProperty> {
+    move |input| {
+        let (input, a) = preceded(tag(keyword), float)(input)?;
+        Ok((input, property(a)))
+    }
+}
+
+/// Parse any material property.
+fn parse_material_property(input: &str) -> IResult<&str, MaterialProperty> {
+    use MaterialProperty::*;
+    alt((
+        color_property("Ka ", AmbientColor),
+        color_property("Kd ", DiffuseColor),
+        color_property("Ks ", SpecularColor),
+        scalar_property("Ns ", SpecularHighlight),
+        color_property("Ke ", EmissiveColor),
+        scalar_property("d ", Transparency),
+        scalar_property("Ni ", IndexOfRefraction),
+        |i| preceded(tag("illum "), digit0)(i).map(|(i, _)| (i, IlluminationModel)),
+    ))(input)
+}
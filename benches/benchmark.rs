@@ -3,11 +3,8 @@ use criterion::{criterion_group, criterion_main, Criterion};
 use rusticvision::prelude::*;
 
 fn render_monkey() {
-    let tracer = RayTracer::new()
-        .directory("test")
-        .obj_file("test_scene.obj")
-        .camera_position(7.0, 1.9, 0.0)
-        .camera_target(0.0, 1.9, 0.0)
+    let tracer = RayTracer::from_scene_file("test/test_scene.scene")
+        .unwrap()
         .resolution(50, 50)
         .sample_count(1000)
         .recursion_depth(4);
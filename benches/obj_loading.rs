@@ -0,0 +1,90 @@
+mod baseline;
+
+use std::{collections::HashMap, fmt::Write, fs::File, io::BufReader, path::PathBuf};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use rusticvision::{
+    primitives::TriangleIndex,
+    resolver::FileSystemResolver,
+    scene::parser::{parse_obj, read_obj},
+};
+
+/// Write an obj file with a grid of `size` x `size` quads, split into two
+/// triangles each, and its material file to the temporary directory. Returns
+/// the path of the obj file.
+fn write_grid_obj(size: usize) -> PathBuf {
+    let mut obj = String::from("mtllib rusticvision_grid.mtl\no Grid\nusemtl Grid\nvn 0 1 0\n");
+    for z in 0..=size {
+        for x in 0..=size {
+            writeln!(obj, "v {} 0 {}", x as f32 * 0.1, z as f32 * 0.1).unwrap();
+            writeln!(
+                obj,
+                "vt {} {}",
+                x as f32 / size as f32,
+                z as f32 / size as f32
+            )
+            .unwrap();
+        }
+    }
+    for z in 0..size {
+        for x in 0..size {
+            let a = z * (size + 1) + x + 1;
+            let (b, c, d) = (a + 1, a + size + 2, a + size + 1);
+            writeln!(obj, "f {a}/{a}/1 {b}/{b}/1 {c}/{c}/1").unwrap();
+            writeln!(obj, "f {a}/{a}/1 {c}/{c}/1 {d}/{d}/1").unwrap();
+        }
+    }
+    let directory = std::env::temp_dir();
+    let mtl = "newmtl Grid\nKd 0.8 0.8 0.8\n";
+    std::fs::write(directory.join("rusticvision_grid.mtl"), mtl).unwrap();
+    let path = directory.join(format!("rusticvision_grid_{}.obj", size));
+    std::fs::write(&path, obj).unwrap();
+    path
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let files = [
+        ("monkey", PathBuf::from("test/monkey.obj")),
+        ("grid", write_grid_obj(500)),
+    ];
+
+    let mut group = c.benchmark_group("obj loading");
+    for (name, path) in &files {
+        // The material files are next to the obj files.
+        let resolver = FileSystemResolver::new(path.parent().unwrap());
+
+        // All loaders load the same number of triangles.
+        let (_, baseline_objects) = baseline::load_obj(path);
+        let input = std::fs::read_to_string(path).unwrap();
+        let (_, _, objects) = parse_obj(&input, &resolver).unwrap();
+        let triangle_count = |objects: &HashMap<String, Vec<TriangleIndex>>| {
+            objects.values().map(Vec::len).sum::<usize>()
+        };
+        assert_eq!(triangle_count(&baseline_objects), triangle_count(&objects));
+
+        // The original two pass loader that the single pass loader replaced.
+        group.bench_with_input(BenchmarkId::new("two pass", name), path, |b, path| {
+            b.iter(|| baseline::load_obj(path))
+        });
+        // Read the whole file into memory and parse it.
+        group.bench_with_input(BenchmarkId::new("read to string", name), path, |b, path| {
+            b.iter(|| {
+                let input = std::fs::read_to_string(path).unwrap();
+                parse_obj(&input, &resolver).unwrap()
+            })
+        });
+        // Parse the file line by line while reading it.
+        group.bench_with_input(BenchmarkId::new("streaming", name), path, |b, path| {
+            b.iter(|| read_obj(BufReader::new(File::open(path).unwrap()), &resolver).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = criterion_benchmark
+);
+criterion_main!(benches);
@@ -0,0 +1,160 @@
+use std::fmt;
+
+/// Kind of problem found while loading a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The file could not be read.
+    Io,
+    /// A line is not of the expected format.
+    Syntax,
+    /// A material is used that is not defined in any material file.
+    UnknownMaterial,
+    /// An index refers to data that does not exist.
+    InvalidIndex,
+    /// The line is valid, but describes something that is not supported.
+    Unsupported,
+    /// An image that is referred to can be read, but not decoded.
+    InvalidImage,
+}
+
+/// Error returned when a file (obj, mtl, ...) can't be loaded. Contains the
+/// location of the problem and a description of it.
+///
+/// Lines and columns start at 1. Errors that concern the whole file, for
+/// example when the file can't be read, have line and column 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    file: String,
+    line: usize,
+    column: usize,
+    kind: ParseErrorKind,
+    description: String,
+}
+
+impl ParseError {
+    /// Creates a new error at the given line and column. The file is left
+    /// empty, use `in_file` to set it.
+    pub fn new(
+        kind: ParseErrorKind,
+        line: usize,
+        column: usize,
+        description: impl Into<String>,
+    ) -> Self {
+        Self {
+            file: String::new(),
+            line,
+            column,
+            kind,
+            description: description.into(),
+        }
+    }
+
+    /// Creates an error for a file that could not be read.
+    pub fn io(file: &str, error: std::io::Error) -> Self {
+        Self::new(ParseErrorKind::Io, 0, 0, error.to_string()).in_file(file)
+    }
+
+    /// Sets the file of the error, unless it is already set. Errors from
+    /// files included by another file keep the name of the included file.
+    pub fn in_file(mut self, file: &str) -> Self {
+        if self.file.is_empty() {
+            self.file = file.to_string();
+        }
+        self
+    }
+
+    /// Get the file the error occurred in. Empty if the parsed input did not
+    /// come from a file.
+    #[inline]
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Get the line of the error, starting at 1.
+    #[inline]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Get the column of the error, starting at 1.
+    #[inline]
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Get the kind of the error.
+    #[inline]
+    pub fn kind(&self) -> ParseErrorKind {
+        self.kind
+    }
+
+    /// Get the description of the error.
+    #[inline]
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file = if self.file.is_empty() {
+            "<input>"
+        } else {
+            &self.file
+        };
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            file, self.line, self.column, self.description
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Column (starting at 1) of the start of `part` in `line`, where `part` is a
+/// slice of `line`.
+#[inline]
+pub(crate) fn column(line: &str, part: &str) -> usize {
+    part.as_ptr() as usize - line.as_ptr() as usize + 1
+}
+
+/// Creates a syntax error from a nom error that occurred while parsing `line`,
+/// or a slice of it.
+pub(crate) fn syntax_error(
+    line: &str,
+    line_number: usize,
+    error: nom::Err<nom::error::Error<&str>>,
+    description: &str,
+) -> ParseError {
+    let remaining = match &error {
+        nom::Err::Error(e) | nom::Err::Failure(e) => e.input,
+        nom::Err::Incomplete(_) => &line[line.len()..],
+    };
+    ParseError::new(
+        ParseErrorKind::Syntax,
+        line_number,
+        column(line, remaining),
+        description,
+    )
+}
+
+/// Returns an error if `remaining`, the rest of `line` after parsing it, is
+/// anything other than whitespace or a comment.
+pub(crate) fn expect_end_of_line(
+    line: &str,
+    line_number: usize,
+    remaining: &str,
+) -> Result<(), ParseError> {
+    let trimmed = remaining.trim_start();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        Ok(())
+    } else {
+        Err(ParseError::new(
+            ParseErrorKind::Syntax,
+            line_number,
+            column(line, trimmed),
+            format!("Unexpected '{}'", trimmed),
+        ))
+    }
+}
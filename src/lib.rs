@@ -1,5 +1,13 @@
-mod scene;
-mod primitives;
-mod material;
-mod traits;
-pub mod prelude;
\ No newline at end of file
+pub mod scene;
+pub mod primitives;
+pub mod material;
+pub mod error;
+pub mod traits;
+pub mod prelude;
+pub mod resolver;
+
+#[cfg(test)]
+mod resolver_tests;
+
+#[cfg(test)]
+mod test_utils;
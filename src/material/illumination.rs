@@ -0,0 +1,204 @@
+use std::f32::consts::PI;
+
+use glam::Vec3A;
+use rand::Rng;
+
+use crate::primitives::{Hit, Ray};
+
+use super::Material;
+
+/// Shading behavior of a material, selected by the illumination model
+/// (`illum`) of the MTL file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IlluminationModel {
+    /// The surface has the diffuse color, without any lighting (`illum 0`).
+    Constant,
+    /// Diffuse reflection (`illum 1` and `illum 10`).
+    Diffuse,
+    /// Diffuse reflection with Blinn-Phong specular highlights (`illum 2`).
+    BlinnPhong,
+    /// Diffuse reflection with mirror reflection, tinted by the specular
+    /// color (`illum 3`, `5` and `8`).
+    Mirror,
+    /// Glass that reflects and refracts light according to the Fresnel
+    /// equations (`illum 4`, `6`, `7` and `9`).
+    Glass,
+}
+
+impl IlluminationModel {
+    /// Get the shading behavior of an `illum` number. Unknown numbers are
+    /// shaded as diffuse surfaces.
+    pub fn from_illum(illum: u32) -> Self {
+        match illum {
+            0 => Self::Constant,
+            2 => Self::BlinnPhong,
+            3 | 5 | 8 => Self::Mirror,
+            4 | 6 | 7 | 9 => Self::Glass,
+            _ => Self::Diffuse,
+        }
+    }
+}
+
+/// Scatter a ray diffusely, with the diffuse color as weight.
+pub(crate) fn scatter_diffuse<R: Rng + ?Sized>(
+    material: &Material,
+    hit: &Hit,
+    rng: &mut R,
+) -> (Ray, Vec3A) {
+    let color = material.diffuse_color_at_hit(hit);
+    (hit.random_outgoing_ray_with(rng), color)
+}
+
+/// Scatter a ray either diffusely or around the Blinn-Phong highlight, see
+/// `choose_lobe`.
+pub(crate) fn scatter_blinn_phong<R: Rng + ?Sized>(
+    material: &Material,
+    hit: &Hit,
+    rng: &mut R,
+) -> Option<(Ray, Vec3A)> {
+    let specular = match choose_lobe(material, hit, rng)? {
+        Lobe::Diffuse(weight) => return Some((hit.random_outgoing_ray_with(rng), weight)),
+        Lobe::Specular(weight) => weight,
+    };
+
+    // Sample the half vector proportional to the Blinn-Phong lobe
+    // (n . h)^Ns, and reflect the incoming ray on it.
+    let normal = facing_normal(hit);
+    let exponent = material.specular_highlight.max(0.0);
+    let cos_theta = rng.gen::<f32>().powf(1.0 / (exponent + 1.0));
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let (sin_phi, cos_phi) = (2.0 * PI * rng.gen::<f32>()).sin_cos();
+    let (right, forward) = normal.any_orthonormal_pair();
+    let half = right * (sin_theta * cos_phi) + forward * (sin_theta * sin_phi) + normal * cos_theta;
+    let direction = reflect(hit.incoming.direction.normalize(), half);
+    if direction.dot(normal) <= 0.0 {
+        return None;
+    }
+    Some((hit.spawn_ray(direction), specular))
+}
+
+/// Scatter a ray either diffusely or in the mirror direction, tinted by the
+/// specular color, see `choose_lobe`.
+pub(crate) fn scatter_mirror<R: Rng + ?Sized>(
+    material: &Material,
+    hit: &Hit,
+    rng: &mut R,
+) -> Option<(Ray, Vec3A)> {
+    match choose_lobe(material, hit, rng)? {
+        Lobe::Diffuse(weight) => Some((hit.random_outgoing_ray_with(rng), weight)),
+        Lobe::Specular(weight) => {
+            let direction = reflect(hit.incoming.direction.normalize(), facing_normal(hit));
+            Some((hit.spawn_ray(direction), weight))
+        }
+    }
+}
+
+/// The part of a material a ray is scattered by, with the weight of the
+/// scattered ray.
+enum Lobe {
+    Diffuse(Vec3A),
+    Specular(Vec3A),
+}
+
+/// Choose at random between the diffuse and the specular part of a material,
+/// by the brightness of their colors. Only light that is not reflected
+/// specularly is reflected diffusely, so the diffuse color is weighted by
+/// `1 - Ks`. This way the material never reflects more light than it
+/// receives, even if `Kd + Ks` is greater than 1.
+///
+/// ### Returns
+/// The chosen part, with its color divided by the probability of choosing it
+/// as weight, or `None` if both colors are black.
+fn choose_lobe<R: Rng + ?Sized>(material: &Material, hit: &Hit, rng: &mut R) -> Option<Lobe> {
+    let specular = material.specular_color_at(hit.tex_coord());
+    let diffuse = material.diffuse_color_at_hit(hit) * (Vec3A::ONE - specular).max(Vec3A::ZERO);
+    let total = diffuse.dot(Vec3A::ONE) + specular.dot(Vec3A::ONE);
+    if total <= 0.0 {
+        return None;
+    }
+    let specular_probability = specular.dot(Vec3A::ONE) / total;
+    if rng.gen::<f32>() >= specular_probability {
+        Some(Lobe::Diffuse(diffuse / (1.0 - specular_probability)))
+    } else {
+        Some(Lobe::Specular(specular / specular_probability))
+    }
+}
+
+/// Reflect or refract the ray at the surface of a glass material. The ray is
+/// reflected with a probability equal to the Fresnel reflectance, and
+/// refracted otherwise. Rays that can't be refracted are reflected (total
+/// internal reflection).
+pub(crate) fn scatter_glass<R: Rng + ?Sized>(
+    material: &Material,
+    hit: &Hit,
+    rng: &mut R,
+) -> (Ray, Vec3A) {
+    let incoming = hit.incoming.direction.normalize();
+    // Rays leaving the material go from the index of refraction of the
+    // material to air.
+    let entering = incoming.dot(hit.normal) < 0.0;
+    let (normal, eta) = if entering {
+        (hit.normal, 1.0 / material.index_of_refraction)
+    } else {
+        (-hit.normal, material.index_of_refraction)
+    };
+
+    let cos_incoming = -incoming.dot(normal);
+    let direction = match refract(incoming, normal, eta) {
+        Some(refracted) if rng.gen::<f32>() >= fresnel(cos_incoming, eta) => refracted,
+        _ => reflect(incoming, normal),
+    };
+    (hit.spawn_ray(direction), Vec3A::ONE)
+}
+
+/// Get the shading normal of the hit, flipped towards the incoming ray.
+#[inline]
+fn facing_normal(hit: &Hit) -> Vec3A {
+    if hit.normal.dot(hit.incoming.direction) > 0.0 {
+        -hit.normal
+    } else {
+        hit.normal
+    }
+}
+
+/// Reflect `direction` on a surface with the given normal.
+#[inline]
+fn reflect(direction: Vec3A, normal: Vec3A) -> Vec3A {
+    direction - 2.0 * direction.dot(normal) * normal
+}
+
+/// Refract `direction` through a surface with the given normal, facing
+/// against the direction. `eta` is the ratio of the indices of refraction on
+/// the incoming and outgoing side.
+///
+/// ### Returns
+/// The refracted direction, or `None` for total internal reflection.
+#[inline]
+fn refract(direction: Vec3A, normal: Vec3A, eta: f32) -> Option<Vec3A> {
+    let cos_incoming = -direction.dot(normal);
+    let sin2_outgoing = eta * eta * (1.0 - cos_incoming * cos_incoming);
+    if sin2_outgoing > 1.0 {
+        return None;
+    }
+    let cos_outgoing = (1.0 - sin2_outgoing).sqrt();
+    Some((eta * direction + (eta * cos_incoming - cos_outgoing) * normal).normalize())
+}
+
+/// Fresnel reflectance of a dielectric for unpolarized light, using Schlick's
+/// approximation.
+#[inline]
+fn fresnel(cos_incoming: f32, eta: f32) -> f32 {
+    let r0 = ((1.0 - eta) / (1.0 + eta)).powi(2);
+    // When leaving the denser material, the approximation uses the angle of
+    // the refracted ray.
+    let cos = if eta > 1.0 {
+        let sin2_outgoing = eta * eta * (1.0 - cos_incoming * cos_incoming);
+        if sin2_outgoing > 1.0 {
+            return 1.0;
+        }
+        (1.0 - sin2_outgoing).sqrt()
+    } else {
+        cos_incoming
+    };
+    r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+}
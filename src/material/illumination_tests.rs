@@ -0,0 +1,119 @@
+use super::Material;
+
+use crate::{
+    primitives::{Hit, Ray},
+    test_utils::test_rng,
+};
+
+use glam::Vec3A;
+
+fn material(illumination_model: u32) -> Material {
+    Material {
+        specular_color: Vec3A::new(0.9, 0.8, 0.7),
+        index_of_refraction: 1.5,
+        illumination_model,
+        ..Material::default()
+    }
+}
+
+/// Hit on the plane y = 0, with the normal pointing up.
+fn hit(direction: Vec3A) -> Hit {
+    let direction = direction.normalize();
+    Hit::from_surface(
+        Vec3A::ZERO,
+        1.0,
+        Ray::new(-direction, direction),
+        Vec3A::Y,
+        Default::default(),
+    )
+}
+
+#[test]
+fn constant_test() {
+    assert!(material(0).scatter(&hit(-Vec3A::Y)).is_none());
+}
+
+#[test]
+fn mirror_test() {
+    let (ray, weight) = material(3)
+        .scatter(&hit(Vec3A::new(1.0, -1.0, 0.0)))
+        .unwrap();
+    assert!((ray.direction - Vec3A::new(1.0, 1.0, 0.0).normalize()).length() < 1e-5);
+    assert_eq!(weight, material(3).specular_color);
+}
+
+/// Mirror materials keep the diffuse reflection of their diffuse color.
+#[test]
+fn mirror_diffuse_test() {
+    let mirror = Material {
+        diffuse_color: Vec3A::splat(0.5),
+        specular_color: Vec3A::splat(0.5),
+        illumination_model: 3,
+        ..Material::default()
+    };
+    let direction = Vec3A::new(1.0, -1.0, 0.0);
+    let reflected = Vec3A::new(1.0, 1.0, 0.0).normalize();
+    let mut rng = test_rng();
+    let rays: Vec<_> = (0..100)
+        .map(|_| mirror.scatter_with(&hit(direction), &mut rng).unwrap().0)
+        .collect();
+    let mirrored = rays
+        .iter()
+        .filter(|ray| (ray.direction - reflected).length() < 1e-5)
+        .count();
+    assert!(mirrored > 25 && mirrored < 75, "{} mirrored rays", mirrored);
+}
+
+/// A Blinn-Phong material doesn't reflect more light than it receives, even
+/// if its diffuse and specular colors add up to more than 1.
+#[test]
+fn blinn_phong_energy_test() {
+    let material = Material {
+        diffuse_color: Vec3A::ONE,
+        specular_color: Vec3A::splat(0.5),
+        specular_highlight: 10.0,
+        illumination_model: 2,
+        ..Material::default()
+    };
+    let hit = hit(Vec3A::new(1.0, -2.0, 0.0));
+    let mut rng = test_rng();
+    let samples = 10000;
+    let throughput = (0..samples)
+        .filter_map(|_| material.scatter_with(&hit, &mut rng))
+        .map(|(_, weight)| weight)
+        .sum::<Vec3A>()
+        / samples as f32;
+    assert!(throughput.max_element() <= 1.0 + 1e-4, "{}", throughput);
+    assert!(throughput.min_element() > 0.9, "{}", throughput);
+}
+
+#[test]
+fn glass_test() {
+    let glass = material(7);
+    let mut rng = test_rng();
+
+    // Most light passes straight through at normal incidence.
+    let transmitted = (0..1000)
+        .map(|_| glass.scatter_with(&hit(-Vec3A::Y), &mut rng).unwrap().0)
+        .filter(|ray| ray.direction.y < 0.0)
+        .count();
+    assert!(transmitted > 900);
+
+    // Refracted towards the normal when entering the glass.
+    let direction = Vec3A::new(1.0, -1.0, 0.0).normalize();
+    for _ in 0..100 {
+        let (ray, weight) = glass.scatter_with(&hit(direction), &mut rng).unwrap();
+        assert_eq!(weight, Vec3A::ONE);
+        if ray.direction.y < 0.0 {
+            let sin_outgoing = ray.direction.x;
+            assert!((sin_outgoing * 1.5 - direction.x).abs() < 1e-4);
+        }
+    }
+
+    // Total internal reflection at grazing angles inside the glass.
+    let direction = Vec3A::new(1.0, 0.3, 0.0);
+    for _ in 0..100 {
+        let (ray, _) = glass.scatter_with(&hit(direction), &mut rng).unwrap();
+        assert!(ray.direction.y < 0.0);
+    }
+}
@@ -1,19 +1,54 @@
+pub mod illumination;
 pub mod parser;
+pub mod pbr;
+pub mod texture;
+pub mod writer;
 
-use glam::Vec3A;
+pub use illumination::IlluminationModel;
+pub use pbr::PhysicalProperties;
+pub use texture::{Texture, TextureMap};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+use crate::primitives::{Hit, Ray};
+
+use glam::{Vec2, Vec3A};
+use rand::Rng;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Material {
     pub ambient_color: Vec3A,
     pub diffuse_color: Vec3A,
     pub specular_color: Vec3A,
     pub specular_highlight: f32,
     pub emissive_color: Vec3A,
+    /// Dissolve of the material (`d`, or `1 - Tr`), the fraction of the light
+    /// that does not pass through the surface. 1 is opaque and 0 fully
+    /// transparent.
     pub transparency: f32,
     pub index_of_refraction: f32,
+    /// Illumination model (`illum`) number, see `illumination`.
+    pub illumination_model: u32,
+    /// Texture multiplied with the diffuse color (`map_Kd`).
+    pub diffuse_map: Option<TextureMap>,
+    /// Texture multiplied with the specular color (`map_Ks`).
+    pub specular_map: Option<TextureMap>,
+    /// Texture multiplied with the emissive color (`map_Ke`).
+    pub emissive_map: Option<TextureMap>,
+    /// Texture multiplied with the transparency (`map_d`).
+    pub transparency_map: Option<TextureMap>,
+    /// Bump map used to perturb the shading normal (`map_Bump`).
+    pub bump_map: Option<TextureMap>,
+    /// Tangent space normal map (`norm`).
+    pub normal_map: Option<TextureMap>,
+    /// Parameters of the physically based rendering extension, `None` if the
+    /// material has none of them.
+    pub physical: Option<PhysicalProperties>,
 }
 
 impl Material {
+    /// Creates a material without texture maps or physically based
+    /// properties, lit by the diffuse illumination model (`illum 1`).
+    /// `transparency` is the dissolve, 1 for opaque materials, see the field
+    /// of the same name.
     pub fn new(
         ambient_color: Vec3A,
         diffuse_color: Vec3A,
@@ -31,11 +66,145 @@ impl Material {
             emissive_color,
             transparency,
             index_of_refraction,
+            illumination_model: 1,
+            diffuse_map: None,
+            specular_map: None,
+            emissive_map: None,
+            transparency_map: None,
+            bump_map: None,
+            normal_map: None,
+            physical: None,
+        }
+    }
+
+    /// Get the shading behavior of the material, from its illumination model.
+    #[inline]
+    pub fn illumination(&self) -> IlluminationModel {
+        IlluminationModel::from_illum(self.illumination_model)
+    }
+
+    /// Sample the direction a ray arriving at the hit is scattered in, using
+    /// the illumination model of the material. Materials with physically
+    /// based properties are shaded with those instead of the diffuse and
+    /// Blinn-Phong models.
+    ///
+    /// ### Returns
+    /// The scattered ray and the fraction of the light along it that is
+    /// reflected towards the incoming ray, or `None` if the ray is absorbed
+    /// or the material is not lit (`IlluminationModel::Constant`).
+    pub fn scatter(&self, hit: &Hit) -> Option<(Ray, Vec3A)> {
+        self.scatter_with(hit, &mut rand::thread_rng())
+    }
+
+    /// Sample the direction a ray arriving at the hit is scattered in, using
+    /// the random numbers of `rng`. See `scatter`.
+    pub(crate) fn scatter_with<R: Rng + ?Sized>(
+        &self,
+        hit: &Hit,
+        rng: &mut R,
+    ) -> Option<(Ray, Vec3A)> {
+        match (self.illumination(), &self.physical) {
+            (IlluminationModel::Constant, _) => None,
+            (IlluminationModel::Diffuse | IlluminationModel::BlinnPhong, Some(physical)) => {
+                physical.scatter_with(self, hit, rng)
+            }
+            (IlluminationModel::Diffuse, None) => {
+                Some(illumination::scatter_diffuse(self, hit, rng))
+            }
+            (IlluminationModel::BlinnPhong, None) => {
+                illumination::scatter_blinn_phong(self, hit, rng)
+            }
+            (IlluminationModel::Mirror, _) => illumination::scatter_mirror(self, hit, rng),
+            (IlluminationModel::Glass, _) => Some(illumination::scatter_glass(self, hit, rng)),
+        }
+    }
+
+    /// Get the diffuse color at the texture coordinates of a surface, ie the
+    /// diffuse color multiplied with the diffuse map.
+    #[inline]
+    pub fn diffuse_color_at(&self, tex_coord: Option<Vec2>) -> Vec3A {
+        Self::textured_color(self.diffuse_color, &self.diffuse_map, tex_coord)
+    }
+
+    /// Get the diffuse color at a hit, ie the diffuse color multiplied with
+    /// the diffuse map and the vertex color of the surface.
+    #[inline]
+    pub fn diffuse_color_at_hit(&self, hit: &Hit) -> Vec3A {
+        let color = self.diffuse_color_at(hit.tex_coord());
+        match hit.color {
+            Some(vertex_color) => color * vertex_color,
+            None => color,
+        }
+    }
+
+    /// Get the fraction of the light that is reflected diffusely at a hit.
+    /// Metals, mirrors, glass and unlit materials reflect no light
+    /// diffusely.
+    pub fn diffuse_reflectance_at_hit(&self, hit: &Hit) -> Vec3A {
+        match (self.illumination(), &self.physical) {
+            (IlluminationModel::Diffuse | IlluminationModel::BlinnPhong, Some(physical)) => {
+                self.diffuse_color_at_hit(hit) * (1.0 - physical.metallic.clamp(0.0, 1.0))
+            }
+            (IlluminationModel::Diffuse | IlluminationModel::BlinnPhong, None) => {
+                self.diffuse_color_at_hit(hit)
+            }
+            _ => Vec3A::ZERO,
+        }
+    }
+
+    /// Get the specular color at the texture coordinates of a surface.
+    #[inline]
+    pub fn specular_color_at(&self, tex_coord: Option<Vec2>) -> Vec3A {
+        Self::textured_color(self.specular_color, &self.specular_map, tex_coord)
+    }
+
+    /// Get the emissive color at the texture coordinates of a surface.
+    #[inline]
+    pub fn emissive_color_at(&self, tex_coord: Option<Vec2>) -> Vec3A {
+        Self::textured_color(self.emissive_color, &self.emissive_map, tex_coord)
+    }
+
+    /// Get the transparency (dissolve, where 1 is opaque) at the texture
+    /// coordinates of a surface. The red channel of the transparency map is
+    /// used.
+    #[inline]
+    pub fn transparency_at(&self, tex_coord: Option<Vec2>) -> f32 {
+        match (&self.transparency_map, tex_coord) {
+            (Some(map), Some(tex_coord)) => self.transparency * map.sample(tex_coord).x,
+            _ => self.transparency,
+        }
+    }
+
+    /// Get mutable references to all texture maps of the material, for
+    /// example to load their textures.
+    pub fn texture_maps_mut(&mut self) -> impl Iterator<Item = &mut TextureMap> {
+        [
+            &mut self.diffuse_map,
+            &mut self.specular_map,
+            &mut self.emissive_map,
+            &mut self.transparency_map,
+            &mut self.bump_map,
+            &mut self.normal_map,
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// Multiply a color with the texture map, if there is a map and texture
+    /// coordinates.
+    #[inline]
+    fn textured_color(color: Vec3A, map: &Option<TextureMap>, tex_coord: Option<Vec2>) -> Vec3A {
+        match (map, tex_coord) {
+            (Some(map), Some(tex_coord)) => color * Vec3A::from(map.sample(tex_coord).truncate()),
+            _ => color,
         }
     }
 }
 
 impl Default for Material {
+    /// A black, opaque material. The transparency is the dissolve, so it is
+    /// 1, and materials that set neither `d` nor `Tr` are opaque. Before the
+    /// transparency was used for shading it defaulted to 0.
     fn default() -> Self {
         Self::new(
             Vec3A::new(0.0, 0.0, 0.0),
@@ -43,7 +212,7 @@ impl Default for Material {
             Vec3A::new(0.0, 0.0, 0.0),
             0.0,
             Vec3A::new(0.0, 0.0, 0.0),
-            0.0,
+            1.0,
             1.0,
         )
     }
@@ -51,3 +220,12 @@ impl Default for Material {
 
 #[cfg(test)]
 mod parser_tests;
+
+#[cfg(test)]
+mod texture_tests;
+
+#[cfg(test)]
+mod pbr_tests;
+
+#[cfg(test)]
+mod illumination_tests;
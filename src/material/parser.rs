@@ -3,16 +3,20 @@ use std::collections::HashMap;
 use nom::{
     self,
     branch::alt,
-    bytes::complete::{tag, take_till},
-    character::complete::{digit0, line_ending, space1},
-    multi::separated_list1,
+    bytes::complete::{tag, take_till1},
+    character::complete::{self, alpha1, char, one_of, space0, space1},
+    combinator::{cut, eof, opt, peek, value},
+    multi::many0,
     number::complete::float,
+    sequence::{preceded, terminated, tuple},
     IResult,
 };
 
 use glam::Vec3A;
 
-use super::Material;
+use crate::error::{column, expect_end_of_line, syntax_error, ParseError, ParseErrorKind};
+
+use super::{Material, PhysicalProperties, TextureMap};
 
 enum MaterialProperty {
     AmbientColor(Vec3A),
@@ -23,143 +27,453 @@ enum MaterialProperty {
     Transparency(f32),
     IndexOfRefraction(f32),
     IlluminationModel(u32),
+    DiffuseMap(TextureMap),
+    SpecularMap(TextureMap),
+    EmissiveMap(TextureMap),
+    TransparencyMap(TextureMap),
+    BumpMap(TextureMap),
+    NormalMap(TextureMap),
+    Roughness(f32),
+    Metallic(f32),
+    Sheen(Vec3A),
+    ClearcoatThickness(f32),
+    ClearcoatRoughness(f32),
+    Anisotropy(f32),
+    AnisotropyRotation(f32),
+}
+
+/// Option of a texture map statement.
+#[derive(Clone)]
+enum TextureOption {
+    Scale(Vec3A),
+    Offset(Vec3A),
+    BumpMultiplier(f32),
+    Clamp(bool),
+    /// Option that is valid, but not supported.
+    Ignored,
 }
 
+type PropertyParser = fn(&str) -> IResult<&str, MaterialProperty>;
+
 /// Parses multiple materials from string slice using [MTL file
-/// format](https://en.wikipedia.org/wiki/Wavefront_.obj_file).
+/// format](https://en.wikipedia.org/wiki/Wavefront_.obj_file). Statements that
+/// are not supported are skipped.
 ///
 /// ### Arguments
 /// * `input` - The string slice to parse. This should be the contents of a MTL
-/// file.
+///   file.
 ///
 /// ### Returns
 /// If successful, returns a tuple containing a vector of materials and a
 /// hashmap mapping material names to their indices in the vector. The map can
-/// be used to look up a material index by name.
-pub fn materials(input: &str) -> IResult<&str, (Vec<Material>, HashMap<String, usize>)> {
+/// be used to look up a material index by name. Otherwise an error with the
+/// line and column of the first invalid line.
+pub fn materials(input: &str) -> Result<(Vec<Material>, HashMap<String, usize>), ParseError> {
     let mut materials = Vec::new();
     let mut material_index_map = HashMap::new();
-    let mut input = input;
-    loop {
-        if input.starts_with("newmtl ") {
-            let (remaining, (name, matprop)) = parse_material(input)?;
+    for (i, line) in input.lines().enumerate() {
+        let line_number = i + 1;
+        let keyword = match line.split_whitespace().next() {
+            Some(keyword) if !keyword.starts_with('#') => keyword,
+            _ => continue,
+        };
+
+        if keyword == "newmtl" {
+            let name = parse_material_name(line, line_number)?;
             material_index_map.insert(name.to_string(), materials.len());
-            materials.push(material_from_properties(&matprop));
-            input = remaining;
+            materials.push(Material::default());
+            continue;
         }
-        if input.is_empty() {
-            break;
+
+        let Some(property) = parse_material_property(line, line_number, keyword)? else {
+            continue;
+        };
+        match materials.last_mut() {
+            Some(material) => set_property(material, property),
+            None => {
+                return Err(ParseError::new(
+                    ParseErrorKind::Syntax,
+                    line_number,
+                    column(line, keyword),
+                    format!(
+                        "Property '{}' outside of material (missing `newmtl`)",
+                        keyword
+                    ),
+                ))
+            }
         }
-        let (remaining, _) = take_till(|c| c == '\n')(input)?;
-        let (remaining, _) = line_ending(remaining)?;
-        input = remaining;
     }
-    Ok((input, (materials, material_index_map)))
+    Ok((materials, material_index_map))
+}
+
+/// Parse a material property line of an mtl file, starting with `keyword`,
+/// and set the property of `material`. Properties that are not supported are
+/// skipped, like in `materials`.
+pub(crate) fn parse_property(
+    material: &mut Material,
+    line: &str,
+    line_number: usize,
+    keyword: &str,
+) -> Result<(), ParseError> {
+    if let Some(property) = parse_material_property(line, line_number, keyword)? {
+        set_property(material, property);
+    }
+    Ok(())
 }
 
-/// Constructs a material from a list of material properties. Missing properties
-/// are set to their default values.
-fn material_from_properties(properties: &Vec<MaterialProperty>) -> Material {
+/// Find the texture map statement of the image file `path` in the contents of
+/// an mtl file, for example to report that the image can't be loaded.
+///
+/// ### Returns
+/// The line and column of the path in the first statement with the path, or
+/// `(0, 0)` if there is none.
+pub(crate) fn find_texture_map(input: &str, path: &str) -> (usize, usize) {
     use MaterialProperty::*;
-    let mut material = Material::default();
-    for prop in properties {
-        match &prop {
-            AmbientColor(c) => material.ambient_color = *c,
-            DiffuseColor(c) => material.diffuse_color = *c,
-            SpecularColor(c) => material.specular_color = *c,
-            EmissiveColor(c) => material.emissive_color = *c,
-            SpecularHighlight(h) => material.specular_highlight = *h,
-            Transparency(t) => material.transparency = *t,
-            IndexOfRefraction(i) => material.index_of_refraction = *i,
-            IlluminationModel(_m) => (),
+    for (i, line) in input.lines().enumerate() {
+        let Some(keyword) = line.split_whitespace().next() else {
+            continue;
+        };
+        if let Ok(Some(
+            DiffuseMap(map) | SpecularMap(map) | EmissiveMap(map) | TransparencyMap(map)
+            | BumpMap(map) | NormalMap(map),
+        )) = parse_material_property(line, i + 1, keyword)
+        {
+            if map.path == path {
+                let start = line.rfind(path).unwrap_or_default();
+                return (i + 1, column(line, &line[start..]));
+            }
         }
     }
-    material
+    (0, 0)
+}
+
+/// Sets a property of the material.
+fn set_property(material: &mut Material, property: MaterialProperty) {
+    use MaterialProperty::*;
+    match property {
+        AmbientColor(c) => material.ambient_color = c,
+        DiffuseColor(c) => material.diffuse_color = c,
+        SpecularColor(c) => material.specular_color = c,
+        EmissiveColor(c) => material.emissive_color = c,
+        SpecularHighlight(h) => material.specular_highlight = h,
+        Transparency(t) => material.transparency = t,
+        IndexOfRefraction(i) => material.index_of_refraction = i,
+        IlluminationModel(m) => material.illumination_model = m,
+        DiffuseMap(m) => material.diffuse_map = Some(m),
+        SpecularMap(m) => material.specular_map = Some(m),
+        EmissiveMap(m) => material.emissive_map = Some(m),
+        TransparencyMap(m) => material.transparency_map = Some(m),
+        BumpMap(m) => material.bump_map = Some(m),
+        NormalMap(m) => material.normal_map = Some(m),
+        Roughness(r) => physical(material).roughness = r,
+        Metallic(m) => physical(material).metallic = m,
+        Sheen(s) => physical(material).sheen = s,
+        ClearcoatThickness(t) => physical(material).clearcoat_thickness = t,
+        ClearcoatRoughness(r) => physical(material).clearcoat_roughness = r,
+        Anisotropy(a) => physical(material).anisotropy = a,
+        AnisotropyRotation(r) => physical(material).anisotropy_rotation = r,
+    }
+}
+
+/// Get the physically based properties of the material, which are added with
+/// default values if the material has none yet.
+fn physical(material: &mut Material) -> &mut PhysicalProperties {
+    material.physical.get_or_insert_with(Default::default)
+}
+
+/// Parse the material name of a line of the form `newmtl <material_name>`.
+fn parse_material_name(line: &str, line_number: usize) -> Result<&str, ParseError> {
+    let (name, _) = preceded(space0, terminated(tag("newmtl"), space1))(line)
+        .map_err(|e| syntax_error(line, line_number, e, "Expected `newmtl <material_name>`"))?;
+    let name = name.trim_end();
+    if name.is_empty() {
+        return Err(ParseError::new(
+            ParseErrorKind::Syntax,
+            line_number,
+            column(line, name),
+            "Expected material name",
+        ));
+    }
+    Ok(name)
 }
 
-fn parse_material(input: &str) -> IResult<&str, (&str, Vec<MaterialProperty>)> {
-    let (input, material_name) = parse_material_name(input)?;
-    let (input, _) = line_ending(input)?;
-    let (input, material_properties) = separated_list1(line_ending, parse_material_property)(input)?;
-    Ok((input, (material_name, material_properties)))
+/// Parse a material property line, starting with `keyword`.
+///
+/// ### Returns
+/// The property, `None` if the property is not supported, or an error if the
+/// property is supported but its value is invalid.
+fn parse_material_property(
+    line: &str,
+    line_number: usize,
+    keyword: &str,
+) -> Result<Option<MaterialProperty>, ParseError> {
+    let (parser, format): (PropertyParser, _) = match keyword {
+        "Ka" => (parse_ambient_color, "`Ka r g b`"),
+        "Kd" => (parse_diffuse_color, "`Kd r g b`"),
+        "Ks" => (parse_specular_color, "`Ks r g b`"),
+        "Ke" => (parse_emissive_color, "`Ke r g b`"),
+        "Ns" => (parse_specular_highlight, "`Ns exponent`"),
+        "d" => (parse_transparency, "`d factor`"),
+        "Tr" => (parse_transmission, "`Tr factor`"),
+        "Ni" => (parse_index_of_refraction, "`Ni index`"),
+        "illum" => (parse_illumination_model, "`illum model`"),
+        "map_Kd" => (parse_diffuse_map, "`map_Kd [options] file`"),
+        "map_Ks" => (parse_specular_map, "`map_Ks [options] file`"),
+        "map_Ke" => (parse_emissive_map, "`map_Ke [options] file`"),
+        "map_d" => (parse_transparency_map, "`map_d [options] file`"),
+        "map_Bump" | "map_bump" | "bump" => (parse_bump_map, "`map_Bump [options] file`"),
+        "norm" => (parse_normal_map, "`norm [options] file`"),
+        "Pr" => (parse_roughness, "`Pr roughness`"),
+        "Pm" => (parse_metallic, "`Pm metallic`"),
+        "Ps" => (parse_sheen, "`Ps r g b`"),
+        "Pc" => (parse_clearcoat_thickness, "`Pc thickness`"),
+        "Pcr" => (parse_clearcoat_roughness, "`Pcr roughness`"),
+        "aniso" => (parse_anisotropy, "`aniso anisotropy`"),
+        "anisor" => (parse_anisotropy_rotation, "`anisor rotation`"),
+        _ => return Ok(None),
+    };
+    let (remaining, property) = parser(line.trim_start()).map_err(|e| {
+        syntax_error(
+            line,
+            line_number,
+            e,
+            &format!("Expected property of the form {}", format),
+        )
+    })?;
+    expect_end_of_line(line, line_number, remaining)?;
+    Ok(Some(property))
 }
 
-/// Parse material name.
-fn parse_material_name(input: &str) -> IResult<&str, &str> {
-    let (input, _) = tag("newmtl ")(input)?;
-    let (input, name) = take_till(|c| c == '\n')(input)?;
-    Ok((input, name))
+/// Parse a color of the form `r g b`, or `r` for a gray color.
+fn color(input: &str) -> IResult<&str, Vec3A> {
+    let (input, r) = preceded(space1, float)(input)?;
+    let (input, gb) = opt(tuple((preceded(space1, float), preceded(space1, float))))(input)?;
+    let color = match gb {
+        Some((g, b)) => Vec3A::new(r, g, b),
+        None => Vec3A::splat(r),
+    };
+    Ok((input, color))
 }
 
 /// Pase ambient color property line.
 fn parse_ambient_color(input: &str) -> IResult<&str, MaterialProperty> {
-    let (input, _) = tag("Ka ")(input)?;
-    let (input, a) = separated_list1(space1, float)(input)?;
-    Ok((input, MaterialProperty::AmbientColor(Vec3A::from_slice(&a))))
+    let (input, _) = tag("Ka")(input)?;
+    let (input, c) = color(input)?;
+    Ok((input, MaterialProperty::AmbientColor(c)))
 }
 
 /// Parse diffuse color property.
 fn parse_diffuse_color(input: &str) -> IResult<&str, MaterialProperty> {
-    let (input, _) = tag("Kd ")(input)?;
-    let (input, a) = separated_list1(space1, float)(input)?;
-    Ok((input, MaterialProperty::DiffuseColor(Vec3A::from_slice(&a))))
+    let (input, _) = tag("Kd")(input)?;
+    let (input, c) = color(input)?;
+    Ok((input, MaterialProperty::DiffuseColor(c)))
 }
 
 /// Parse specular color property.
 fn parse_specular_color(input: &str) -> IResult<&str, MaterialProperty> {
-    let (input, _) = tag("Ks ")(input)?;
-    let (input, a) = separated_list1(space1, float)(input)?;
-    Ok((input, MaterialProperty::SpecularColor(Vec3A::from_slice(&a))))
+    let (input, _) = tag("Ks")(input)?;
+    let (input, c) = color(input)?;
+    Ok((input, MaterialProperty::SpecularColor(c)))
 }
 
 /// Parse specular highlight property.
 fn parse_specular_highlight(input: &str) -> IResult<&str, MaterialProperty> {
-    let (input, _) = tag("Ns ")(input)?;
-    let (input, a) = float(input)?;
+    let (input, _) = tag("Ns")(input)?;
+    let (input, a) = preceded(space1, float)(input)?;
     Ok((input, MaterialProperty::SpecularHighlight(a)))
 }
 
 /// Parse emissive color property.
 fn parse_emissive_color(input: &str) -> IResult<&str, MaterialProperty> {
-    let (input, _) = tag("Ke ")(input)?;
-    let (input, a) = separated_list1(space1, float)(input)?;
-    Ok((input, MaterialProperty::EmissiveColor(Vec3A::from_slice(&a))))
+    let (input, _) = tag("Ke")(input)?;
+    let (input, c) = color(input)?;
+    Ok((input, MaterialProperty::EmissiveColor(c)))
 }
 
 /// Parse transparency property.
 fn parse_transparency(input: &str) -> IResult<&str, MaterialProperty> {
-    let (input, _) = tag("d ")(input)?;
-    let (input, a) = float(input)?;
+    let (input, _) = tag("d")(input)?;
+    let (input, a) = preceded(space1, float)(input)?;
     Ok((input, MaterialProperty::Transparency(a)))
 }
 
+/// Parse transmission property, the inverse of the transparency (`d`).
+fn parse_transmission(input: &str) -> IResult<&str, MaterialProperty> {
+    let (input, _) = tag("Tr")(input)?;
+    let (input, a) = preceded(space1, float)(input)?;
+    Ok((input, MaterialProperty::Transparency(1.0 - a)))
+}
+
 /// Parse index of refraction property.
 fn parse_index_of_refraction(input: &str) -> IResult<&str, MaterialProperty> {
-    let (input, _) = tag("Ni ")(input)?;
-    let (input, a) = float(input)?;
+    let (input, _) = tag("Ni")(input)?;
+    let (input, a) = preceded(space1, float)(input)?;
     Ok((input, MaterialProperty::IndexOfRefraction(a)))
 }
 
 /// Parse illumination model property.
 fn parse_illumination_model(input: &str) -> IResult<&str, MaterialProperty> {
-    let (input, _) = tag("illum ")(input)?;
-    let (input, a) = digit0(input)?;
-    Ok((
-        input,
-        MaterialProperty::IlluminationModel(a.parse::<u32>().unwrap()),
-    ))
-}
-
-/// Parse any material property.
-fn parse_material_property(input: &str) -> IResult<&str, MaterialProperty> {
-    alt((
-        parse_ambient_color,
-        parse_diffuse_color,
-        parse_specular_color,
-        parse_specular_highlight,
-        parse_emissive_color,
-        parse_transparency,
-        parse_index_of_refraction,
-        parse_illumination_model,
-    ))(input)
+    let (input, _) = tag("illum")(input)?;
+    let (input, a) = preceded(space1, complete::u32)(input)?;
+    Ok((input, MaterialProperty::IlluminationModel(a)))
+}
+
+/// Parse diffuse texture map property.
+fn parse_diffuse_map(input: &str) -> IResult<&str, MaterialProperty> {
+    let (input, map) = preceded(tag("map_Kd"), texture_map)(input)?;
+    Ok((input, MaterialProperty::DiffuseMap(map)))
+}
+
+/// Parse specular texture map property.
+fn parse_specular_map(input: &str) -> IResult<&str, MaterialProperty> {
+    let (input, map) = preceded(tag("map_Ks"), texture_map)(input)?;
+    Ok((input, MaterialProperty::SpecularMap(map)))
+}
+
+/// Parse emissive texture map property.
+fn parse_emissive_map(input: &str) -> IResult<&str, MaterialProperty> {
+    let (input, map) = preceded(tag("map_Ke"), texture_map)(input)?;
+    Ok((input, MaterialProperty::EmissiveMap(map)))
+}
+
+/// Parse transparency texture map property.
+fn parse_transparency_map(input: &str) -> IResult<&str, MaterialProperty> {
+    let (input, map) = preceded(tag("map_d"), texture_map)(input)?;
+    Ok((input, MaterialProperty::TransparencyMap(map)))
+}
+
+/// Parse bump map property, `map_Bump`, `map_bump` or `bump`.
+fn parse_bump_map(input: &str) -> IResult<&str, MaterialProperty> {
+    let keyword = alt((tag("map_Bump"), tag("map_bump"), tag("bump")));
+    let (input, map) = preceded(keyword, texture_map)(input)?;
+    Ok((input, MaterialProperty::BumpMap(map)))
+}
+
+/// Parse normal map property.
+fn parse_normal_map(input: &str) -> IResult<&str, MaterialProperty> {
+    let (input, map) = preceded(tag("norm"), texture_map)(input)?;
+    Ok((input, MaterialProperty::NormalMap(map)))
+}
+
+/// Parse roughness property.
+fn parse_roughness(input: &str) -> IResult<&str, MaterialProperty> {
+    let (input, r) = preceded(tag("Pr"), preceded(space1, number))(input)?;
+    Ok((input, MaterialProperty::Roughness(r)))
+}
+
+/// Parse metallic property.
+fn parse_metallic(input: &str) -> IResult<&str, MaterialProperty> {
+    let (input, m) = preceded(tag("Pm"), preceded(space1, number))(input)?;
+    Ok((input, MaterialProperty::Metallic(m)))
+}
+
+/// Parse sheen property, a color like `Kd`.
+fn parse_sheen(input: &str) -> IResult<&str, MaterialProperty> {
+    let (input, c) = preceded(tag("Ps"), color)(input)?;
+    Ok((input, MaterialProperty::Sheen(c)))
+}
+
+/// Parse clearcoat thickness property.
+fn parse_clearcoat_thickness(input: &str) -> IResult<&str, MaterialProperty> {
+    let (input, t) = preceded(tag("Pc"), preceded(space1, number))(input)?;
+    Ok((input, MaterialProperty::ClearcoatThickness(t)))
+}
+
+/// Parse clearcoat roughness property.
+fn parse_clearcoat_roughness(input: &str) -> IResult<&str, MaterialProperty> {
+    let (input, r) = preceded(tag("Pcr"), preceded(space1, number))(input)?;
+    Ok((input, MaterialProperty::ClearcoatRoughness(r)))
+}
+
+/// Parse anisotropy property.
+fn parse_anisotropy(input: &str) -> IResult<&str, MaterialProperty> {
+    let (input, a) = preceded(tag("aniso"), preceded(space1, number))(input)?;
+    Ok((input, MaterialProperty::Anisotropy(a)))
+}
+
+/// Parse anisotropy rotation property.
+fn parse_anisotropy_rotation(input: &str) -> IResult<&str, MaterialProperty> {
+    let (input, r) = preceded(tag("anisor"), preceded(space1, number))(input)?;
+    Ok((input, MaterialProperty::AnisotropyRotation(r)))
+}
+
+/// Parse the options and the file of a texture map statement, ie `[-option
+/// values ...] file`. The file is the rest of the line, so it can contain
+/// spaces.
+fn texture_map(input: &str) -> IResult<&str, TextureMap> {
+    let (input, options) = many0(preceded(space1, texture_option))(input)?;
+    let (remaining, path) = preceded(space1, take_till1(|c| c == '#'))(input)?;
+    let path = path.trim_end();
+    if path.starts_with('-') {
+        // Options that are not known.
+        return Err(nom::Err::Error(nom::error::Error::new(
+            path,
+            nom::error::ErrorKind::Tag,
+        )));
+    }
+
+    let mut map = TextureMap::new(path);
+    for option in options {
+        match option {
+            TextureOption::Scale(s) => map.scale = s,
+            TextureOption::Offset(o) => map.offset = o,
+            TextureOption::BumpMultiplier(m) => map.bump_multiplier = m,
+            TextureOption::Clamp(c) => map.clamp = c,
+            TextureOption::Ignored => (),
+        }
+    }
+    Ok((remaining, map))
+}
+
+/// Parse a texture map option of the form `-name values`. Options that are
+/// not supported are parsed, but ignored.
+fn texture_option(input: &str) -> IResult<&str, TextureOption> {
+    let (input, name) = preceded(char('-'), alpha1)(input)?;
+    let on_off = || preceded(space1, alt((value(true, tag("on")), value(false, tag("off")))));
+    match name {
+        "s" => cut(uvw(Vec3A::ONE))(input).map(|(i, s)| (i, TextureOption::Scale(s))),
+        "o" => cut(uvw(Vec3A::ZERO))(input).map(|(i, o)| (i, TextureOption::Offset(o))),
+        "bm" => cut(preceded(space1, number))(input)
+            .map(|(i, m)| (i, TextureOption::BumpMultiplier(m))),
+        "clamp" => cut(on_off())(input).map(|(i, c)| (i, TextureOption::Clamp(c))),
+        "blendu" | "blendv" | "cc" => value(TextureOption::Ignored, cut(on_off()))(input),
+        "boost" | "texres" => value(TextureOption::Ignored, cut(preceded(space1, number)))(input),
+        "mm" => value(
+            TextureOption::Ignored,
+            cut(tuple((preceded(space1, number), preceded(space1, number)))),
+        )(input),
+        "t" => value(TextureOption::Ignored, cut(uvw(Vec3A::ZERO)))(input),
+        "imfchan" => value(
+            TextureOption::Ignored,
+            cut(preceded(space1, one_of("rgbmlz"))),
+        )(input),
+        "type" => value(
+            TextureOption::Ignored,
+            cut(preceded(space1, take_till1(|c: char| c.is_whitespace()))),
+        )(input),
+        _ => Err(nom::Err::Error(nom::error::Error::new(
+            name,
+            nom::error::ErrorKind::Tag,
+        ))),
+    }
+}
+
+/// Parse the values `u [v [w]]` of a texture option. Values that are left
+/// out are taken from `default`.
+fn uvw(default: Vec3A) -> impl FnMut(&str) -> IResult<&str, Vec3A> {
+    move |input| {
+        let (input, u) = preceded(space1, number)(input)?;
+        let (input, v) = opt(preceded(space1, number))(input)?;
+        let (input, w) = opt(preceded(space1, number))(input)?;
+        Ok((
+            input,
+            Vec3A::new(u, v.unwrap_or(default.y), w.unwrap_or(default.z)),
+        ))
+    }
+}
+
+/// Parse a number that is followed by whitespace or the end of the input, so
+/// that file names starting with a digit are not taken for numbers.
+fn number(input: &str) -> IResult<&str, f32> {
+    terminated(float, peek(alt((space1, eof))))(input)
 }
@@ -1,5 +1,7 @@
 use super::{parser, Material};
 
+use crate::error::ParseErrorKind;
+
 use glam::Vec3A;
 
 #[test]
@@ -26,10 +28,12 @@ illum 2";
             emissive_color: Vec3A::new(0.0, 0.0, 0.0),
             transparency: 1.0,
             index_of_refraction: 1.45,
+            illumination_model: 2,
+            ..Material::default()
         }
     ];
 
-    let (_, (mats, _)) = parser::materials(input).unwrap();
+    let (mats, _) = parser::materials(input).unwrap();
     assert_eq!(expected_materials, mats);
 }
 
@@ -62,6 +66,8 @@ illum 2";
             emissive_color: Vec3A::new(0.0, 0.0, 0.0),
             transparency: 1.0,
             index_of_refraction: 1.45,
+            illumination_model: 2,
+            ..Material::default()
         },
         Material {
             ambient_color: Vec3A::new(1.0, 0.0, 0.0),
@@ -71,8 +77,113 @@ illum 2";
             emissive_color: Vec3A::new(0.0, 0.0, 0.0),
             transparency: 1.0,
             index_of_refraction: 1.45,
+            illumination_model: 2,
+            ..Material::default()
         }
     ];
-    let (_, (mats,_)) = parser::materials(input).unwrap();
+    let (mats, _) = parser::materials(input).unwrap();
     assert_eq!(expected_materials, mats);
 }
+
+#[test]
+fn invalid_material_test() {
+    let input = "newmtl Material\nKd 0.8 0.8 0.8\nNs high";
+    let error = parser::materials(input).unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::Syntax);
+    assert_eq!((error.line(), error.column()), (3, 4));
+
+    let input = "# Missing newmtl\nKd 0.8 0.8 0.8";
+    let error = parser::materials(input).unwrap_err();
+    assert_eq!((error.line(), error.column()), (2, 1));
+}
+
+#[test]
+fn transparency_test() {
+    let input = "newmtl Opaque\nKd 1 1 1\nnewmtl Dissolved\nd 0.25\nnewmtl Transmissive\nTr 0.25";
+    let (mats, _) = parser::materials(input).unwrap();
+
+    // `Tr` is the inverse of `d`, materials without either are opaque.
+    assert_eq!(mats[0].transparency, 1.0);
+    assert_eq!(mats[1].transparency, 0.25);
+    assert_eq!(mats[2].transparency, 0.75);
+}
+
+#[test]
+fn texture_map_test() {
+    let input = "newmtl Textured
+map_Kd -s 2 2 -o 0.5 0 -clamp on textures/wood floor.png
+map_Ks -blendu off -mm 0 1 -imfchan r 1specular.png # comment
+bump -bm 0.25 bump.png
+map_d alpha.png";
+    let (mats, _) = parser::materials(input).unwrap();
+    let material = &mats[0];
+
+    let diffuse_map = material.diffuse_map.as_ref().unwrap();
+    assert_eq!(diffuse_map.path, "textures/wood floor.png");
+    assert_eq!(diffuse_map.scale, Vec3A::new(2.0, 2.0, 1.0));
+    assert_eq!(diffuse_map.offset, Vec3A::new(0.5, 0.0, 0.0));
+    assert!(diffuse_map.clamp);
+    assert!(diffuse_map.texture.is_none());
+
+    assert_eq!(material.specular_map.as_ref().unwrap().path, "1specular.png");
+    assert_eq!(material.bump_map.as_ref().unwrap().bump_multiplier, 0.25);
+    assert_eq!(material.transparency_map.as_ref().unwrap().path, "alpha.png");
+    assert!(material.emissive_map.is_none());
+
+    let error = parser::materials("newmtl A\nmap_Kd -s x a.png").unwrap_err();
+    assert_eq!((error.line(), error.column()), (2, 11));
+    let error = parser::materials("newmtl A\nmap_Kd -unknown 1 a.png").unwrap_err();
+    assert_eq!((error.line(), error.column()), (2, 8));
+    let error = parser::materials("newmtl A\nmap_Kd -clamp on").unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::Syntax);
+}
+
+#[test]
+fn physical_properties_test() {
+    let input = "newmtl Metal
+Kd 0.9 0.6 0.2
+Pr 0.3
+Pm 1
+Ps 0.1 0.2 0.3
+Pc 0.5
+Pcr 0.05
+aniso 0.4
+anisor 0.25
+norm -bm 0.5 normal.png
+newmtl Plastic
+Kd 0.8 0.8 0.8";
+    let (mats, _) = parser::materials(input).unwrap();
+
+    let physical = mats[0].physical.unwrap();
+    assert_eq!(physical.roughness, 0.3);
+    assert_eq!(physical.metallic, 1.0);
+    assert_eq!(physical.sheen, Vec3A::new(0.1, 0.2, 0.3));
+    assert_eq!(physical.clearcoat_thickness, 0.5);
+    assert_eq!(physical.clearcoat_roughness, 0.05);
+    assert_eq!(physical.anisotropy, 0.4);
+    assert_eq!(physical.anisotropy_rotation, 0.25);
+    assert_eq!(mats[0].normal_map.as_ref().unwrap().path, "normal.png");
+
+    // Materials without any of the properties are not physically based.
+    assert!(mats[1].physical.is_none());
+
+    let error = parser::materials("newmtl A\nPm shiny").unwrap_err();
+    assert_eq!((error.line(), error.column()), (2, 4));
+    let error = parser::materials("newmtl A\nPr 0.5x").unwrap_err();
+    assert_eq!(
+        error.description(),
+        "Expected property of the form `Pr roughness`"
+    );
+}
+
+#[test]
+fn illumination_model_test() {
+    use crate::material::IlluminationModel;
+
+    let input = "newmtl Constant\nillum 0\nnewmtl Glass\nillum 7\nnewmtl Default\nKd 1 1 1";
+    let (mats, _) = parser::materials(input).unwrap();
+    assert_eq!(mats[0].illumination(), IlluminationModel::Constant);
+    assert_eq!(mats[1].illumination_model, 7);
+    assert_eq!(mats[1].illumination(), IlluminationModel::Glass);
+    assert_eq!(mats[2].illumination(), IlluminationModel::Diffuse);
+}
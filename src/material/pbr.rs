@@ -0,0 +1,223 @@
+use std::f32::consts::PI;
+
+use glam::{Vec2, Vec3A};
+use rand::Rng;
+
+use crate::primitives::{Hit, Ray};
+
+use super::Material;
+
+/// Reflectance at normal incidence of dielectrics, and of the clearcoat layer.
+const DIELECTRIC_REFLECTANCE: f32 = 0.04;
+/// Smallest roughness used for sampling, perfectly smooth surfaces would need
+/// a separate mirror lobe.
+const MIN_ROUGHNESS: f32 = 0.02;
+
+/// Parameters of the physically based rendering extension of the MTL format.
+/// Materials with any of these parameters are shaded with a metal/roughness
+/// model, where the diffuse color is the base color.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicalProperties {
+    /// Roughness of the surface in [0, 1] (`Pr`).
+    pub roughness: f32,
+    /// How metallic the surface is in [0, 1] (`Pm`).
+    pub metallic: f32,
+    /// Color of the sheen at grazing angles (`Ps`).
+    pub sheen: Vec3A,
+    /// Thickness, ie strength, of the clearcoat layer in [0, 1] (`Pc`).
+    pub clearcoat_thickness: f32,
+    /// Roughness of the clearcoat layer in [0, 1] (`Pcr`).
+    pub clearcoat_roughness: f32,
+    /// Anisotropy of the specular highlight in [0, 1] (`aniso`).
+    pub anisotropy: f32,
+    /// Rotation of the direction of anisotropy in [0, 1], where 1 is a full
+    /// turn (`anisor`).
+    pub anisotropy_rotation: f32,
+}
+
+impl Default for PhysicalProperties {
+    fn default() -> Self {
+        Self {
+            roughness: 0.5,
+            metallic: 0.0,
+            sheen: Vec3A::ZERO,
+            clearcoat_thickness: 0.0,
+            clearcoat_roughness: 0.0,
+            anisotropy: 0.0,
+            anisotropy_rotation: 0.0,
+        }
+    }
+}
+
+/// Layer of the material a scattered ray is sampled from.
+enum Lobe {
+    Clearcoat,
+    Specular,
+    Diffuse,
+}
+
+impl PhysicalProperties {
+    /// Sample the direction a ray arriving at the hit is scattered in.
+    ///
+    /// The material consists of a GGX microfacet specular layer on top of a
+    /// diffuse layer, covered by an optional clearcoat. One of the layers is
+    /// chosen at random, and its contribution is divided by the probability
+    /// of choosing it.
+    ///
+    /// ### Arguments
+    /// - `material` - The material the properties belong to, used for the
+    ///   base color.
+    /// - `hit` - The hit to scatter the ray at.
+    ///
+    /// ### Returns
+    /// The scattered ray and the fraction of the light along it that is
+    /// reflected towards the incoming ray, or `None` if the ray is absorbed.
+    pub fn scatter(&self, material: &Material, hit: &Hit) -> Option<(Ray, Vec3A)> {
+        self.scatter_with(material, hit, &mut rand::thread_rng())
+    }
+
+    /// Sample the direction a ray arriving at the hit is scattered in, using
+    /// the random numbers of `rng`. See `scatter`.
+    pub(crate) fn scatter_with<R: Rng + ?Sized>(
+        &self,
+        material: &Material,
+        hit: &Hit,
+        rng: &mut R,
+    ) -> Option<(Ray, Vec3A)> {
+        let view = -hit.incoming.direction.normalize();
+        let normal = if hit.normal.dot(view) < 0.0 {
+            -hit.normal
+        } else {
+            hit.normal
+        };
+        let n_dot_v = normal.dot(view).max(1e-4);
+        let base_color = material.diffuse_color_at_hit(hit);
+        let specular_color = Vec3A::splat(DIELECTRIC_REFLECTANCE).lerp(base_color, self.metallic);
+
+        // Light that is reflected by the clearcoat does not reach the layers
+        // below it.
+        let clearcoat = self.clearcoat_thickness.clamp(0.0, 1.0);
+        let clearcoat_fresnel = schlick(Vec3A::splat(DIELECTRIC_REFLECTANCE), n_dot_v).x;
+        let base_weight = 1.0 - clearcoat * clearcoat_fresnel;
+
+        let clearcoat_probability = 0.25 * clearcoat;
+        let specular_probability = (1.0 - clearcoat_probability) * (0.5 + 0.5 * self.metallic);
+        let lobe = match rng.gen::<f32>() {
+            r if r < clearcoat_probability => Lobe::Clearcoat,
+            r if r < clearcoat_probability + specular_probability => Lobe::Specular,
+            _ => Lobe::Diffuse,
+        };
+
+        let (right, forward) = self.tangents(normal);
+        let (direction, weight) = match lobe {
+            Lobe::Clearcoat => {
+                let alpha = Vec2::splat(alpha(self.clearcoat_roughness));
+                let (direction, fresnel_g) = sample_ggx(
+                    view,
+                    normal,
+                    (right, forward),
+                    alpha,
+                    Vec3A::splat(DIELECTRIC_REFLECTANCE),
+                    rng,
+                )?;
+                (direction, fresnel_g * clearcoat / clearcoat_probability)
+            }
+            Lobe::Specular => {
+                let alpha = self.anisotropic_alpha();
+                let (direction, fresnel_g) =
+                    sample_ggx(view, normal, (right, forward), alpha, specular_color, rng)?;
+                (direction, fresnel_g * base_weight / specular_probability)
+            }
+            Lobe::Diffuse => {
+                let ray = hit.random_outgoing_ray_with(rng);
+                let direction = if ray.direction.dot(normal) < 0.0 {
+                    -ray.direction
+                } else {
+                    ray.direction
+                };
+                let n_dot_l = normal.dot(direction).clamp(0.0, 1.0);
+                let sheen = self.sheen * (1.0 - n_dot_l).powi(5);
+                let diffuse = (base_color + sheen)
+                    * (1.0 - self.metallic)
+                    * (Vec3A::ONE - schlick(specular_color, n_dot_v));
+                let probability = 1.0 - clearcoat_probability - specular_probability;
+                (direction, diffuse * base_weight / probability)
+            }
+        };
+        Some((hit.spawn_ray(direction), weight))
+    }
+
+    /// Get the directions of the tangent and bitangent at the surface, along
+    /// which the roughness of anisotropic materials differs.
+    fn tangents(&self, normal: Vec3A) -> (Vec3A, Vec3A) {
+        let (right, forward) = normal.any_orthonormal_pair();
+        let angle = self.anisotropy_rotation * 2.0 * PI;
+        let (sin, cos) = angle.sin_cos();
+        (right * cos + forward * sin, forward * cos - right * sin)
+    }
+
+    /// Get the GGX alpha along the tangent and bitangent, see `tangents`.
+    fn anisotropic_alpha(&self) -> Vec2 {
+        let alpha = alpha(self.roughness);
+        let aspect = (1.0 - 0.9 * self.anisotropy.clamp(0.0, 1.0)).sqrt();
+        Vec2::new(alpha / aspect, alpha * aspect)
+    }
+}
+
+/// Convert a perceptual roughness into the alpha of the GGX distribution.
+#[inline]
+fn alpha(roughness: f32) -> f32 {
+    let roughness = roughness.clamp(MIN_ROUGHNESS, 1.0);
+    roughness * roughness
+}
+
+/// Schlick's approximation of the Fresnel reflectance.
+#[inline]
+fn schlick(reflectance: Vec3A, cos_theta: f32) -> Vec3A {
+    reflectance + (Vec3A::ONE - reflectance) * (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5)
+}
+
+/// Sample a microfacet normal from the (anisotropic) GGX distribution and
+/// reflect the view direction on it.
+///
+/// ### Returns
+/// The reflected direction and the weight of the reflected light, ie the
+/// Fresnel reflectance times the shadowing-masking term, divided by the
+/// probability of the sample. `None` if the reflected direction is below the
+/// surface.
+fn sample_ggx<R: Rng + ?Sized>(
+    view: Vec3A,
+    normal: Vec3A,
+    (tangent, bitangent): (Vec3A, Vec3A),
+    alpha: Vec2,
+    reflectance: Vec3A,
+    rng: &mut R,
+) -> Option<(Vec3A, Vec3A)> {
+    let (u1, u2) = (rng.gen::<f32>(), rng.gen::<f32>());
+    // Sample the slope of the microfacet, stretched by alpha.
+    let slope = (u1 / (1.0 - u1)).sqrt();
+    let (sin, cos) = (2.0 * PI * u2).sin_cos();
+    let half = (tangent * (alpha.x * slope * cos) + bitangent * (alpha.y * slope * sin) + normal)
+        .normalize();
+    let direction = 2.0 * view.dot(half) * half - view;
+
+    let n_dot_l = normal.dot(direction);
+    if n_dot_l <= 0.0 {
+        return None;
+    }
+    let n_dot_v = normal.dot(view).max(1e-4);
+    let n_dot_h = normal.dot(half).max(1e-4);
+    let v_dot_h = view.dot(half).clamp(0.0, 1.0);
+
+    let lambda = |w: Vec3A| {
+        let (x, y, z) = (
+            w.dot(tangent) * alpha.x,
+            w.dot(bitangent) * alpha.y,
+            w.dot(normal),
+        );
+        (-1.0 + (1.0 + (x * x + y * y) / (z * z)).sqrt()) / 2.0
+    };
+    let shadowing = 1.0 / (1.0 + lambda(view) + lambda(direction));
+    let weight = schlick(reflectance, v_dot_h) * shadowing * v_dot_h / (n_dot_v * n_dot_h);
+    Some((direction, weight))
+}
@@ -0,0 +1,61 @@
+use super::{Material, PhysicalProperties};
+
+use crate::{
+    primitives::{Hit, Ray},
+    test_utils::test_rng,
+};
+
+use glam::Vec3A;
+
+fn hit_from_above(direction: Vec3A) -> Hit {
+    let ray = Ray::new(-direction * 2.0, direction);
+    Hit::from_surface(Vec3A::ZERO, 2.0, ray, Vec3A::Y, Default::default())
+}
+
+#[test]
+fn smooth_metal_reflects_test() {
+    let material = Material {
+        diffuse_color: Vec3A::new(1.0, 0.5, 0.25),
+        ..Material::default()
+    };
+    let physical = PhysicalProperties {
+        roughness: 0.0,
+        metallic: 1.0,
+        ..PhysicalProperties::default()
+    };
+    let direction = Vec3A::new(1.0, -1.0, 0.0).normalize();
+    let hit = hit_from_above(direction);
+
+    let mut rng = test_rng();
+    for _ in 0..100 {
+        let (ray, weight) = physical.scatter_with(&material, &hit, &mut rng).unwrap();
+        // Close to a mirror reflection, tinted by the base color.
+        let mirror = Vec3A::new(1.0, 1.0, 0.0).normalize();
+        assert!(ray.direction.dot(mirror) > 0.99);
+        assert!((weight - material.diffuse_color).abs().max_element() < 0.1);
+    }
+}
+
+#[test]
+fn rough_dielectric_scatters_above_surface_test() {
+    let material = Material {
+        diffuse_color: Vec3A::splat(0.8),
+        ..Material::default()
+    };
+    let physical = PhysicalProperties {
+        roughness: 1.0,
+        sheen: Vec3A::splat(0.5),
+        clearcoat_thickness: 1.0,
+        anisotropy: 0.8,
+        ..PhysicalProperties::default()
+    };
+    let hit = hit_from_above(Vec3A::new(0.3, -1.0, 0.2).normalize());
+
+    let mut rng = test_rng();
+    for _ in 0..1000 {
+        if let Some((ray, weight)) = physical.scatter_with(&material, &hit, &mut rng) {
+            assert!(ray.direction.y >= 0.0);
+            assert!(weight.min_element() >= 0.0 && weight.is_finite());
+        }
+    }
+}
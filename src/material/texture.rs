@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use glam::{Vec2, Vec3A, Vec4};
+use image::RgbaImage;
+
+/// Image used as a texture map. Colors are stored as 8 bit RGBA values and
+/// sampled with bilinear filtering.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Texture {
+    image: RgbaImage,
+}
+
+impl Texture {
+    /// Creates a texture from an image.
+    pub fn new(image: RgbaImage) -> Self {
+        Self { image }
+    }
+
+    /// Decodes a texture from the contents of an image file, in any format
+    /// supported by the `image` crate.
+    pub fn from_memory(data: &[u8]) -> Result<Self, image::ImageError> {
+        Ok(Self::new(image::load_from_memory(data)?.into_rgba8()))
+    }
+
+    /// Get the width and height of the texture in pixels.
+    #[inline]
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.image.dimensions()
+    }
+
+    /// Sample the texture at the given texture coordinates. (0, 0) is the
+    /// bottom left corner of the image and (1, 1) the top right corner.
+    ///
+    /// ### Arguments
+    /// - `tex_coord` - The texture coordinates to sample at.
+    /// - `clamp` - If true, coordinates outside of [0, 1] are clamped to the
+    ///   edge of the image. Otherwise the texture is repeated.
+    ///
+    /// ### Returns
+    /// The RGBA color at the coordinates, with components in [0, 1].
+    pub fn sample(&self, tex_coord: Vec2, clamp: bool) -> Vec4 {
+        let (width, height) = self.dimensions();
+        if width == 0 || height == 0 {
+            return Vec4::ONE;
+        }
+        let tex_coord = if clamp {
+            tex_coord.clamp(Vec2::ZERO, Vec2::ONE)
+        } else {
+            tex_coord - tex_coord.floor()
+        };
+        // Pixel centers are at half integer coordinates, rows start at the
+        // top of the image.
+        let x = tex_coord.x * width as f32 - 0.5;
+        let y = (1.0 - tex_coord.y) * height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+
+        let pixel = |x: f32, y: f32| {
+            let (x, y) = (x as i64, y as i64);
+            let (x, y) = if clamp {
+                (x.clamp(0, width as i64 - 1), y.clamp(0, height as i64 - 1))
+            } else {
+                (x.rem_euclid(width as i64), y.rem_euclid(height as i64))
+            };
+            let [r, g, b, a] = self.image.get_pixel(x as u32, y as u32).0;
+            Vec4::new(r as f32, g as f32, b as f32, a as f32) / 255.0
+        };
+        let top = pixel(x0, y0).lerp(pixel(x0 + 1.0, y0), fx);
+        let bottom = pixel(x0, y0 + 1.0).lerp(pixel(x0 + 1.0, y0 + 1.0), fx);
+        top.lerp(bottom, fy)
+    }
+}
+
+/// Texture map of a material, for example `map_Kd`, with the options of the
+/// map statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureMap {
+    /// Path of the image file, as written in the material file.
+    pub path: String,
+    /// The loaded image, `None` until the texture is loaded.
+    pub texture: Option<Arc<Texture>>,
+    /// Scale of the texture coordinates (`-s u v w`).
+    pub scale: Vec3A,
+    /// Offset of the texture coordinates (`-o u v w`).
+    pub offset: Vec3A,
+    /// Multiplier of the heights of a bump map (`-bm mult`).
+    pub bump_multiplier: f32,
+    /// Clamp texture coordinates instead of repeating the texture
+    /// (`-clamp on`).
+    pub clamp: bool,
+}
+
+impl TextureMap {
+    /// Creates a texture map of the image at `path`, with default options.
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            texture: None,
+            scale: Vec3A::ONE,
+            offset: Vec3A::ZERO,
+            bump_multiplier: 1.0,
+            clamp: false,
+        }
+    }
+
+    /// Transform texture coordinates of a surface to coordinates in the
+    /// texture, using the scale and offset of the map.
+    #[inline]
+    fn transform(&self, tex_coord: Vec2) -> Vec2 {
+        tex_coord * Vec2::new(self.scale.x, self.scale.y) + Vec2::new(self.offset.x, self.offset.y)
+    }
+
+    /// Sample the texture at the texture coordinates of a surface.
+    ///
+    /// ### Returns
+    /// The RGBA color, or white if the texture is not loaded.
+    pub fn sample(&self, tex_coord: Vec2) -> Vec4 {
+        match &self.texture {
+            Some(texture) => texture.sample(self.transform(tex_coord), self.clamp),
+            None => Vec4::ONE,
+        }
+    }
+
+    /// Get the height of a bump map at the texture coordinates of a surface,
+    /// ie the red channel scaled by the bump multiplier.
+    #[inline]
+    pub fn height(&self, tex_coord: Vec2) -> f32 {
+        self.sample(tex_coord).x * self.bump_multiplier
+    }
+
+    /// Get the derivatives of the height of a bump map with respect to the
+    /// texture coordinates of a surface, using central differences over one
+    /// pixel of the texture.
+    pub fn height_gradient(&self, tex_coord: Vec2) -> Vec2 {
+        let Some(texture) = &self.texture else {
+            return Vec2::ZERO;
+        };
+        let (width, height) = texture.dimensions();
+        let step = Vec2::new(1.0 / width.max(1) as f32, 1.0 / height.max(1) as f32)
+            / Vec2::new(self.scale.x, self.scale.y)
+                .abs()
+                .max(Vec2::splat(f32::EPSILON));
+        let du = Vec2::new(step.x, 0.0);
+        let dv = Vec2::new(0.0, step.y);
+        Vec2::new(
+            (self.height(tex_coord + du) - self.height(tex_coord - du)) / (2.0 * step.x),
+            (self.height(tex_coord + dv) - self.height(tex_coord - dv)) / (2.0 * step.y),
+        )
+    }
+}
@@ -0,0 +1,84 @@
+use std::io::Cursor;
+
+use super::{Texture, TextureMap};
+
+use crate::{error::ParseErrorKind, resolver::MemoryResolver, scene::parser::load_obj};
+
+use glam::{Vec2, Vec3A, Vec4};
+use image::{ImageOutputFormat, Rgba, RgbaImage};
+
+/// 2x2 texture with a red, green, blue and white pixel, from the top left to
+/// the bottom right.
+fn test_image() -> RgbaImage {
+    RgbaImage::from_fn(2, 2, |x, y| match (x, y) {
+        (0, 0) => Rgba([255, 0, 0, 255]),
+        (1, 0) => Rgba([0, 255, 0, 255]),
+        (0, 1) => Rgba([0, 0, 255, 255]),
+        _ => Rgba([255, 255, 255, 255]),
+    })
+}
+
+#[test]
+fn texture_sample_test() {
+    let texture = Texture::new(test_image());
+
+    // The image is upside down in texture space.
+    assert_eq!(
+        texture.sample(Vec2::new(0.25, 0.75), false),
+        Vec4::new(1.0, 0.0, 0.0, 1.0)
+    );
+    assert_eq!(texture.sample(Vec2::new(0.75, 0.25), false), Vec4::ONE);
+    // Halfway between the blue and white pixel.
+    assert_eq!(
+        texture.sample(Vec2::new(0.5, 0.25), true),
+        Vec4::new(0.5, 0.5, 1.0, 1.0)
+    );
+    // Repeated or clamped outside of [0, 1].
+    assert_eq!(
+        texture.sample(Vec2::new(1.25, -0.25), false),
+        texture.sample(Vec2::new(0.25, 0.75), false)
+    );
+    assert_eq!(texture.sample(Vec2::new(1.5, -0.5), true), Vec4::ONE);
+}
+
+#[test]
+fn material_texture_test() {
+    let mut png = Vec::new();
+    test_image()
+        .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+        .unwrap();
+    let obj = "mtllib materials/textured.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 0 1
+vn 0 0 1
+usemtl Textured
+f 1/1/1 2/2/1 3/3/1";
+    let resolver = MemoryResolver::new()
+        .with_file("scene.obj", obj)
+        .with_file(
+            "materials/textured.mtl",
+            "newmtl Textured\nKd 0.5 1 1\nmap_Kd ../textures/test.png",
+        )
+        .with_file("textures/test.png", png);
+    let (mesh, material_map, _) = load_obj(&resolver, "scene.obj").unwrap();
+    let material = &mesh.materials()[material_map["Textured"]];
+
+    // The diffuse color is multiplied with the texture.
+    let color = material.diffuse_color_at(Some(Vec2::new(0.25, 0.75)));
+    assert_eq!(color, Vec3A::new(0.5, 0.0, 0.0));
+    assert_eq!(material.diffuse_color_at(None), Vec3A::new(0.5, 1.0, 1.0));
+
+    let map = TextureMap::new("test.png");
+    assert_eq!(map.sample(Vec2::ZERO), Vec4::ONE);
+
+    // Images that can't be decoded are reported at their map statement.
+    let resolver = resolver.with_file("textures/test.png", "not an image");
+    let error = load_obj(&resolver, "scene.obj").unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::InvalidImage);
+    assert_eq!(error.file(), "materials/textured.mtl");
+    assert_eq!((error.line(), error.column()), (3, 8));
+}
@@ -0,0 +1,94 @@
+use std::io::{self, Write};
+
+use glam::Vec3A;
+
+use super::{Material, TextureMap};
+
+/// Write materials in the [MTL file
+/// format](https://en.wikipedia.org/wiki/Wavefront_.obj_file), so that
+/// `parser::materials` reads them back unchanged. All properties are written,
+/// including the physically based properties and the texture maps with their
+/// options. The paths of the texture maps are written as they are, so they
+/// need to be valid relative to the written file.
+///
+/// ### Arguments
+/// - `writer` - The writer to write the mtl file to.
+/// - `materials` - The materials to write.
+/// - `names` - The name of every material, see `scene::writer::material_names`.
+///
+/// ### Panics
+/// If there are fewer names than materials.
+pub fn write_mtl<W: Write>(
+    mut writer: W,
+    materials: &[Material],
+    names: &[String],
+) -> io::Result<()> {
+    assert!(
+        names.len() >= materials.len(),
+        "Every material needs a name"
+    );
+    for (i, (material, name)) in materials.iter().zip(names).enumerate() {
+        if i > 0 {
+            writeln!(writer)?;
+        }
+        writeln!(writer, "newmtl {}", name)?;
+        write_color(&mut writer, "Ka", material.ambient_color)?;
+        write_color(&mut writer, "Kd", material.diffuse_color)?;
+        write_color(&mut writer, "Ks", material.specular_color)?;
+        write_color(&mut writer, "Ke", material.emissive_color)?;
+        writeln!(writer, "Ns {}", material.specular_highlight)?;
+        writeln!(writer, "Ni {}", material.index_of_refraction)?;
+        writeln!(writer, "d {}", material.transparency)?;
+        writeln!(writer, "illum {}", material.illumination_model)?;
+
+        if let Some(physical) = &material.physical {
+            writeln!(writer, "Pr {}", physical.roughness)?;
+            writeln!(writer, "Pm {}", physical.metallic)?;
+            write_color(&mut writer, "Ps", physical.sheen)?;
+            writeln!(writer, "Pc {}", physical.clearcoat_thickness)?;
+            writeln!(writer, "Pcr {}", physical.clearcoat_roughness)?;
+            writeln!(writer, "aniso {}", physical.anisotropy)?;
+            writeln!(writer, "anisor {}", physical.anisotropy_rotation)?;
+        }
+
+        let maps = [
+            ("map_Kd", &material.diffuse_map),
+            ("map_Ks", &material.specular_map),
+            ("map_Ke", &material.emissive_map),
+            ("map_d", &material.transparency_map),
+            ("map_Bump", &material.bump_map),
+            ("norm", &material.normal_map),
+        ];
+        for (keyword, map) in maps {
+            if let Some(map) = map {
+                write_texture_map(&mut writer, keyword, map)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_color<W: Write>(writer: &mut W, keyword: &str, color: Vec3A) -> io::Result<()> {
+    writeln!(writer, "{} {} {} {}", keyword, color.x, color.y, color.z)
+}
+
+/// Write a texture map statement, with the options that differ from the
+/// defaults of `TextureMap::new`.
+fn write_texture_map<W: Write>(writer: &mut W, keyword: &str, map: &TextureMap) -> io::Result<()> {
+    write!(writer, "{}", keyword)?;
+    if map.scale != Vec3A::ONE {
+        let s = map.scale;
+        write!(writer, " -s {} {} {}", s.x, s.y, s.z)?;
+    }
+    if map.offset != Vec3A::ZERO {
+        let o = map.offset;
+        write!(writer, " -o {} {} {}", o.x, o.y, o.z)?;
+    }
+    if map.bump_multiplier != 1.0 {
+        write!(writer, " -bm {}", map.bump_multiplier)?;
+    }
+    if map.clamp {
+        write!(writer, " -clamp on")?;
+    }
+    writeln!(writer, " {}", map.path)
+}
@@ -1,4 +1,14 @@
-use crate::scene::{self, camera::CameraBuilder, Camera, Scene, SceneRenderer};
+use std::path::Path;
+
+use crate::material::Material;
+use crate::scene::{
+    self, camera::CameraBuilder, description, instance::get_instances, Camera, Scene,
+    SceneDescription, SceneRenderer,
+};
+
+pub use crate::error::{ParseError, ParseErrorKind};
+pub use crate::primitives::IntersectionAlgorithm;
+pub use crate::resolver::{AssetResolver, FileSystemResolver, MemoryResolver};
 
 use glam::Vec3A;
 use image::RgbImage;
@@ -6,10 +16,15 @@ use image::RgbImage;
 pub struct RayTracer {
     camera_builder: CameraBuilder,
     directory: Option<String>,
+    resolver: Option<Box<dyn AssetResolver>>,
     obj_file: Option<String>,
     camera: Option<Camera>,
     sample_count: Option<u32>,
     recursion_depth: Option<u32>,
+    intersection_algorithm: IntersectionAlgorithm,
+    stl_material: Option<Material>,
+    crease_angle: Option<f32>,
+    scene_description: Option<SceneDescription>,
 }
 
 impl RayTracer {
@@ -18,11 +33,56 @@ impl RayTracer {
         Self {
             camera_builder: CameraBuilder::new(),
             directory: None,
+            resolver: None,
             obj_file: None,
             camera: None,
             sample_count: None,
             recursion_depth: None,
+            intersection_algorithm: IntersectionAlgorithm::default(),
+            stl_material: None,
+            crease_angle: None,
+            scene_description: None,
+        }
+    }
+
+    /// Creates a ray tracer for the scene described by a scene file, see
+    /// `scene::description::load_scene_description` for the format. The
+    /// camera and render settings of the file can be changed with the other
+    /// methods afterwards.
+    ///
+    /// ### Arguments
+    /// - `path` - The path of the scene file. Files referenced by the scene
+    ///   file are resolved relative to its directory.
+    ///
+    /// ### Returns
+    /// The ray tracer, or an error if the scene file or its material
+    /// libraries can't be loaded. Included files are loaded when rendering.
+    pub fn from_scene_file(path: &str) -> Result<Self, ParseError> {
+        let path = Path::new(path);
+        let resolver = FileSystemResolver::new(path.parent().unwrap_or(Path::new("")));
+        let file = path
+            .file_name()
+            .map_or_else(String::new, |f| f.to_string_lossy().into_owned());
+        let description = description::load_scene_description(&resolver, &file)?;
+        Ok(Self::from_scene_description(description).resolver(resolver))
+    }
+
+    /// Creates a ray tracer for a scene description, using its camera and
+    /// render settings. The included files are loaded from the resolver or
+    /// directory, which needs to be set before calling `render` or
+    /// `render_save`. Setting an obj file with `obj_file` or `file`
+    /// afterwards replaces the scene description.
+    pub fn from_scene_description(description: SceneDescription) -> Self {
+        let settings = &description.settings;
+        let mut tracer = Self::new();
+        tracer.camera_builder = description.camera.clone();
+        tracer.sample_count = settings.sample_count;
+        tracer.recursion_depth = settings.recursion_depth;
+        if let Some(algorithm) = settings.intersection_algorithm {
+            tracer.intersection_algorithm = algorithm;
         }
+        tracer.scene_description = Some(description);
+        tracer
     }
 
     /// Sets the directory of the obj file to be loaded.
@@ -34,12 +94,27 @@ impl RayTracer {
         self
     }
 
-    /// Sets the obj file to be loaded.
+    /// Sets the resolver the obj file and the files it refers to are loaded
+    /// from, instead of the directory set with `directory`. Use this to load
+    /// scenes from memory (`MemoryResolver`) or other sources.
+    #[inline]
+    pub fn resolver(mut self, resolver: impl AssetResolver + 'static) -> Self {
+        self.resolver = Some(Box::new(resolver));
+        self
+    }
+
+    /// Sets the obj file to be loaded. The path is relative to the directory,
+    /// or the root of the resolver if one is set. glTF (`.gltf` and `.glb`),
+    /// ply (`.ply`) and stl (`.stl`) files are loaded as well, including the
+    /// punctual lights of glTF files. The first camera of a glTF file is used
+    /// if neither `camera`, `camera_position` nor `camera_target` are set.
+    /// The file replaces the scene description of `from_scene_description`.
     ///
     /// Needs to be set before calling `render` or `render_save`.
     #[inline]
     pub fn obj_file(mut self, obj_file: &str) -> Self {
         self.obj_file = Some(obj_file.to_string());
+        self.scene_description = None;
         self
     }
 
@@ -49,10 +124,8 @@ impl RayTracer {
     /// `render_save`.  This is a convenience method for calling `directory` and
     /// `obj_file` in succession.
     #[inline]
-    pub fn file(mut self, directory: &str, obj_file: &str) -> Self {
-        self.directory = Some(directory.to_string());
-        self.obj_file = Some(obj_file.to_string());
-        self
+    pub fn file(self, directory: &str, obj_file: &str) -> Self {
+        self.directory(directory).obj_file(obj_file)
     }
 
     /// Sets the resolution of the image to be rendered.
@@ -101,43 +174,108 @@ impl RayTracer {
         self
     }
 
+    /// Sets the algorithm used for ray-triangle intersection tests. Use
+    /// `IntersectionAlgorithm::Watertight` if there are visible holes along
+    /// the edges of the triangles.
+    #[inline]
+    pub fn intersection_algorithm(mut self, algorithm: IntersectionAlgorithm) -> Self {
+        self.intersection_algorithm = algorithm;
+        self
+    }
+
+    /// Sets the material of stl files, which don't have materials of their
+    /// own. Defaults to a light grey diffuse material. Also used for stl
+    /// files included by a scene file, unless the include sets a material
+    /// with `usemtl`.
+    #[inline]
+    pub fn stl_material(mut self, material: Material) -> Self {
+        self.stl_material = Some(material);
+        self
+    }
+
+    /// Generate smooth normals for the faces of obj files that don't have
+    /// normals, instead of flat normals. Normals are only smoothed between
+    /// faces that meet at an angle of at most the crease angle, in radians.
+    #[inline]
+    pub fn smooth_normals(mut self, crease_angle: f32) -> Self {
+        self.crease_angle = Some(crease_angle);
+        self
+    }
+
     /// Renders the scene and returns the image.
     ///
     /// ### Panics
-    /// If the directory or obj file have not been specified.
+    /// If the obj file and a directory or resolver have not been specified, or
+    /// if the files can't be loaded. Use `try_render` to handle load errors.
     pub fn render(&self) -> RgbImage {
-        // Directory and obj file must be specified
-        if self.directory.is_none() || self.obj_file.is_none() {
-            panic!("Directory and obj file must be specified");
-        }
+        self.try_render().unwrap_or_else(|e| panic!("{}", e))
+    }
 
-        let directory = self.directory.as_ref().unwrap();
-        let obj_file = self.obj_file.as_ref().unwrap();
+    /// Renders the scene and returns the image, or an error if the obj file
+    /// or the files it refers to can't be loaded.
+    ///
+    /// ### Panics
+    /// If the obj file and a directory or resolver have not been specified.
+    pub fn try_render(&self) -> Result<RgbImage, ParseError> {
+        // Directory or resolver must be specified
+        let file_system_resolver;
+        let resolver: &dyn AssetResolver = match (&self.resolver, &self.directory) {
+            (Some(resolver), _) => resolver.as_ref(),
+            (None, Some(directory)) => {
+                file_system_resolver = FileSystemResolver::new(directory);
+                &file_system_resolver
+            }
+            (None, None) => panic!("Directory or resolver must be specified"),
+        };
 
-        let built_camera = self.camera_builder.clone().build();
+        // Setting an obj file clears the scene description, so at most one
+        // of them is set.
+        let mut geometry = match (&self.scene_description, &self.obj_file) {
+            (Some(description), _) => description.load(resolver, self.stl_material.as_ref())?,
+            (None, Some(obj_file)) => scene::load_geometry(
+                resolver,
+                obj_file,
+                self.stl_material.clone(),
+                self.crease_angle,
+            )?,
+            (None, None) => panic!("Obj file must be specified"),
+        };
+        geometry
+            .mesh
+            .set_intersection_algorithm(self.intersection_algorithm);
+        let objects = scene::parser::get_objects(&geometry.mesh, &geometry.object_map);
+        let prototypes = scene::parser::get_objects(&geometry.mesh, &geometry.prototypes);
 
+        let mut scene = Scene::new(&geometry.mesh, objects);
+        scene.add_instances(get_instances(&prototypes, &geometry.instances)?);
+        for light in geometry.lights {
+            scene.add_light(light);
+        }
+
+        // Without a camera position or target, the first camera of the file
+        // is used.
+        let camera_builder = match geometry.cameras.first() {
+            Some(file_camera) => self.camera_builder.clone().or_camera(file_camera),
+            None => self.camera_builder.clone(),
+        };
+        let built_camera = camera_builder.build();
         let camera = match &self.camera {
             Some(camera) => camera,
             None => &built_camera,
         };
-
-        let (mesh, mat_map, obj_map) =
-            scene::parser::get_triangle_mesh_and_obj_map(directory, obj_file);
-        let objects = scene::parser::get_objects(&mesh, &obj_map, &mat_map);
-
-        let scene = Scene::new(&mesh, objects);
         let mut renderer = SceneRenderer::new(camera, &scene);
 
         renderer.set_sample_count(self.sample_count.unwrap_or(1));
         renderer.set_recursion_depth(self.recursion_depth.unwrap_or(1));
 
-        renderer.render()
+        Ok(renderer.render())
     }
 
     /// Renders the scene and saves the image to the specified file path.
     ///
     /// ### Panics
-    /// If the directory or obj file have not been specified.
+    /// If the obj file and a directory or resolver have not been specified, or
+    /// if the files can't be loaded.
     pub fn render_save(&self, file_path: &str) {
         let image = self.render();
         image.save(file_path).unwrap();
@@ -1,9 +1,17 @@
-use super::{Ray};
-
+use super::{Position, Ray};
 
 use glam::Vec3A;
 
-#[derive(Debug)]
+/// Bound on the relative rounding error of three floating point operations.
+const GAMMA_3: f32 = 3.0 * (f32::EPSILON / 2.0) / (1.0 - 3.0 * (f32::EPSILON / 2.0));
+
+/// Factor the exit distance of a ray is scaled by in the slab test, so that
+/// rounding errors never cause a ray that touches the box to miss it. See
+/// [Robust BVH Ray Traversal](https://jcgt.org/published/0002/02/02/paper.pdf).
+pub(crate) const ROBUST_EXIT_SCALE: f32 = 1.0 + 2.0 * GAMMA_3;
+
+/// Axis aligned bounding box, defined by its minimum and maximum corners.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct BoundingBox {
     min: Vec3A,
     max: Vec3A,
@@ -15,21 +23,116 @@ impl BoundingBox {
         Self { min, max }
     }
 
+    /// Creates an empty bounding box, ie a box that contains nothing. The union
+    /// of an empty box and any other box is the other box.
+    #[inline]
+    pub fn empty() -> Self {
+        Self {
+            min: Vec3A::splat(f32::INFINITY),
+            max: Vec3A::splat(f32::NEG_INFINITY),
+        }
+    }
+
+    /// Returns the minimum corner of the bounding box.
+    #[inline]
+    pub fn min(&self) -> Vec3A {
+        self.min
+    }
+
+    /// Returns the maximum corner of the bounding box.
+    #[inline]
+    pub fn max(&self) -> Vec3A {
+        self.max
+    }
+
+    /// Returns true if the bounding box does not contain any point.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.min.cmpgt(self.max).any()
+    }
+
+    /// Returns the smallest bounding box containing both `self` and `other`.
+    #[inline]
+    pub fn union(&self, other: &BoundingBox) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Returns the smallest bounding box containing both `self` and `point`.
+    #[inline]
+    pub fn grow(&self, point: Position) -> Self {
+        Self {
+            min: self.min.min(point),
+            max: self.max.max(point),
+        }
+    }
+
+    /// Returns the center point of the bounding box.
+    #[inline]
+    pub fn centroid(&self) -> Position {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Returns the size of the bounding box along each axis.
+    #[inline]
+    pub fn extent(&self) -> Vec3A {
+        self.max - self.min
+    }
+
+    /// Returns the surface area of the bounding box. An empty bounding box has
+    /// zero surface area.
+    #[inline]
+    pub fn surface_area(&self) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let e = self.extent();
+        2.0 * (e.x * e.y + e.y * e.z + e.z * e.x)
+    }
+
     /// Check if a ray intersects with the bounding box.
     ///
     /// [Reference](https://medium.com/@bromanz/another-view-on-the-classic-ray-aabb-intersection-algorithm-for-bvh-traversal-41125138b525).
     #[inline]
     pub fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
-        let inv_dir = ray.direction.recip();
-        let t0 = (self.min - ray.origin) * inv_dir;
-        let t1 = (self.max - ray.origin) * inv_dir;
+        self.hit_distance(ray.origin, ray.direction.recip(), t_min, t_max)
+            .is_some()
+    }
+
+    /// Returns the distance along the ray where it enters the bounding box, or
+    /// `None` if the ray misses the box in the range `[t_min, t_max]`.
+    ///
+    /// The inverse ray direction is passed in so that it can be computed once
+    /// per ray when testing many boxes, for example during BVH traversal.
+    #[inline]
+    pub fn hit_distance(
+        &self,
+        origin: Vec3A,
+        inv_direction: Vec3A,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<f32> {
+        let t0 = (self.min - origin) * inv_direction;
+        let t1 = (self.max - origin) * inv_direction;
 
         let t_small = t0.min(t1);
         let t_big = t0.max(t1);
 
         let t_min = t_min.max(t_small.max_element());
-        let t_max = t_max.min(t_big.min_element());
+        let t_max = t_max.min(t_big.min_element() * ROBUST_EXIT_SCALE);
+
+        if t_max >= t_min {
+            Some(t_min)
+        } else {
+            None
+        }
+    }
+}
 
-        t_max >= t_min
+impl Default for BoundingBox {
+    fn default() -> Self {
+        Self::empty()
     }
 }
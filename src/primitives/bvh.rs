@@ -0,0 +1,488 @@
+use super::{
+    packet::{hit_distances, merge_hits},
+    BoundingBox, Hit, PacketHits, Ray, RayPacket,
+};
+
+use glam::{Vec3A, Vec4};
+
+/// Number of buckets used when evaluating split candidates along an axis.
+const BIN_COUNT: usize = 12;
+/// Nodes with at most this many primitives are turned into leaves when no
+/// split is cheaper than intersecting all primitives.
+const MAX_LEAF_SIZE: usize = 4;
+/// Maximum depth of the tree. Limits the size of the traversal stack.
+const MAX_DEPTH: usize = 64;
+/// Relative cost of traversing a node compared to intersecting a primitive.
+const TRAVERSAL_COST: f32 = 1.0;
+
+/// Node of a bounding volume hierarchy. Interior nodes store the index of
+/// their first child, the second child directly follows the first. Leaf nodes
+/// store a range into the primitive index list.
+#[derive(Debug, Clone, Copy)]
+struct BvhNode {
+    bounding_box: BoundingBox,
+    /// Index of the first child (interior) or first primitive (leaf).
+    offset: usize,
+    /// Number of primitives in the node. Zero for interior nodes.
+    count: usize,
+}
+
+impl BvhNode {
+    #[inline]
+    fn is_leaf(&self) -> bool {
+        self.count > 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Bin {
+    bounding_box: BoundingBox,
+    count: usize,
+}
+
+/// Bounding volume hierarchy built with the surface area heuristic (SAH).
+///
+/// The hierarchy does not own any primitives, it only stores indices into the
+/// list of bounding boxes it was built from. Intersection tests against the
+/// actual primitives are done by a closure passed to the traversal functions,
+/// which makes the same structure usable for triangles as well as for whole
+/// objects.
+#[derive(Debug, Clone, Default)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    primitive_indices: Vec<usize>,
+}
+
+impl Bvh {
+    /// Build a new hierarchy over the primitives with the given bounding
+    /// boxes. The index of a bounding box in the slice is the index passed
+    /// to the intersection closure during traversal.
+    pub fn new(bounding_boxes: &[BoundingBox]) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(2 * bounding_boxes.len()),
+            primitive_indices: (0..bounding_boxes.len()).collect(),
+        };
+        if bounding_boxes.is_empty() {
+            return bvh;
+        }
+
+        let centroids: Vec<Vec3A> = bounding_boxes.iter().map(|b| b.centroid()).collect();
+        bvh.nodes.push(BvhNode {
+            bounding_box: BoundingBox::empty(),
+            offset: 0,
+            count: bounding_boxes.len(),
+        });
+        bvh.subdivide(0, 0, bounding_boxes, &centroids);
+        bvh
+    }
+
+    /// Returns the bounding box of all primitives in the hierarchy.
+    #[inline]
+    pub fn bounding_box(&self) -> BoundingBox {
+        self.nodes
+            .first()
+            .map_or_else(BoundingBox::empty, |n| n.bounding_box)
+    }
+
+    /// Returns the number of nodes in the hierarchy.
+    #[inline]
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if the hierarchy does not contain any primitives.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Recursively split the node at `node_index` until the SAH decides that
+    /// the node is cheaper as a leaf.
+    fn subdivide(
+        &mut self,
+        node_index: usize,
+        depth: usize,
+        bounding_boxes: &[BoundingBox],
+        centroids: &[Vec3A],
+    ) {
+        let BvhNode { offset, count, .. } = self.nodes[node_index];
+        let indices = &self.primitive_indices[offset..offset + count];
+
+        let (node_box, centroid_box) = indices.iter().fold(
+            (BoundingBox::empty(), BoundingBox::empty()),
+            |(node_box, centroid_box), &i| {
+                (
+                    node_box.union(&bounding_boxes[i]),
+                    centroid_box.grow(centroids[i]),
+                )
+            },
+        );
+        self.nodes[node_index].bounding_box = node_box;
+
+        if count <= 1 || depth + 1 >= MAX_DEPTH {
+            return;
+        }
+
+        let leaf_cost = count as f32;
+        let split = Self::find_split(indices, bounding_boxes, centroids, &centroid_box);
+        let (axis, split_bin, split_cost) = match split {
+            Some(split) => split,
+            None => return,
+        };
+
+        // Split cost is relative to the surface area of the current node.
+        let split_cost = TRAVERSAL_COST + split_cost / node_box.surface_area().max(f32::EPSILON);
+        if split_cost >= leaf_cost && count <= MAX_LEAF_SIZE {
+            return;
+        }
+
+        // Partition the primitive indices so that all primitives left of the
+        // split plane come first.
+        let min = centroid_box.min()[axis];
+        let scale = BIN_COUNT as f32 / centroid_box.extent()[axis];
+        let indices = &mut self.primitive_indices[offset..offset + count];
+        let mut left_count = 0;
+        for i in 0..count {
+            if Self::bin_index(centroids[indices[i]][axis], min, scale) < split_bin {
+                indices.swap(i, left_count);
+                left_count += 1;
+            }
+        }
+        if left_count == 0 || left_count == count {
+            return;
+        }
+
+        let left_index = self.nodes.len();
+        self.nodes.push(BvhNode {
+            bounding_box: BoundingBox::empty(),
+            offset,
+            count: left_count,
+        });
+        self.nodes.push(BvhNode {
+            bounding_box: BoundingBox::empty(),
+            offset: offset + left_count,
+            count: count - left_count,
+        });
+        self.nodes[node_index].offset = left_index;
+        self.nodes[node_index].count = 0;
+
+        self.subdivide(left_index, depth + 1, bounding_boxes, centroids);
+        self.subdivide(left_index + 1, depth + 1, bounding_boxes, centroids);
+    }
+
+    /// Find the cheapest split according to the surface area heuristic by
+    /// binning the primitive centroids along each axis.
+    ///
+    /// ### Returns
+    /// The axis, the index of the first bin on the right side of the split and
+    /// the (unnormalized) cost of the split. `None` if all centroids coincide.
+    fn find_split(
+        indices: &[usize],
+        bounding_boxes: &[BoundingBox],
+        centroids: &[Vec3A],
+        centroid_box: &BoundingBox,
+    ) -> Option<(usize, usize, f32)> {
+        let mut best: Option<(usize, usize, f32)> = None;
+        for axis in 0..3 {
+            let min = centroid_box.min()[axis];
+            let extent = centroid_box.extent()[axis];
+            if extent <= 0.0 {
+                continue;
+            }
+            let scale = BIN_COUNT as f32 / extent;
+
+            let mut bins = [Bin::default(); BIN_COUNT];
+            for &i in indices {
+                let bin = &mut bins[Self::bin_index(centroids[i][axis], min, scale)];
+                bin.count += 1;
+                bin.bounding_box = bin.bounding_box.union(&bounding_boxes[i]);
+            }
+
+            // Sweep from the right to get the area and count right of each
+            // split, then sweep from the left to evaluate the cost.
+            let mut right_area = [0.0; BIN_COUNT];
+            let mut right_count = [0; BIN_COUNT];
+            let mut right_box = BoundingBox::empty();
+            let mut count = 0;
+            for b in (1..BIN_COUNT).rev() {
+                right_box = right_box.union(&bins[b].bounding_box);
+                count += bins[b].count;
+                right_area[b] = right_box.surface_area();
+                right_count[b] = count;
+            }
+
+            let mut left_box = BoundingBox::empty();
+            let mut left_count = 0;
+            for b in 1..BIN_COUNT {
+                left_box = left_box.union(&bins[b - 1].bounding_box);
+                left_count += bins[b - 1].count;
+                if left_count == 0 || right_count[b] == 0 {
+                    continue;
+                }
+                let cost = left_count as f32 * left_box.surface_area()
+                    + right_count[b] as f32 * right_area[b];
+                if best.map_or(true, |(_, _, c)| cost < c) {
+                    best = Some((axis, b, cost));
+                }
+            }
+        }
+        best
+    }
+
+    #[inline]
+    fn bin_index(centroid: f32, min: f32, scale: f32) -> usize {
+        (((centroid - min) * scale) as usize).min(BIN_COUNT - 1)
+    }
+
+    /// Find the closest intersection between the ray and the primitives in
+    /// the hierarchy.
+    ///
+    /// ### Arguments
+    /// - `ray` - The ray to intersect with.
+    /// - `t_min` - The minimum distance to consider.
+    /// - `t_max` - The maximum distance to consider.
+    /// - `intersect_primitive` - Closure that intersects the ray with the
+    ///   primitive of the given index in the range `[t_min, t_max]`.
+    ///
+    /// ### Returns
+    /// The closest hit returned by `intersect_primitive`, `None` if no
+    /// primitive was hit.
+    pub fn intersect<F>(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        mut intersect_primitive: F,
+    ) -> Option<Hit>
+    where
+        F: FnMut(usize, &Ray, f32, f32) -> Option<Hit>,
+    {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let inv_direction = ray.direction.recip();
+        let mut closest_hit: Option<Hit> = None;
+        let mut t_max = t_max;
+
+        let mut stack = [0usize; MAX_DEPTH];
+        let mut stack_size = 0;
+        let mut node = &self.nodes[0];
+        node.bounding_box
+            .hit_distance(ray.origin, inv_direction, t_min, t_max)?;
+
+        loop {
+            if node.is_leaf() {
+                for &i in &self.primitive_indices[node.offset..node.offset + node.count] {
+                    if let Some(hit) = intersect_primitive(i, ray, t_min, t_max) {
+                        t_max = hit.distance;
+                        closest_hit = Some(hit);
+                    }
+                }
+            } else {
+                // Visit the closest child first, so that hits found there can
+                // be used to cull the other child.
+                let (first, second) = (node.offset, node.offset + 1);
+                let t_first = self.nodes[first].bounding_box.hit_distance(
+                    ray.origin,
+                    inv_direction,
+                    t_min,
+                    t_max,
+                );
+                let t_second = self.nodes[second].bounding_box.hit_distance(
+                    ray.origin,
+                    inv_direction,
+                    t_min,
+                    t_max,
+                );
+                match (t_first, t_second) {
+                    (Some(a), Some(b)) => {
+                        let (near, far) = if a <= b {
+                            (first, second)
+                        } else {
+                            (second, first)
+                        };
+                        stack[stack_size] = far;
+                        stack_size += 1;
+                        node = &self.nodes[near];
+                        continue;
+                    }
+                    (Some(_), None) => {
+                        node = &self.nodes[first];
+                        continue;
+                    }
+                    (None, Some(_)) => {
+                        node = &self.nodes[second];
+                        continue;
+                    }
+                    (None, None) => (),
+                }
+            }
+
+            // Pop the next node that can still contain a closer hit.
+            loop {
+                if stack_size == 0 {
+                    return closest_hit;
+                }
+                stack_size -= 1;
+                node = &self.nodes[stack[stack_size]];
+                if node
+                    .bounding_box
+                    .hit_distance(ray.origin, inv_direction, t_min, t_max)
+                    .is_some()
+                {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Returns true if the ray intersects any primitive in the hierarchy.
+    /// Traversal stops at the first intersection found, so this is cheaper
+    /// than `intersect` when the closest hit is not needed, for example for
+    /// shadow rays.
+    ///
+    /// ### Arguments
+    /// - `ray` - The ray to intersect with.
+    /// - `t_min` - The minimum distance to consider.
+    /// - `t_max` - The maximum distance to consider.
+    /// - `intersected_primitive` - Closure that returns true if the ray
+    ///   intersects the primitive of the given index in the range
+    ///   `[t_min, t_max]`.
+    pub fn intersected<F>(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        mut intersected_primitive: F,
+    ) -> bool
+    where
+        F: FnMut(usize, &Ray, f32, f32) -> bool,
+    {
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let inv_direction = ray.direction.recip();
+        let hits_node = |node: &BvhNode| {
+            node.bounding_box
+                .hit_distance(ray.origin, inv_direction, t_min, t_max)
+                .is_some()
+        };
+
+        let mut stack = [0usize; MAX_DEPTH];
+        let mut stack_size = 0;
+        if hits_node(&self.nodes[0]) {
+            stack[0] = 0;
+            stack_size = 1;
+        }
+
+        while stack_size > 0 {
+            stack_size -= 1;
+            let node = &self.nodes[stack[stack_size]];
+            if node.is_leaf() {
+                let indices = &self.primitive_indices[node.offset..node.offset + node.count];
+                if indices
+                    .iter()
+                    .any(|&i| intersected_primitive(i, ray, t_min, t_max))
+                {
+                    return true;
+                }
+            } else {
+                for child in [node.offset, node.offset + 1] {
+                    if hits_node(&self.nodes[child]) {
+                        stack[stack_size] = child;
+                        stack_size += 1;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Find the closest intersection for every ray of the packet. Nodes are
+    /// visited as long as at least one ray of the packet hits them.
+    ///
+    /// ### Arguments
+    /// - `packet` - The rays to intersect with.
+    /// - `t_min` - The minimum distance to consider.
+    /// - `t_max` - The maximum distance to consider for each ray.
+    /// - `intersect_primitive` - Closure that intersects the packet with the
+    ///   primitive of the given index, returning the hits that are closer
+    ///   than the given per ray maximum distance.
+    pub fn intersect_packet<F>(
+        &self,
+        packet: &RayPacket,
+        t_min: f32,
+        t_max: Vec4,
+        mut intersect_primitive: F,
+    ) -> PacketHits
+    where
+        F: FnMut(usize, &RayPacket, f32, Vec4) -> PacketHits,
+    {
+        let mut hits = PacketHits::default();
+        if self.nodes.is_empty() {
+            return hits;
+        }
+
+        let mut t_max = t_max;
+        let mut stack = [0usize; MAX_DEPTH];
+        let mut stack_size = 0;
+        let mut node = &self.nodes[0];
+        if !packet.hit_bounding_box(&node.bounding_box, t_min, t_max).0.any() {
+            return hits;
+        }
+
+        loop {
+            if node.is_leaf() {
+                for &i in &self.primitive_indices[node.offset..node.offset + node.count] {
+                    merge_hits(&mut hits, intersect_primitive(i, packet, t_min, t_max));
+                    t_max = hit_distances(&hits, t_max);
+                }
+            } else {
+                let (first, second) = (node.offset, node.offset + 1);
+                let (mask_first, t_first) =
+                    packet.hit_bounding_box(&self.nodes[first].bounding_box, t_min, t_max);
+                let (mask_second, t_second) =
+                    packet.hit_bounding_box(&self.nodes[second].bounding_box, t_min, t_max);
+                match (mask_first.any(), mask_second.any()) {
+                    (true, true) => {
+                        // Visit the child that the active rays enter first.
+                        let inf = Vec4::splat(f32::INFINITY);
+                        let t_first = Vec4::select(mask_first, t_first, inf).min_element();
+                        let t_second = Vec4::select(mask_second, t_second, inf).min_element();
+                        let (near, far) = if t_first <= t_second {
+                            (first, second)
+                        } else {
+                            (second, first)
+                        };
+                        stack[stack_size] = far;
+                        stack_size += 1;
+                        node = &self.nodes[near];
+                        continue;
+                    }
+                    (true, false) => {
+                        node = &self.nodes[first];
+                        continue;
+                    }
+                    (false, true) => {
+                        node = &self.nodes[second];
+                        continue;
+                    }
+                    (false, false) => (),
+                }
+            }
+
+            loop {
+                if stack_size == 0 {
+                    return hits;
+                }
+                stack_size -= 1;
+                node = &self.nodes[stack[stack_size]];
+                if packet.hit_bounding_box(&node.bounding_box, t_min, t_max).0.any() {
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use super::{BoundingBox, Bvh, Ray, RayPacket, TriangleIndex, TriangleMesh};
+
+use crate::{material::Material, test_utils::test_rng};
+
+use glam::{Vec3A, Vec4};
+use rand::{rngs::StdRng, Rng};
+
+/// Random vector with components in [0, 1).
+fn random_vector(rng: &mut StdRng) -> Vec3A {
+    Vec3A::new(rng.gen(), rng.gen(), rng.gen())
+}
+
+/// Create a mesh of `count` small triangles scattered in a 10x10x10 cube.
+fn random_mesh(rng: &mut StdRng, count: usize) -> (TriangleMesh, Vec<TriangleIndex>) {
+    let mut positions = Vec::with_capacity(3 * count);
+    let mut indices = Vec::with_capacity(count);
+    for i in 0..count {
+        let center = random_vector(rng) * 10.0 - 5.0;
+        for _ in 0..3 {
+            let offset = random_vector(rng) - 0.5;
+            positions.push(center + offset);
+        }
+        indices.push(TriangleIndex::new((3 * i, 3 * i + 1, 3 * i + 2), 0, 0));
+    }
+    let mesh = TriangleMesh::new(positions, vec![Vec3A::Y], vec![Material::default()]);
+    (mesh, indices)
+}
+
+fn random_ray(rng: &mut StdRng) -> Ray {
+    let origin = random_vector(rng) * 20.0 - 10.0;
+    let target = random_vector(rng) * 4.0 - 2.0;
+    Ray::new(origin, (target - origin).normalize())
+}
+
+#[test]
+fn bounding_box_operations_test() {
+    let a = BoundingBox::new(Vec3A::ZERO, Vec3A::ONE);
+    let b = BoundingBox::new(Vec3A::splat(-1.0), Vec3A::new(0.5, 0.5, 2.0));
+    let union = a.union(&b);
+
+    assert_eq!(union, BoundingBox::new(Vec3A::splat(-1.0), Vec3A::new(1.0, 1.0, 2.0)));
+    assert_eq!(a.centroid(), Vec3A::splat(0.5));
+    assert_eq!(a.surface_area(), 6.0);
+    assert_eq!(BoundingBox::empty().surface_area(), 0.0);
+    assert_eq!(BoundingBox::empty().union(&a), a);
+}
+
+#[test]
+fn empty_bvh_test() {
+    let bvh = Bvh::new(&[]);
+    assert!(bvh.is_empty());
+    assert!(bvh
+        .intersect(&random_ray(&mut test_rng()), 0.0, f32::INFINITY, |_, _, _, _| unreachable!())
+        .is_none());
+}
+
+#[test]
+fn bvh_matches_brute_force_test() {
+    let mut rng = test_rng();
+    let (mesh, indices) = random_mesh(&mut rng, 500);
+    let boxes: Vec<BoundingBox> = indices
+        .iter()
+        .map(|t| mesh.get_triangle(t).bounding_box())
+        .collect();
+    let bvh = Bvh::new(&boxes);
+    assert!(bvh.node_count() > 1);
+
+    for _ in 0..1000 {
+        let ray = random_ray(&mut rng);
+        let expected = indices
+            .iter()
+            .filter_map(|t| mesh.intersect_triangle(&ray, t, 0.0, f32::INFINITY))
+            .reduce(|a, b| a.closest_hit(b));
+        let actual = bvh.intersect(&ray, 0.0, f32::INFINITY, |i, ray, t_min, t_max| {
+            mesh.intersect_triangle(ray, &indices[i], t_min, t_max)
+        });
+
+        match (expected, actual) {
+            (Some(e), Some(a)) => assert_eq!(e.distance, a.distance),
+            (None, None) => (),
+            (e, a) => panic!("Expected {:?}, got {:?}", e, a),
+        }
+    }
+}
+
+#[test]
+fn packet_matches_single_rays_test() {
+    let mut rng = test_rng();
+    let (mesh, indices) = random_mesh(&mut rng, 500);
+    let boxes: Vec<BoundingBox> = indices
+        .iter()
+        .map(|t| mesh.get_triangle(t).bounding_box())
+        .collect();
+    let bvh = Bvh::new(&boxes);
+
+    for _ in 0..250 {
+        let packet = RayPacket::new([(); 4].map(|_| random_ray(&mut rng)));
+        let t_max = Vec4::new(f32::INFINITY, 20.0, 10.0, 5.0);
+        let hits = bvh.intersect_packet(&packet, 0.0, t_max, |i, packet, t_min, t_max| {
+            mesh.intersect_triangle_packet(packet, &indices[i], t_min, t_max)
+        });
+
+        for (lane, ray) in packet.rays().iter().enumerate() {
+            let expected = bvh.intersect(ray, 0.0, t_max[lane], |i, ray, t_min, t_max| {
+                mesh.intersect_triangle(ray, &indices[i], t_min, t_max)
+            });
+            match (expected, &hits[lane]) {
+                (Some(e), Some(a)) => assert!((e.distance - a.distance).abs() < 1e-4),
+                (None, None) => (),
+                (e, a) => panic!("Expected {:?}, got {:?}", e, a),
+            }
+        }
+    }
+}
+
+#[test]
+fn any_hit_matches_closest_hit_test() {
+    let mut rng = test_rng();
+    let (mesh, indices) = random_mesh(&mut rng, 500);
+    let boxes: Vec<BoundingBox> = indices
+        .iter()
+        .map(|t| mesh.get_triangle(t).bounding_box())
+        .collect();
+    let bvh = Bvh::new(&boxes);
+
+    for _ in 0..1000 {
+        let ray = random_ray(&mut rng);
+        let t_max = rng.gen::<f32>() * 20.0;
+        let closest = bvh.intersect(&ray, 0.0, t_max, |i, ray, t_min, t_max| {
+            mesh.intersect_triangle(ray, &indices[i], t_min, t_max)
+        });
+        let any = bvh.intersected(&ray, 0.0, t_max, |i, ray, t_min, t_max| {
+            mesh.triangle_intersected(ray, &indices[i], t_min, t_max)
+        });
+        assert_eq!(closest.is_some(), any);
+    }
+}
@@ -2,15 +2,26 @@ pub mod triangle;
 pub mod trianglemesh;
 pub mod ray;
 pub mod aabb;
+pub mod bvh;
+pub mod packet;
+pub mod shape;
 
 pub use aabb::BoundingBox;
+pub use bvh::Bvh;
+pub use packet::{PacketHits, RayPacket, PACKET_SIZE};
 pub use trianglemesh::TriangleMesh;
 pub use ray::{Ray, Hit};
-pub use triangle::{Triangle, TriangleIndex};
+pub use shape::Shape;
+pub use triangle::{IntersectionAlgorithm, Triangle, TriangleIndex};
 
 /// Position in 3D space.
-type Position = glam::Vec3A;
+pub(crate) type Position = glam::Vec3A;
 /// Normal vector in 3D space. Should be normalized, but this is not enforced.
-type Normal = glam::Vec3A;
+pub(crate) type Normal = glam::Vec3A;
 /// Texture coordinate in 2D space.
-type TexCoord = glam::Vec2;
+pub(crate) type TexCoord = glam::Vec2;
+/// Linear RGB color, with components in [0, 1].
+pub(crate) type Color = glam::Vec3A;
+
+#[cfg(test)]
+mod bvh_tests;
@@ -0,0 +1,170 @@
+use super::{aabb::ROBUST_EXIT_SCALE, BoundingBox, Hit, Ray};
+
+use std::ops::Sub;
+
+use glam::{BVec4A, Vec3A, Vec4};
+
+/// Number of rays in a ray packet.
+pub const PACKET_SIZE: usize = 4;
+
+/// Closest hits of all rays in a packet. A lane is `None` if the ray did not
+/// hit anything.
+pub type PacketHits = [Option<Hit>; PACKET_SIZE];
+
+/// Vector with three components where every component holds the value for all
+/// rays in a packet, ie structure of arrays layout.
+#[derive(Debug, Clone, Copy)]
+pub struct Vec3x4 {
+    pub x: Vec4,
+    pub y: Vec4,
+    pub z: Vec4,
+}
+
+impl Vec3x4 {
+    #[inline]
+    pub fn new(x: Vec4, y: Vec4, z: Vec4) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Creates a packed vector with the same vector in all lanes.
+    #[inline]
+    pub fn splat(v: Vec3A) -> Self {
+        Self::new(Vec4::splat(v.x), Vec4::splat(v.y), Vec4::splat(v.z))
+    }
+
+    /// Creates a packed vector from one vector per lane.
+    #[inline]
+    pub fn from_lanes(v: [Vec3A; PACKET_SIZE]) -> Self {
+        Self::new(
+            Vec4::new(v[0].x, v[1].x, v[2].x, v[3].x),
+            Vec4::new(v[0].y, v[1].y, v[2].y, v[3].y),
+            Vec4::new(v[0].z, v[1].z, v[2].z, v[3].z),
+        )
+    }
+
+    #[inline]
+    pub fn dot(&self, rhs: &Self) -> Vec4 {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+
+    #[inline]
+    pub fn cross(&self, rhs: &Self) -> Self {
+        Self::new(
+            self.y * rhs.z - self.z * rhs.y,
+            self.z * rhs.x - self.x * rhs.z,
+            self.x * rhs.y - self.y * rhs.x,
+        )
+    }
+}
+
+impl Sub for Vec3x4 {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+
+/// A packet of coherent rays, for example the primary rays of a 2x2 pixel
+/// tile, that are intersected together using SIMD instructions.
+#[derive(Debug, Clone, Copy)]
+pub struct RayPacket {
+    rays: [Ray; PACKET_SIZE],
+    origin: Vec3x4,
+    direction: Vec3x4,
+    inv_direction: Vec3x4,
+}
+
+impl RayPacket {
+    /// Creates a new packet from the given rays.
+    #[inline]
+    pub fn new(rays: [Ray; PACKET_SIZE]) -> Self {
+        let origin = Vec3x4::from_lanes(rays.map(|r| r.origin));
+        let direction = Vec3x4::from_lanes(rays.map(|r| r.direction));
+        let inv_direction = Vec3x4::new(
+            direction.x.recip(),
+            direction.y.recip(),
+            direction.z.recip(),
+        );
+        Self {
+            rays,
+            origin,
+            direction,
+            inv_direction,
+        }
+    }
+
+    /// Get the individual rays of the packet.
+    #[inline]
+    pub fn rays(&self) -> &[Ray; PACKET_SIZE] {
+        &self.rays
+    }
+
+    /// Get the origins of the rays.
+    #[inline]
+    pub fn origin(&self) -> &Vec3x4 {
+        &self.origin
+    }
+
+    /// Get the directions of the rays.
+    #[inline]
+    pub fn direction(&self) -> &Vec3x4 {
+        &self.direction
+    }
+
+    /// Test the rays of the packet against a bounding box.
+    ///
+    /// ### Returns
+    /// A mask of the rays that hit the box within `[t_min, t_max]` and the
+    /// distance where each ray enters the box.
+    #[inline]
+    pub fn hit_bounding_box(
+        &self,
+        bounding_box: &BoundingBox,
+        t_min: f32,
+        t_max: Vec4,
+    ) -> (BVec4A, Vec4) {
+        let (min, max) = (bounding_box.min(), bounding_box.max());
+        let (o, inv) = (&self.origin, &self.inv_direction);
+
+        let tx0 = (Vec4::splat(min.x) - o.x) * inv.x;
+        let tx1 = (Vec4::splat(max.x) - o.x) * inv.x;
+        let ty0 = (Vec4::splat(min.y) - o.y) * inv.y;
+        let ty1 = (Vec4::splat(max.y) - o.y) * inv.y;
+        let tz0 = (Vec4::splat(min.z) - o.z) * inv.z;
+        let tz1 = (Vec4::splat(max.z) - o.z) * inv.z;
+
+        let t_enter = tx0
+            .min(tx1)
+            .max(ty0.min(ty1))
+            .max(tz0.min(tz1))
+            .max(Vec4::splat(t_min));
+        let t_exit = (tx0.max(tx1).min(ty0.max(ty1)).min(tz0.max(tz1)) * ROBUST_EXIT_SCALE).min(t_max);
+
+        (t_exit.cmpge(t_enter), t_enter)
+    }
+}
+
+/// Returns the maximum distance of each lane, ie the distance of the hit if
+/// there is one and `t_max` otherwise.
+#[inline]
+pub fn hit_distances(hits: &PacketHits, t_max: Vec4) -> Vec4 {
+    Vec4::new(
+        hits[0].as_ref().map_or(t_max.x, |h| h.distance),
+        hits[1].as_ref().map_or(t_max.y, |h| h.distance),
+        hits[2].as_ref().map_or(t_max.z, |h| h.distance),
+        hits[3].as_ref().map_or(t_max.w, |h| h.distance),
+    )
+}
+
+/// Merge the hits of `other` into `hits`, keeping the closest hit per lane.
+#[inline]
+pub fn merge_hits(hits: &mut PacketHits, other: PacketHits) {
+    for (hit, other) in hits.iter_mut().zip(other) {
+        *hit = match (hit.take(), other) {
+            (Some(a), Some(b)) => Some(a.closest_hit(b)),
+            (a, b) => a.or(b),
+        };
+    }
+}
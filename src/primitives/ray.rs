@@ -1,8 +1,11 @@
-use glam::Vec3A;
+use std::sync::Arc;
+
+use glam::{Vec2, Vec3A};
+use rand::Rng;
 
 use crate::material::Material;
 
-use super::{Normal, Position, TriangleIndex, TriangleMesh};
+use super::{Color, Normal, Position, TexCoord, TriangleIndex, TriangleMesh};
 
 /// A light ray in 3D space. The ray is defined by an origin and a direction.
 /// The directions is not necessarily normalized.
@@ -57,27 +60,100 @@ impl Default for Ray {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Distance new rays are moved away from the surface they start on, relative
+/// to the magnitude of the hit point. Prevents rays from hitting the surface
+/// they start on due to floating point errors.
+const RAY_OFFSET: f32 = 1e-4;
+
+/// Data about an intersection between a ray and a surface in the scene.
+#[derive(Debug, Clone)]
 pub struct Hit {
     pub hit_point: Position,
     pub distance: f32,
     pub incoming: Ray,
-    pub triangle_index: TriangleIndex,
+    /// The shading normal, for triangles the vertex normals interpolated at
+    /// the hit point.
+    pub normal: Normal,
+    /// The normal of the actual surface that was hit. Differs from `normal`
+    /// for smooth shaded triangles.
+    pub geometric_normal: Normal,
+    /// Barycentric coordinates `(u, v)` of the hit point, ie the weights of
+    /// the second and third vertex of the triangle. Zero for surfaces that
+    /// are not triangles.
+    pub barycentric: Vec2,
+    /// Texture coordinates at the hit point, `None` if the surface has no
+    /// texture coordinates.
+    pub tex_coord: Option<TexCoord>,
+    /// Derivatives of the hit point with respect to the texture coordinates,
+    /// `(dp/du, dp/dv)`, in the same space as the hit point. `None` if the
+    /// surface has no (or degenerate) texture coordinates.
+    pub tex_coord_derivatives: Option<(Vec3A, Vec3A)>,
+    /// Vertex color at the hit point, `None` if the surface has no vertex
+    /// colors.
+    pub color: Option<Color>,
+    /// Index of the material of the triangle in the materials of the mesh.
+    /// Zero for surfaces that carry their own `material`.
+    pub material_index: usize,
+    /// The material of a surface that is not part of a triangle mesh, for
+    /// example an analytic shape. `None` for triangles.
+    pub material: Option<Arc<Material>>,
+    /// The triangle that was hit, `None` if the surface is not part of a
+    /// triangle mesh.
+    pub triangle_index: Option<TriangleIndex>,
 }
 
 impl Hit {
+    /// Creates a hit on a triangle. The material is the material of the
+    /// triangle.
     #[inline]
     pub fn new(
         hit_point: Position,
         distance: f32,
         incoming: Ray,
+        normal: Normal,
+        geometric_normal: Normal,
+        barycentric: Vec2,
         triangle_index: TriangleIndex,
     ) -> Self {
         Self {
             hit_point,
             distance,
             incoming,
-            triangle_index,
+            normal,
+            geometric_normal,
+            barycentric,
+            tex_coord: None,
+            tex_coord_derivatives: None,
+            color: None,
+            material_index: triangle_index.material_index(),
+            material: None,
+            triangle_index: Some(triangle_index),
+        }
+    }
+
+    /// Creates a hit on a surface that is not part of a triangle mesh, for
+    /// example an analytic shape, with the material of the surface.
+    #[inline]
+    pub fn from_surface(
+        hit_point: Position,
+        distance: f32,
+        incoming: Ray,
+        normal: Normal,
+        material: Arc<Material>,
+    ) -> Self {
+        Self {
+            hit_point,
+            distance,
+            incoming,
+            normal,
+            geometric_normal: normal,
+            barycentric: Vec2::ZERO,
+            tex_coord: None,
+            tex_coord_derivatives: None,
+            color: None,
+            material_index: 0,
+            material: Some(material),
+            triangle_index: None,
         }
     }
 
@@ -91,21 +167,42 @@ impl Hit {
         }
     }
 
-    /// Returns the normal index of the triangle that was hit.
+    /// Returns the normal indices of the triangle that was hit, `None` if the
+    /// surface is not a triangle.
     ///
-    /// Use this to get the normal of the triangle from the mesh.
+    /// Use this to get the vertex normals of the triangle from the mesh.
     #[inline]
-    pub fn normal_index(&self) -> usize {
-        self.triangle_index.normal_index()
+    pub fn normal_indices(&self) -> Option<(usize, usize, usize)> {
+        self.triangle_index.map(|t| t.normal_indices())
     }
 
-    /// Returns the normal of the triangle that was hit, using the mesh.
-    ///
-    /// Mesh is needed because the `hit` struct only contains the index of the
-    /// normal.
+    /// Returns the shading normal of the surface at the hit point, in the same
+    /// space as the hit point.
+    #[inline]
+    pub fn normal(&self) -> Normal {
+        self.normal
+    }
+
+    /// Returns the texture coordinates at the hit point, `None` if the surface
+    /// has no texture coordinates.
     #[inline]
-    pub fn normal(&self, mesh: &TriangleMesh) -> Normal {
-        mesh.triangle_normals()[self.normal_index()]
+    pub fn tex_coord(&self) -> Option<TexCoord> {
+        self.tex_coord
+    }
+
+    /// Creates a ray leaving the surface at the hit point in the given
+    /// direction. The origin is moved slightly along the geometric normal, to
+    /// the side the ray is leaving to, so the ray does not hit the surface it
+    /// starts on.
+    #[inline]
+    pub fn spawn_ray(&self, direction: Vec3A) -> Ray {
+        let offset = RAY_OFFSET * self.hit_point.abs().max_element().max(1.0);
+        let offset = if direction.dot(self.geometric_normal) < 0.0 {
+            -offset
+        } else {
+            offset
+        };
+        Ray::new(self.hit_point + self.geometric_normal * offset, direction)
     }
 
     /// Returns the material index of the triangle that was hit.
@@ -113,31 +210,40 @@ impl Hit {
     /// Use this to get the material of the triangle from the mesh.
     #[inline]
     pub fn material_index(&self) -> usize {
-        self.triangle_index.material_index()
+        self.material_index
     }
 
-    /// Returns the material of the triangle that was hit, using the mesh.
+    /// Returns the material of the surface that was hit. Surfaces that are
+    /// not part of a triangle mesh carry their own material, the material of
+    /// a triangle is looked up in the mesh.
     ///
-    /// Mesh is needed because the `hit` struct only contains the index of the
-    /// material.
+    /// ### Panics
+    /// If the hit is on a triangle whose material index is not a material of
+    /// the mesh.
     #[inline]
-    pub fn material(&self, mesh: &TriangleMesh) -> Material {
-        mesh.materials()[self.material_index()]
+    pub fn material<'a>(&'a self, mesh: &'a TriangleMesh) -> &'a Material {
+        match &self.material {
+            Some(material) => material,
+            None => &mesh.materials()[self.material_index()],
+        }
     }
 
     /// Returns a random outgoing ray from the hit point.
     #[inline]
-    pub fn random_outgoing_ray(&self, mesh: &TriangleMesh) -> Ray {
-        let triangle = mesh.get_triangle(&self.triangle_index);
+    pub fn random_outgoing_ray(&self) -> Ray {
+        self.random_outgoing_ray_with(&mut rand::thread_rng())
+    }
 
+    /// Returns a random outgoing ray from the hit point, using the random
+    /// numbers of `rng`.
+    pub(crate) fn random_outgoing_ray_with<R: Rng + ?Sized>(&self, rng: &mut R) -> Ray {
         // Local coordinate system
-        let up = *triangle.normal;
-        let right = (*triangle.vertex_positions.1 - *triangle.vertex_positions.0).normalize();
-        let forward = right.cross(up);
+        let up = self.normal;
+        let (right, forward) = up.any_orthonormal_pair();
 
         // Random spherical coordinates (theta, phi) to get a random direction
-        let theta = rand::random::<f32>() * 2.0 * std::f32::consts::PI;
-        let phi = rand::random::<f32>() * std::f32::consts::PI;
+        let theta = rng.gen::<f32>() * 2.0 * std::f32::consts::PI;
+        let phi = rng.gen::<f32>() * std::f32::consts::PI;
 
         let spherical_point =
             Vec3A::new(phi.sin() * theta.cos(), phi.sin() * theta.sin(), phi.cos());
@@ -146,6 +252,6 @@ impl Hit {
             right * spherical_point.x + forward * spherical_point.y + up * spherical_point.z;
         let direction = direction.normalize();
 
-        Ray::new(self.hit_point, direction)
+        self.spawn_ray(direction)
     }
 }
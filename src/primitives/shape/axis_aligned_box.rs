@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use crate::{
+    material::Material,
+    traits::{Bounded, Intersectable},
+};
+
+use crate::primitives::{BoundingBox, Hit, Normal, Position, Ray};
+
+use glam::Vec3A;
+
+/// Solid axis aligned box defined by its minimum and maximum corners.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AxisAlignedBox {
+    pub min: Position,
+    pub max: Position,
+    pub material: Arc<Material>,
+}
+
+impl AxisAlignedBox {
+    /// Creates a new box. The material can be shared with other shapes by
+    /// passing an `Arc<Material>`.
+    #[inline]
+    pub fn new(min: Position, max: Position, material: impl Into<Arc<Material>>) -> Self {
+        Self {
+            min: min.min(max),
+            max: min.max(max),
+            material: material.into(),
+        }
+    }
+
+    /// Returns the distance to the closest intersection in `(t_min, t_max)`.
+    /// If the ray starts inside the box, this is where it exits the box.
+    #[inline]
+    fn hit_distance(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<f32> {
+        let inv_dir = ray.direction.recip();
+        let t0 = (self.min - ray.origin) * inv_dir;
+        let t1 = (self.max - ray.origin) * inv_dir;
+
+        let t_enter = t0.min(t1).max_element();
+        let t_exit = t0.max(t1).min_element();
+        if t_exit < t_enter {
+            return None;
+        }
+
+        [t_enter, t_exit]
+            .into_iter()
+            .find(|&t| t > t_min && t < t_max)
+    }
+
+    /// Returns the outward normal of the face closest to the given point on
+    /// the surface of the box.
+    #[inline]
+    fn normal_at(&self, point: Position) -> Normal {
+        let center = (self.min + self.max) * 0.5;
+        let half_extent = ((self.max - self.min) * 0.5).max(Vec3A::splat(f32::EPSILON));
+        let local = (point - center) / half_extent;
+        let abs = local.abs();
+        if abs.x >= abs.y && abs.x >= abs.z {
+            Vec3A::X * local.x.signum()
+        } else if abs.y >= abs.z {
+            Vec3A::Y * local.y.signum()
+        } else {
+            Vec3A::Z * local.z.signum()
+        }
+    }
+}
+
+impl Bounded for AxisAlignedBox {
+    #[inline]
+    fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::new(self.min, self.max)
+    }
+}
+
+impl Intersectable for AxisAlignedBox {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+        let t = self.hit_distance(ray, t_min, t_max)?;
+        let hit_point = ray.at(t);
+        Some(Hit::from_surface(
+            hit_point,
+            t,
+            *ray,
+            self.normal_at(hit_point),
+            self.material.clone(),
+        ))
+    }
+
+    fn intersected(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.hit_distance(ray, t_min, t_max).is_some()
+    }
+}
@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use crate::{
+    material::Material,
+    traits::{Bounded, Intersectable},
+};
+
+use crate::primitives::{BoundingBox, Hit, Normal, Position, Ray};
+
+use super::Plane;
+
+use glam::Vec3A;
+
+/// Flat disk defined by its center, normal and radius.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disk {
+    pub center: Position,
+    pub normal: Normal,
+    pub radius: f32,
+    pub material: Arc<Material>,
+}
+
+impl Disk {
+    /// Creates a new disk. The normal is normalized. The material can be
+    /// shared with other shapes by passing an `Arc<Material>`.
+    #[inline]
+    pub fn new(
+        center: Position,
+        normal: Normal,
+        radius: f32,
+        material: impl Into<Arc<Material>>,
+    ) -> Self {
+        Self {
+            center,
+            normal: normal.normalize(),
+            radius,
+            material: material.into(),
+        }
+    }
+
+    /// Returns the distance to the intersection if it is in `(t_min, t_max)`.
+    #[inline]
+    fn hit_distance(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<f32> {
+        let t = Plane::hit_distance(self.center, self.normal, ray, t_min, t_max)?;
+        if (ray.at(t) - self.center).length_squared() <= self.radius * self.radius {
+            Some(t)
+        } else {
+            None
+        }
+    }
+}
+
+impl Bounded for Disk {
+    /// The extent of the disk along each axis is the radius scaled by the
+    /// sine of the angle between the axis and the normal.
+    #[inline]
+    fn bounding_box(&self) -> BoundingBox {
+        let extent = (Vec3A::ONE - self.normal * self.normal).max(Vec3A::ZERO);
+        let extent = Vec3A::new(extent.x.sqrt(), extent.y.sqrt(), extent.z.sqrt()) * self.radius;
+        BoundingBox::new(self.center - extent, self.center + extent)
+    }
+}
+
+impl Intersectable for Disk {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+        let t = self.hit_distance(ray, t_min, t_max)?;
+        Some(Hit::from_surface(
+            ray.at(t),
+            t,
+            *ray,
+            self.normal,
+            self.material.clone(),
+        ))
+    }
+
+    fn intersected(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.hit_distance(ray, t_min, t_max).is_some()
+    }
+}
@@ -0,0 +1,110 @@
+pub mod axis_aligned_box;
+pub mod disk;
+pub mod plane;
+pub mod sphere;
+
+pub use axis_aligned_box::AxisAlignedBox;
+pub use disk::Disk;
+pub use plane::Plane;
+pub use sphere::Sphere;
+
+use std::sync::Arc;
+
+use crate::{
+    material::Material,
+    traits::{Bounded, Intersectable},
+};
+
+use super::{BoundingBox, Hit, Ray};
+
+/// Analytic shape that can be placed in the scene next to triangle mesh
+/// objects. Shapes carry their own material and compute their normals
+/// directly, without going through the triangle mesh.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Sphere(Sphere),
+    Plane(Plane),
+    Disk(Disk),
+    AxisAlignedBox(AxisAlignedBox),
+}
+
+impl Shape {
+    /// Returns the material of the shape.
+    #[inline]
+    pub fn material(&self) -> &Arc<Material> {
+        match self {
+            Shape::Sphere(s) => &s.material,
+            Shape::Plane(p) => &p.material,
+            Shape::Disk(d) => &d.material,
+            Shape::AxisAlignedBox(b) => &b.material,
+        }
+    }
+
+    /// Returns true if the shape has a finite bounding box, ie if it can be
+    /// put in a bounding volume hierarchy.
+    #[inline]
+    pub fn is_bounded(&self) -> bool {
+        !matches!(self, Shape::Plane(_))
+    }
+}
+
+impl Bounded for Shape {
+    #[inline]
+    fn bounding_box(&self) -> BoundingBox {
+        match self {
+            Shape::Sphere(s) => s.bounding_box(),
+            Shape::Plane(p) => p.bounding_box(),
+            Shape::Disk(d) => d.bounding_box(),
+            Shape::AxisAlignedBox(b) => b.bounding_box(),
+        }
+    }
+}
+
+impl Intersectable for Shape {
+    #[inline]
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+        match self {
+            Shape::Sphere(s) => s.intersect(ray, t_min, t_max),
+            Shape::Plane(p) => p.intersect(ray, t_min, t_max),
+            Shape::Disk(d) => d.intersect(ray, t_min, t_max),
+            Shape::AxisAlignedBox(b) => b.intersect(ray, t_min, t_max),
+        }
+    }
+
+    #[inline]
+    fn intersected(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        match self {
+            Shape::Sphere(s) => s.intersected(ray, t_min, t_max),
+            Shape::Plane(p) => p.intersected(ray, t_min, t_max),
+            Shape::Disk(d) => d.intersected(ray, t_min, t_max),
+            Shape::AxisAlignedBox(b) => b.intersected(ray, t_min, t_max),
+        }
+    }
+}
+
+impl From<Sphere> for Shape {
+    fn from(sphere: Sphere) -> Self {
+        Shape::Sphere(sphere)
+    }
+}
+
+impl From<Plane> for Shape {
+    fn from(plane: Plane) -> Self {
+        Shape::Plane(plane)
+    }
+}
+
+impl From<Disk> for Shape {
+    fn from(disk: Disk) -> Self {
+        Shape::Disk(disk)
+    }
+}
+
+impl From<AxisAlignedBox> for Shape {
+    fn from(aabox: AxisAlignedBox) -> Self {
+        Shape::AxisAlignedBox(aabox)
+    }
+}
+
+#[cfg(test)]
+mod shape_tests;
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use crate::{
+    material::Material,
+    traits::{Bounded, Intersectable},
+};
+
+use crate::primitives::{BoundingBox, Hit, Normal, Position, Ray};
+
+use glam::Vec3A;
+
+/// Infinite plane defined by a point on the plane and the plane normal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plane {
+    pub point: Position,
+    pub normal: Normal,
+    pub material: Arc<Material>,
+}
+
+impl Plane {
+    /// Creates a new plane. The normal is normalized. The material can be
+    /// shared with other shapes by passing an `Arc<Material>`.
+    #[inline]
+    pub fn new(point: Position, normal: Normal, material: impl Into<Arc<Material>>) -> Self {
+        Self {
+            point,
+            normal: normal.normalize(),
+            material: material.into(),
+        }
+    }
+
+    /// Returns the distance to the intersection if it is in `(t_min, t_max)`.
+    #[inline]
+    pub(super) fn hit_distance(
+        point: Position,
+        normal: Normal,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<f32> {
+        let denominator = ray.direction.dot(normal);
+        if denominator.abs() < f32::EPSILON {
+            return None;
+        }
+        let t = (point - ray.origin).dot(normal) / denominator;
+        if t > t_min && t < t_max {
+            Some(t)
+        } else {
+            None
+        }
+    }
+}
+
+impl Bounded for Plane {
+    /// Planes are unbounded, so the bounding box is infinite.
+    #[inline]
+    fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::new(Vec3A::splat(f32::NEG_INFINITY), Vec3A::splat(f32::INFINITY))
+    }
+}
+
+impl Intersectable for Plane {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+        let t = Self::hit_distance(self.point, self.normal, ray, t_min, t_max)?;
+        Some(Hit::from_surface(
+            ray.at(t),
+            t,
+            *ray,
+            self.normal,
+            self.material.clone(),
+        ))
+    }
+
+    fn intersected(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        Self::hit_distance(self.point, self.normal, ray, t_min, t_max).is_some()
+    }
+}
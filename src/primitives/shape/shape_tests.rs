@@ -0,0 +1,89 @@
+use super::{AxisAlignedBox, Disk, Plane, Shape, Sphere};
+
+use crate::{
+    material::Material,
+    primitives::{Ray, TriangleMesh},
+    traits::{Bounded, Intersectable},
+};
+
+use glam::Vec3A;
+
+#[test]
+fn sphere_intersection_test() {
+    let material = Material {
+        diffuse_color: Vec3A::X,
+        ..Material::default()
+    };
+    let sphere = Sphere::new(Vec3A::new(0.0, 0.0, -5.0), 1.0, material.clone());
+    let ray = Ray::new(Vec3A::ZERO, Vec3A::NEG_Z);
+
+    let hit = sphere.intersect(&ray, 0.0, f32::INFINITY).unwrap();
+    assert_eq!(hit.distance, 4.0);
+    assert_eq!(hit.normal, Vec3A::Z);
+    assert!(hit.triangle_index.is_none());
+
+    // The material of the shape is used, not a material of the mesh.
+    let mesh = TriangleMesh::new(Vec::new(), Vec::new(), Vec::new());
+    assert_eq!(hit.material(&mesh), &material);
+    assert_eq!(Shape::from(sphere.clone()).material().as_ref(), &material);
+
+    // Starting inside the sphere hits the far side.
+    let ray = Ray::new(Vec3A::new(0.0, 0.0, -5.0), Vec3A::NEG_Z);
+    let hit = sphere.intersect(&ray, 0.0, f32::INFINITY).unwrap();
+    assert_eq!(hit.distance, 1.0);
+    assert_eq!(hit.normal, Vec3A::NEG_Z);
+
+    assert!(!sphere.intersected(&Ray::new(Vec3A::ZERO, Vec3A::X), 0.0, f32::INFINITY));
+}
+
+#[test]
+fn plane_intersection_test() {
+    let plane = Plane::new(
+        Vec3A::new(0.0, -1.0, 0.0),
+        Vec3A::new(0.0, 2.0, 0.0),
+        Material::default(),
+    );
+    let ray = Ray::new(Vec3A::ZERO, Vec3A::new(1.0, -1.0, 0.0));
+
+    let hit = plane.intersect(&ray, 0.0, f32::INFINITY).unwrap();
+    assert_eq!(hit.hit_point, Vec3A::new(1.0, -1.0, 0.0));
+    assert_eq!(hit.normal, Vec3A::Y);
+    assert!(!plane.intersected(&ray, 0.0, 0.5));
+    assert!(!Shape::from(plane).is_bounded());
+}
+
+#[test]
+fn disk_intersection_test() {
+    let disk = Disk::new(Vec3A::new(0.0, 0.0, -2.0), Vec3A::Z, 1.0, Material::default());
+
+    let ray = Ray::new(Vec3A::new(0.5, 0.5, 0.0), Vec3A::NEG_Z);
+    assert_eq!(disk.intersect(&ray, 0.0, f32::INFINITY).unwrap().distance, 2.0);
+
+    let ray = Ray::new(Vec3A::new(1.0, 1.0, 0.0), Vec3A::NEG_Z);
+    assert!(disk.intersect(&ray, 0.0, f32::INFINITY).is_none());
+
+    let bounding_box = disk.bounding_box();
+    assert_eq!(bounding_box.min(), Vec3A::new(-1.0, -1.0, -2.0));
+    assert_eq!(bounding_box.max(), Vec3A::new(1.0, 1.0, -2.0));
+}
+
+#[test]
+fn axis_aligned_box_intersection_test() {
+    let aabox = AxisAlignedBox::new(
+        Vec3A::new(1.0, -1.0, -1.0),
+        Vec3A::new(3.0, 1.0, 1.0),
+        Material::default(),
+    );
+
+    let ray = Ray::new(Vec3A::ZERO, Vec3A::X);
+    let hit = aabox.intersect(&ray, 0.0, f32::INFINITY).unwrap();
+    assert_eq!(hit.distance, 1.0);
+    assert_eq!(hit.normal, Vec3A::NEG_X);
+
+    let ray = Ray::new(Vec3A::new(2.0, 5.0, 0.5), Vec3A::NEG_Y);
+    let hit = aabox.intersect(&ray, 0.0, f32::INFINITY).unwrap();
+    assert_eq!(hit.distance, 4.0);
+    assert_eq!(hit.normal, Vec3A::Y);
+
+    assert!(!aabox.intersected(&Ray::new(Vec3A::ZERO, Vec3A::NEG_X), 0.0, f32::INFINITY));
+}
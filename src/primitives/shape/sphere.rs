@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use crate::{
+    material::Material,
+    traits::{Bounded, Intersectable},
+};
+
+use crate::primitives::{BoundingBox, Hit, Position, Ray};
+
+use glam::Vec3A;
+
+/// Sphere defined by its center and radius.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sphere {
+    pub center: Position,
+    pub radius: f32,
+    pub material: Arc<Material>,
+}
+
+impl Sphere {
+    /// Creates a new sphere. The material can be shared with other shapes by
+    /// passing an `Arc<Material>`.
+    #[inline]
+    pub fn new(center: Position, radius: f32, material: impl Into<Arc<Material>>) -> Self {
+        Self {
+            center,
+            radius,
+            material: material.into(),
+        }
+    }
+
+    /// Returns the distance to the closest intersection in `(t_min, t_max)`.
+    #[inline]
+    fn hit_distance(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<f32> {
+        let oc = ray.origin - self.center;
+        let a = ray.direction.length_squared();
+        let half_b = oc.dot(ray.direction);
+        let c = oc.length_squared() - self.radius * self.radius;
+
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let sqrt_d = discriminant.sqrt();
+        [(-half_b - sqrt_d) / a, (-half_b + sqrt_d) / a]
+            .into_iter()
+            .find(|&t| t > t_min && t < t_max)
+    }
+}
+
+impl Bounded for Sphere {
+    #[inline]
+    fn bounding_box(&self) -> BoundingBox {
+        let r = Vec3A::splat(self.radius.abs());
+        BoundingBox::new(self.center - r, self.center + r)
+    }
+}
+
+impl Intersectable for Sphere {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+        let t = self.hit_distance(ray, t_min, t_max)?;
+        let hit_point = ray.at(t);
+        let normal = (hit_point - self.center) / self.radius;
+        Some(Hit::from_surface(hit_point, t, *ray, normal, self.material.clone()))
+    }
+
+    fn intersected(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.hit_distance(ray, t_min, t_max).is_some()
+    }
+}
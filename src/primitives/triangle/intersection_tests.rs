@@ -0,0 +1,176 @@
+use crate::{
+    material::Material,
+    primitives::{IntersectionAlgorithm, Ray, TriangleIndex, TriangleMesh},
+    scene::{
+        object::Object,
+        parser::{self, ObjectMap},
+    },
+    test_utils::test_rng,
+    traits::{Bounded, Intersectable},
+};
+
+use glam::{Vec2, Vec3A};
+use rand::Rng;
+
+/// Load the mesh and objects of an obj file in the `test` directory, using the
+/// given intersection algorithm.
+fn load_mesh(obj_file: &str, algorithm: IntersectionAlgorithm) -> (TriangleMesh, ObjectMap) {
+    let (mut mesh, _, obj_map) = parser::get_triangle_mesh_and_obj_map("test", obj_file).unwrap();
+    mesh.set_intersection_algorithm(algorithm);
+    (mesh, obj_map)
+}
+
+/// Fire a ray from outside of the (convex) object towards every target point
+/// and assert that the surface is hit exactly at the target.
+fn assert_targets_hit(object: &Object, targets: &[Vec3A]) {
+    let center = object.bounding_box().centroid();
+    for &target in targets {
+        let origin = center + (target - center) * 3.0;
+        let ray = Ray::new(origin, target - origin);
+        let hit = object
+            .intersect(&ray, 0.0, f32::INFINITY)
+            .unwrap_or_else(|| panic!("Ray towards {} missed {}", target, object.identifier));
+        assert!((hit.distance - 1.0).abs() < 1e-4);
+    }
+}
+
+/// Vertices and edge midpoints of all triangles of the object. Every one of
+/// these points is shared by at least two triangles.
+fn vertices_and_edges(object: &Object) -> Vec<Vec3A> {
+    let mut targets = Vec::new();
+    for t_idx in &object.triangles {
+        let triangle = object.mesh().get_triangle(t_idx);
+        let (&v0, &v1, &v2) = triangle.vertex_positions;
+        targets.extend([v0, v1, v2, (v0 + v1) * 0.5, (v1 + v2) * 0.5, (v2 + v0) * 0.5]);
+    }
+    targets
+}
+
+#[test]
+fn watertight_shared_edges_and_vertices_test() {
+    let (mesh, obj_map) = load_mesh("test.obj", IntersectionAlgorithm::Watertight);
+    let objects = parser::get_objects(&mesh, &obj_map);
+    assert_eq!(objects.len(), 2);
+
+    for object in &objects {
+        assert_targets_hit(object, &vertices_and_edges(object));
+    }
+}
+
+#[test]
+fn watertight_ray_along_face_diagonal_test() {
+    let (mesh, obj_map) = load_mesh("test.obj", IntersectionAlgorithm::Watertight);
+    let objects = parser::get_objects(&mesh, &obj_map);
+    let cube = objects.iter().find(|o| o.identifier == "Cube").unwrap();
+
+    // Rays perpendicular to the faces of the cube, through points on the
+    // diagonal edge that splits each face into two triangles.
+    for s in [-0.75, -0.5, 0.0, 0.25, 0.9] {
+        let targets = [
+            (Vec3A::new(2.0, s, s), Vec3A::NEG_X),
+            (Vec3A::new(-2.0, s, -s), Vec3A::X),
+            (Vec3A::new(s, 2.0, s), Vec3A::NEG_Y),
+            (Vec3A::new(s, -2.0, s), Vec3A::Y),
+            (Vec3A::new(s, s, 2.0), Vec3A::NEG_Z),
+            (Vec3A::new(s, -s, -2.0), Vec3A::Z),
+        ];
+        for (origin, direction) in targets {
+            let hit = cube.intersect(&Ray::new(origin, direction), 0.0, f32::INFINITY);
+            assert_eq!(hit.map(|h| h.distance), Some(1.0), "Ray from {} missed", origin);
+        }
+    }
+}
+
+#[test]
+fn watertight_matches_moller_trumbore_test() {
+    let (mt_mesh, obj_map) = load_mesh("monkey.obj", IntersectionAlgorithm::MollerTrumbore);
+    let mut wt_mesh = mt_mesh.clone();
+    wt_mesh.set_intersection_algorithm(IntersectionAlgorithm::Watertight);
+    let mt_objects = parser::get_objects(&mt_mesh, &obj_map);
+    let wt_objects = parser::get_objects(&wt_mesh, &obj_map);
+
+    let mut rng = test_rng();
+    for _ in 0..500 {
+        let origin = Vec3A::new(rng.gen(), rng.gen(), rng.gen()) * 6.0 - 3.0;
+        let ray = Ray::new(origin, -origin);
+        let mt = mt_objects[0].intersect(&ray, 0.0, f32::INFINITY);
+        let wt = wt_objects[0].intersect(&ray, 0.0, f32::INFINITY);
+        match (mt, wt) {
+            (Some(a), Some(b)) => assert!((a.distance - b.distance).abs() < 1e-4),
+            (None, None) => (),
+            (a, b) => panic!("Expected {:?}, got {:?}", a, b),
+        }
+    }
+}
+
+#[test]
+fn interpolated_normal_test() {
+    let positions = vec![Vec3A::ZERO, Vec3A::X, Vec3A::Y];
+    let normals = vec![
+        Vec3A::new(-1.0, -1.0, 1.0).normalize(),
+        Vec3A::new(1.0, 0.0, 1.0).normalize(),
+        Vec3A::new(0.0, 1.0, 1.0).normalize(),
+    ];
+    let mut mesh = TriangleMesh::new(positions, normals.clone(), vec![Material::default()]);
+    let triangle_index = TriangleIndex::with_normals((0, 1, 2), (0, 1, 2), 0);
+
+    for algorithm in [IntersectionAlgorithm::MollerTrumbore, IntersectionAlgorithm::Watertight] {
+        mesh.set_intersection_algorithm(algorithm);
+
+        // At a vertex the shading normal is the normal of that vertex.
+        let ray = Ray::new(Vec3A::new(1.0, 0.0, 1.0), Vec3A::NEG_Z);
+        let hit = mesh.intersect_triangle(&ray, &triangle_index, 0.0, f32::INFINITY).unwrap();
+        assert!((hit.barycentric.x - 1.0).abs() < 1e-5);
+        assert!(hit.normal.abs_diff_eq(normals[1], 1e-5));
+        assert_eq!(hit.geometric_normal, Vec3A::Z);
+
+        // Halfway between the second and third vertex.
+        let ray = Ray::new(Vec3A::new(0.5, 0.5, 1.0), Vec3A::NEG_Z);
+        let hit = mesh.intersect_triangle(&ray, &triangle_index, 0.0, f32::INFINITY).unwrap();
+        let expected = (normals[1] + normals[2]).normalize();
+        assert!(hit.normal.abs_diff_eq(expected, 1e-5));
+        assert_eq!(hit.geometric_normal, Vec3A::Z);
+
+        // Outgoing rays start above the surface on the side they leave to.
+        assert!(hit.spawn_ray(Vec3A::Z).origin.z > 0.0);
+        assert!(hit.spawn_ray(Vec3A::NEG_Z).origin.z < 0.0);
+    }
+}
+
+#[test]
+fn interpolated_tex_coord_test() {
+    let positions = vec![Vec3A::ZERO, Vec3A::X, Vec3A::Y];
+    let tex_coords = vec![Vec2::new(0.5, 0.5), Vec2::new(1.0, 0.5), Vec2::new(0.5, 1.0)];
+    let mesh = TriangleMesh::with_tex_coords(
+        positions,
+        vec![Vec3A::Z],
+        tex_coords,
+        vec![Material::default()],
+    );
+    let mut triangle_index = TriangleIndex::new((0, 1, 2), 0, 0);
+    let ray = Ray::new(Vec3A::new(0.25, 0.5, 1.0), Vec3A::NEG_Z);
+
+    let hit = mesh.intersect_triangle(&ray, &triangle_index, 0.0, f32::INFINITY).unwrap();
+    assert_eq!(hit.tex_coord(), None);
+
+    triangle_index.set_tex_coord_indices(Some((0, 1, 2)));
+    let hit = mesh.intersect_triangle(&ray, &triangle_index, 0.0, f32::INFINITY).unwrap();
+    assert!(hit.tex_coord().unwrap().abs_diff_eq(Vec2::new(0.625, 0.75), 1e-5));
+}
+
+#[test]
+fn obj_tex_coords_test() {
+    let (mesh, obj_map) = load_mesh("test.obj", IntersectionAlgorithm::MollerTrumbore);
+    let objects = parser::get_objects(&mesh, &obj_map);
+    assert!(!mesh.tex_coords().is_empty());
+
+    for object in &objects {
+        let center = object.bounding_box().centroid();
+        let ray = Ray::new(center + Vec3A::new(0.1, 0.2, 10.0), Vec3A::NEG_Z);
+        let tex_coord = object
+            .intersect(&ray, 0.0, f32::INFINITY)
+            .and_then(|hit| hit.tex_coord())
+            .unwrap_or_else(|| panic!("Expected texture coordinates on {}", object.identifier));
+        assert!(tex_coord.cmpge(Vec2::ZERO).all() && tex_coord.cmple(Vec2::ONE).all());
+    }
+}
@@ -1,22 +1,29 @@
 pub mod parser;
+pub mod triangulation;
 
 use crate::traits::Intersectable;
 
-use super::{Hit, Normal, Position, Ray};
+use super::{BoundingBox, Color, Hit, Normal, Position, Ray, TexCoord};
 
-use glam::Vec3A;
+#[cfg(not(feature = "libm"))]
+use super::packet::{PacketHits, RayPacket, Vec3x4};
+
+use glam::{Vec2, Vec3A};
+#[cfg(not(feature = "libm"))]
+use glam::Vec4;
 
 /// Single indices of a triangle.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct TriangleIndex {
     vertex_indices: (usize, usize, usize),
-    normal_index: usize,
+    normal_indices: (usize, usize, usize),
+    tex_coord_indices: Option<(usize, usize, usize)>,
     material_index: usize,
 }
 
 impl TriangleIndex {
-    /// Creates a new triangle from the given vertex indices, normal index and
-    /// material index.
+    /// Creates a new flat shaded triangle from the given vertex indices,
+    /// normal index and material index. All vertices share the same normal.
     ///
     /// ### Arguments
     /// * `vertex_indices` - The vertex indices of the triangle.
@@ -30,10 +37,34 @@ impl TriangleIndex {
         vertex_indices: (usize, usize, usize),
         normal_index: usize,
         material_index: usize,
+    ) -> Self {
+        Self::with_normals(
+            vertex_indices,
+            (normal_index, normal_index, normal_index),
+            material_index,
+        )
+    }
+
+    /// Creates a new smooth shaded triangle from the given vertex indices,
+    /// per vertex normal indices and material index.
+    ///
+    /// ### Arguments
+    /// * `vertex_indices` - The vertex indices of the triangle.
+    /// * `normal_indices` - The normal index of each vertex of the triangle.
+    /// * `material_index` - The material index of the triangle.
+    ///
+    /// ### Return value
+    /// The newly created triangle.
+    #[inline]
+    pub fn with_normals(
+        vertex_indices: (usize, usize, usize),
+        normal_indices: (usize, usize, usize),
+        material_index: usize,
     ) -> Self {
         Self {
             vertex_indices,
-            normal_index,
+            normal_indices,
+            tex_coord_indices: None,
             material_index,
         }
     }
@@ -53,26 +84,48 @@ impl TriangleIndex {
         self.material_index
     }
 
-    /// Returns the normal index of the triangle.
+    /// Returns the normal indices of the vertices of the triangle.
+    ///
+    /// ### Return value
+    /// (n1, n2, n3)
+    #[inline]
+    pub fn normal_indices(&self) -> (usize, usize, usize) {
+        self.normal_indices
+    }
+
+    /// Sets the material index of the triangle.
     #[inline]
-    pub fn normal_index(&self) -> usize {
-        self.normal_index
+    pub fn set_material_index(&mut self, material_index: usize) {
+        self.material_index = material_index;
     }
 
-    /// Return vertex indices, normal index and material index as a tuple in the
-    /// order specified below.
+    /// Returns the texture coordinate indices of the vertices of the triangle,
+    /// `None` if the triangle has no texture coordinates.
     ///
     /// ### Return value
-    /// (v1, v2, v3, n, m)
-    #[inline]
-    pub fn indices(&self) -> (usize, usize, usize, usize, usize) {
-        (
-            self.vertex_indices.0,
-            self.vertex_indices.1,
-            self.vertex_indices.2,
-            self.normal_index,
-            self.material_index,
-        )
+    /// (t1, t2, t3)
+    #[inline]
+    pub fn tex_coord_indices(&self) -> Option<(usize, usize, usize)> {
+        self.tex_coord_indices
+    }
+
+    /// Sets the texture coordinate indices of the vertices of the triangle.
+    #[inline]
+    pub fn set_tex_coord_indices(&mut self, tex_coord_indices: Option<(usize, usize, usize)>) {
+        self.tex_coord_indices = tex_coord_indices;
+    }
+
+    /// Sets the normal indices of the vertices of the triangle.
+    #[inline]
+    pub fn set_normal_indices(&mut self, normal_indices: (usize, usize, usize)) {
+        self.normal_indices = normal_indices;
+    }
+
+    /// Returns true if all vertices of the triangle share the same normal.
+    #[inline]
+    pub fn is_flat(&self) -> bool {
+        let (n1, n2, n3) = self.normal_indices;
+        n1 == n2 && n2 == n3
     }
 }
 
@@ -125,11 +178,26 @@ impl Default for TriangleIndex {
     }
 }
 
+/// Algorithm used for ray-triangle intersection tests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntersectionAlgorithm {
+    /// Möller–Trumbore intersection. Fast, but rays that hit exactly on an
+    /// edge or vertex shared by two triangles can slip through both.
+    #[default]
+    MollerTrumbore,
+    /// Watertight intersection (Woop, Benthin and Wald, 2013). Slightly
+    /// slower, but never misses along shared edges and vertices.
+    Watertight,
+}
+
 /// Struct of all data needed to define a triangle. This includes the vertices,
-/// the normal and the material. Data is stored as references.
+/// the vertex normals, the texture coordinates, the vertex colors and the
+/// material. Data is stored as references.
 pub struct Triangle<'mesh> {
     pub vertex_positions: (&'mesh Position, &'mesh Position, &'mesh Position),
-    pub normal: &'mesh Normal,
+    pub vertex_normals: (&'mesh Normal, &'mesh Normal, &'mesh Normal),
+    pub vertex_tex_coords: Option<(&'mesh TexCoord, &'mesh TexCoord, &'mesh TexCoord)>,
+    pub vertex_colors: Option<(&'mesh Color, &'mesh Color, &'mesh Color)>,
     pub triangle_index: TriangleIndex,
 }
 
@@ -137,16 +205,29 @@ impl<'mesh> Triangle<'mesh> {
     #[inline]
     pub fn new(
         vertex_positions: (&'mesh Position, &'mesh Position, &'mesh Position),
-        normal: &'mesh Normal,
+        vertex_normals: (&'mesh Normal, &'mesh Normal, &'mesh Normal),
+        vertex_tex_coords: Option<(&'mesh TexCoord, &'mesh TexCoord, &'mesh TexCoord)>,
         triangle_index: TriangleIndex,
     ) -> Self {
         Self {
             vertex_positions,
-            normal,
+            vertex_normals,
+            vertex_tex_coords,
+            vertex_colors: None,
             triangle_index,
         }
     }
 
+    /// Sets the colors of the vertices of the triangle.
+    #[inline]
+    pub fn with_vertex_colors(
+        mut self,
+        vertex_colors: Option<(&'mesh Color, &'mesh Color, &'mesh Color)>,
+    ) -> Self {
+        self.vertex_colors = vertex_colors;
+        self
+    }
+
     /// Returns the minimum vertex positions of the triangle. This is useful
     /// for bounding box calculations.
     #[inline]
@@ -162,20 +243,106 @@ impl<'mesh> Triangle<'mesh> {
         let (&v0, &v1, &v2) = self.vertex_positions;
         v0.max(v1).max(v2)
     }
-}
 
-impl<'mesh> Intersectable for Triangle<'mesh> {
-    /// Intersects a ray with the triangle. [Möller–Trumbore
-    /// intersection](https://www.scratchapixel.com/lessons/3d-basic-rendering/ray-tracing-rendering-a-triangle/ray-triangle-intersection-geometric-solution.html)
-    ///
-    /// ### Arguments
-    /// - `ray` - The ray to intersect with.
-    /// - `t_min` - The minimum distance to consider.
-    /// - `t_max` - The maximum distance to consider.
+    /// Returns the bounding box of the triangle.
+    #[inline]
+    pub fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::new(self.min(), self.max())
+    }
+
+    /// Returns the geometric normal of the triangle, ie the normal of the
+    /// plane the triangle lies in. The normal is oriented to the same side as
+    /// the vertex normals.
+    #[inline]
+    pub fn geometric_normal(&self) -> Normal {
+        let (&v0, &v1, &v2) = self.vertex_positions;
+        let normal = (v1 - v0).cross(v2 - v0).normalize_or_zero();
+        if normal.dot(self.interpolated_normal(Vec2::splat(1.0 / 3.0))) < 0.0 {
+            -normal
+        } else {
+            normal
+        }
+    }
+
+    /// Returns the vertex normals interpolated at the given barycentric
+    /// coordinates, where `barycentric.x` is the weight of the second vertex
+    /// and `barycentric.y` the weight of the third vertex.
+    #[inline]
+    pub fn interpolated_normal(&self, barycentric: Vec2) -> Normal {
+        let (&n0, &n1, &n2) = self.vertex_normals;
+        let (u, v) = (barycentric.x, barycentric.y);
+        (n0 * (1.0 - u - v) + n1 * u + n2 * v).normalize_or_zero()
+    }
+
+    /// Returns the texture coordinates interpolated at the given barycentric
+    /// coordinates, `None` if the triangle has no texture coordinates.
+    #[inline]
+    pub fn interpolated_tex_coord(&self, barycentric: Vec2) -> Option<TexCoord> {
+        let (&t0, &t1, &t2) = self.vertex_tex_coords?;
+        let (u, v) = (barycentric.x, barycentric.y);
+        Some(t0 * (1.0 - u - v) + t1 * u + t2 * v)
+    }
+
+    /// Returns the vertex colors interpolated at the given barycentric
+    /// coordinates, `None` if the triangle has no vertex colors.
+    #[inline]
+    pub fn interpolated_color(&self, barycentric: Vec2) -> Option<Color> {
+        let (&c0, &c1, &c2) = self.vertex_colors?;
+        let (u, v) = (barycentric.x, barycentric.y);
+        Some(c0 * (1.0 - u - v) + c1 * u + c2 * v)
+    }
+
+    /// Returns the derivatives of the position on the triangle with respect to
+    /// the texture coordinates, `(dp/du, dp/dv)`. `None` if the triangle has no
+    /// texture coordinates, or if they are degenerate.
+    pub fn tex_coord_derivatives(&self) -> Option<(Vec3A, Vec3A)> {
+        let (&t0, &t1, &t2) = self.vertex_tex_coords?;
+        let (&p0, &p1, &p2) = self.vertex_positions;
+        let (e1, e2) = (p1 - p0, p2 - p0);
+        let (d1, d2) = (t1 - t0, t2 - t0);
+        let det = d1.x * d2.y - d1.y * d2.x;
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let dp_du = (e1 * d2.y - e2 * d1.y) / det;
+        let dp_dv = (e2 * d1.x - e1 * d2.x) / det;
+        Some((dp_du, dp_dv))
+    }
+
+    /// Creates the hit data for an intersection at distance `t` along the
+    /// ray, with barycentric coordinates `(u, v)`.
+    #[inline]
+    fn hit(&self, ray: &Ray, t: f32, u: f32, v: f32) -> Hit {
+        let barycentric = Vec2::new(u, v);
+        let geometric_normal = self.geometric_normal();
+        let normal = if self.triangle_index.is_flat() {
+            *self.vertex_normals.0
+        } else {
+            self.interpolated_normal(barycentric)
+        };
+        let mut hit = Hit::new(
+            ray.at(t),
+            t,
+            *ray,
+            normal,
+            geometric_normal,
+            barycentric,
+            self.triangle_index,
+        );
+        hit.tex_coord = self.interpolated_tex_coord(barycentric);
+        hit.tex_coord_derivatives = self.tex_coord_derivatives();
+        hit.color = self.interpolated_color(barycentric);
+        hit
+    }
+
+    /// Möller–Trumbore intersection test.
     ///
     /// ### Return value
-    /// The hit data if the ray intersects with the triangle, `None` otherwise.
-    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+    /// The distance along the ray to the intersection and the barycentric
+    /// coordinates `(u, v)` of the intersection, if there is one in the range
+    /// `(t_min, t_max)`.
+    #[inline]
+    fn moller_trumbore(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32, f32)> {
         let (&v0, &v1, &v2) = self.vertex_positions;
         let edge0 = v1 - v0;
         let edge1 = v2 - v0;
@@ -204,16 +371,231 @@ impl<'mesh> Intersectable for Triangle<'mesh> {
         let t = f * edge1.dot(q);
 
         if t > t_min && t < t_max {
-            return Some(Hit::new(
-                ray.at(t),
-                t,
-                *ray,
-                self.triangle_index
-            ));
+            return Some((t, u, v));
+        }
+        None
+    }
+
+    /// Intersects a ray with the triangle using the given algorithm.
+    #[inline]
+    pub fn intersect_with(
+        &self,
+        algorithm: IntersectionAlgorithm,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Hit> {
+        match algorithm {
+            IntersectionAlgorithm::MollerTrumbore => self.intersect(ray, t_min, t_max),
+            IntersectionAlgorithm::Watertight => self.intersect_watertight(ray, t_min, t_max),
+        }
+    }
+
+    /// Intersects a ray with the triangle. [Watertight
+    /// intersection](https://jcgt.org/published/0002/01/05/paper.pdf)
+    ///
+    /// The triangle is transformed into a coordinate system where the ray
+    /// starts at the origin and points along the z-axis, which reduces the
+    /// test to 2D edge functions. Points on a shared edge give the same edge
+    /// function value for both triangles, so the hit is never lost.
+    ///
+    /// ### Arguments
+    /// - `ray` - The ray to intersect with.
+    /// - `t_min` - The minimum distance to consider.
+    /// - `t_max` - The maximum distance to consider.
+    ///
+    /// ### Return value
+    /// The hit data if the ray intersects with the triangle, `None` otherwise.
+    pub fn intersect_watertight(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+        self.watertight(ray, t_min, t_max)
+            .map(|(t, u, v)| self.hit(ray, t, u, v))
+    }
+
+    /// Returns true if the ray intersects with the triangle, using the
+    /// watertight intersection test.
+    #[inline]
+    pub fn intersected_watertight(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.watertight(ray, t_min, t_max).is_some()
+    }
+
+    /// Returns true if the ray intersects with the triangle using the given
+    /// algorithm.
+    #[inline]
+    pub fn intersected_with(
+        &self,
+        algorithm: IntersectionAlgorithm,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> bool {
+        match algorithm {
+            IntersectionAlgorithm::MollerTrumbore => self.intersected(ray, t_min, t_max),
+            IntersectionAlgorithm::Watertight => self.intersected_watertight(ray, t_min, t_max),
+        }
+    }
+
+    /// Watertight intersection test.
+    ///
+    /// ### Return value
+    /// The distance along the ray to the intersection and the barycentric
+    /// coordinates `(u, v)` of the intersection, if there is one in the range
+    /// `(t_min, t_max)`.
+    fn watertight(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32, f32)> {
+        // Dimension where the ray direction is maximal, and the other two
+        // dimensions swapped to preserve the winding of the triangle.
+        let dir = ray.direction;
+        let abs_dir = dir.abs();
+        let kz = if abs_dir.x > abs_dir.y {
+            if abs_dir.x > abs_dir.z {
+                0
+            } else {
+                2
+            }
+        } else if abs_dir.y > abs_dir.z {
+            1
+        } else {
+            2
+        };
+        let mut kx = (kz + 1) % 3;
+        let mut ky = (kx + 1) % 3;
+        if dir[kz] < 0.0 {
+            std::mem::swap(&mut kx, &mut ky);
+        }
+
+        // Shear constants
+        let sz = 1.0 / dir[kz];
+        let sx = dir[kx] * sz;
+        let sy = dir[ky] * sz;
+
+        // Vertices relative to the ray origin
+        let (&v0, &v1, &v2) = self.vertex_positions;
+        let a = v0 - ray.origin;
+        let b = v1 - ray.origin;
+        let c = v2 - ray.origin;
+
+        // Shear and scale the vertices
+        let ax = a[kx] - sx * a[kz];
+        let ay = a[ky] - sy * a[kz];
+        let bx = b[kx] - sx * b[kz];
+        let by = b[ky] - sy * b[kz];
+        let cx = c[kx] - sx * c[kz];
+        let cy = c[ky] - sy * c[kz];
+
+        // Scaled barycentric coordinates
+        let mut u = cx * by - cy * bx;
+        let mut v = ax * cy - ay * cx;
+        let mut w = bx * ay - by * ax;
+
+        // Fall back to double precision when the ray hits an edge exactly
+        if u == 0.0 || v == 0.0 || w == 0.0 {
+            u = (cx as f64 * by as f64 - cy as f64 * bx as f64) as f32;
+            v = (ax as f64 * cy as f64 - ay as f64 * cx as f64) as f32;
+            w = (bx as f64 * ay as f64 - by as f64 * ax as f64) as f32;
+        }
+
+        if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+            return None;
+        }
+
+        let det = u + v + w;
+        if det == 0.0 {
+            return None;
+        }
+
+        // Scaled hit distance
+        let az = sz * a[kz];
+        let bz = sz * b[kz];
+        let cz = sz * c[kz];
+        let t = (u * az + v * bz + w * cz) / det;
+
+        // `u` is the weight of the first vertex, `v` and `w` the weights of
+        // the second and third vertex.
+        if t > t_min && t < t_max {
+            return Some((t, v / det, w / det));
         }
         None
     }
 }
 
+impl<'mesh> Intersectable for Triangle<'mesh> {
+    /// Intersects a ray with the triangle. [Möller–Trumbore
+    /// intersection](https://www.scratchapixel.com/lessons/3d-basic-rendering/ray-tracing-rendering-a-triangle/ray-triangle-intersection-geometric-solution.html)
+    ///
+    /// ### Arguments
+    /// - `ray` - The ray to intersect with.
+    /// - `t_min` - The minimum distance to consider.
+    /// - `t_max` - The maximum distance to consider.
+    ///
+    /// ### Return value
+    /// The hit data if the ray intersects with the triangle, `None` otherwise.
+    /// The hit contains the barycentric coordinates of the hit point and the
+    /// vertex normals and texture coordinates interpolated at that point.
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+        self.moller_trumbore(ray, t_min, t_max)
+            .map(|(t, u, v)| self.hit(ray, t, u, v))
+    }
+
+    /// Returns true if the ray intersects with the triangle. Same test as
+    /// `intersect`, without creating the hit data.
+    fn intersected(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.moller_trumbore(ray, t_min, t_max).is_some()
+    }
+
+    /// Intersects all rays of the packet with the triangle at once. This is
+    /// the same Möller–Trumbore test as `intersect`, with every lane of the
+    /// SIMD vectors holding one ray.
+    ///
+    /// ### Arguments
+    /// - `packet` - The rays to intersect with.
+    /// - `t_min` - The minimum distance to consider.
+    /// - `t_max` - The maximum distance to consider for each ray.
+    ///
+    /// ### Return value
+    /// The hit data for each ray that intersects with the triangle.
+    #[cfg(not(feature = "libm"))]
+    fn intersect_packet(&self, packet: &RayPacket, t_min: f32, t_max: Vec4) -> PacketHits {
+        let mut hits = PacketHits::default();
+        let (&v0, &v1, &v2) = self.vertex_positions;
+        let edge0 = Vec3x4::splat(v1 - v0);
+        let edge1 = Vec3x4::splat(v2 - v0);
+        let direction = packet.direction();
+
+        let h = direction.cross(&edge1);
+        let a = edge0.dot(&h);
+        let mut mask = a.abs().cmpge(Vec4::splat(f32::EPSILON));
+        if !mask.any() {
+            return hits;
+        }
+
+        let f = a.recip();
+        let s = *packet.origin() - Vec3x4::splat(v0);
+        let u = f * s.dot(&h);
+        mask &= u.cmpge(Vec4::ZERO) & u.cmple(Vec4::ONE);
+
+        let q = s.cross(&edge0);
+        let v = f * direction.dot(&q);
+        mask &= v.cmpge(Vec4::ZERO) & (u + v).cmple(Vec4::ONE);
+
+        let t = f * edge1.dot(&q);
+        mask &= t.cmpgt(Vec4::splat(t_min)) & t.cmplt(t_max);
+
+        let bits = mask.bitmask();
+        if bits == 0 {
+            return hits;
+        }
+        let (t, u, v) = (t.to_array(), u.to_array(), v.to_array());
+        for (lane, hit) in hits.iter_mut().enumerate() {
+            if bits & (1 << lane) != 0 {
+                *hit = Some(self.hit(&packet.rays()[lane], t[lane], u[lane], v[lane]));
+            }
+        }
+        hits
+    }
+}
+
+#[cfg(test)]
+mod intersection_tests;
 #[cfg(test)]
 mod parser_tests;
+#[cfg(test)]
+mod triangulation_tests;
@@ -1,112 +1,282 @@
-use std::collections::HashMap;
+use crate::error::{column, syntax_error, ParseError, ParseErrorKind};
 
-use super::TriangleIndex;
+use super::{triangulation::triangulate, Position, TriangleIndex};
 
 use nom::{
     self,
     branch::alt,
-    bytes::complete::{tag, take_till},
-    character::{
-        complete::{self, line_ending, space0},
-        is_newline,
-    },
-    multi::{count, separated_list1},
-    sequence::terminated,
+    bytes::complete::tag,
+    character::complete::{self, space0, space1},
+    sequence::{preceded, terminated},
     IResult,
 };
 
+/// Indices of a face vertex. As written in the file the indices start at 1
+/// and negative indices are relative to the end of the vertex data, see
+/// `resolve`.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum VertexIndexGroup {
-    PosNormTex(usize, usize, usize),
-    PosNorm(usize, usize),
-    PosTex(usize, usize),
-    Pos(usize),
+enum VertexIndexGroup<I = usize> {
+    PosNormTex(I, I, I),
+    PosNorm(I, I),
+    PosTex(I, I),
+    Pos(I),
 }
 
-#[derive(Debug)]
-enum TriangleIndexData<'a> {
-    Face([VertexIndexGroup; 3]),
-    MaterialName(&'a str),
-}
+impl VertexIndexGroup {
+    /// Returns the position index of the vertex.
+    fn position_index(&self) -> usize {
+        match *self {
+            Self::PosNormTex(pos, _, _)
+            | Self::PosNorm(pos, _)
+            | Self::PosTex(pos, _)
+            | Self::Pos(pos) => pos,
+        }
+    }
 
-/// Parse all the triangle index data in the input. This includes the material
-/// name and all triangles.  Input should be a string of the form:
-/// ```text
-/// usemtl <material_name>
-/// f <vertex_index_group> <vertex_index_group> <vertex_index_group>
-/// ...
-/// usemtl <material_name>
-/// f <vertex_index_group> <vertex_index_group> <vertex_index_group>
-/// ...
-/// ```
-/// where `<vertex_index_group>` is of the form: `v/vt/vn` or `v//vn` or `v/vt`
-/// or `v`.  where `v` is the position index, `vt` is the texture index and `vn`
-/// is the normal index.
-///
-/// ### Panics
-/// When the input is not of the form specified above.  
-pub fn parse_triangle_indices<'a>(
-    input: &'a str,
-    material_map: &'a HashMap<String, usize>,
-) -> IResult<&'a str, Vec<TriangleIndex>> {
-    use TriangleIndexData::*;
-    let (input, data) = parse_triangle_index_data(input)?;
-
-    let mut triangle_indices = Vec::new();
-    let mut current_material_index = 0;
-    data.into_iter().for_each(|d| match d {
-        MaterialName(name) => {
-            current_material_index = *material_map.get(name).expect("Material not found");
+    /// Returns the texture coordinate index of the vertex, if it has one.
+    fn tex_coord_index(&self) -> Option<usize> {
+        match *self {
+            Self::PosNormTex(_, tex, _) | Self::PosTex(_, tex) => Some(tex),
+            Self::PosNorm(..) | Self::Pos(_) => None,
         }
-        Face([v1, v2, v3]) => {
-            let triangle_index = convert_to_triangle_index(&v1, &v2, &v3, current_material_index);
-            triangle_indices.push(triangle_index);
+    }
+
+    /// Returns the normal index of the vertex, if it has one.
+    fn normal_index(&self) -> Option<usize> {
+        match *self {
+            Self::PosNormTex(_, _, norm) | Self::PosNorm(_, norm) => Some(norm),
+            Self::PosTex(..) | Self::Pos(_) => None,
         }
-    });
-    Ok((input, triangle_indices))
+    }
+}
+
+impl VertexIndexGroup<i64> {
+    /// Resolve the indices as written in the file into indices into the
+    /// vertex data.
+    ///
+    /// ### Returns
+    /// The resolved indices, or a description of the first index that is out
+    /// of range.
+    fn resolve(self, vertex_data: &FaceVertexData) -> Result<VertexIndexGroup, String> {
+        use VertexIndexGroup::*;
+        let pos = |i| resolve_index(i, vertex_data.positions.len(), "Vertex");
+        let tex = |i| resolve_index(i, vertex_data.tex_coord_count, "Texture coordinate");
+        let norm = |i| resolve_index(i, vertex_data.normal_count, "Normal");
+        Ok(match self {
+            PosNormTex(p, t, n) => PosNormTex(pos(p)?, tex(t)?, norm(n)?),
+            PosNorm(p, n) => PosNorm(pos(p)?, norm(n)?),
+            PosTex(p, t) => PosTex(pos(p)?, tex(t)?),
+            Pos(p) => Pos(pos(p)?),
+        })
+    }
+}
+
+/// Normal index of the triangles of faces without vertex normals, like `f 1 2
+/// 3` or `f 1/1 2/2 3/3`. The normals of these triangles need to be
+/// generated, see `trianglemesh::normals::generate_normals`.
+pub(crate) const MISSING_NORMAL: usize = usize::MAX;
+
+/// Resolve an index as written in an obj file into an index into data with
+/// `count` elements. Positive indices start at 1 at the start of the data,
+/// negative indices start at -1 at the end of the data.
+fn resolve_index(index: i64, count: usize, name: &str) -> Result<usize, String> {
+    let resolved = if index > 0 {
+        Some(index as usize - 1).filter(|&i| i < count)
+    } else {
+        count.checked_sub(index.unsigned_abs() as usize)
+    };
+    match resolved {
+        Some(i) if index != 0 => Ok(i),
+        _ if count == 0 => Err(format!("{} index {} refers to no data", name, index)),
+        _ => Err(format!(
+            "{} index {} out of range, expected 1 to {} or -1 to -{}",
+            name, index, count, count
+        )),
+    }
+}
+
+/// The vertex data the faces of an obj file can refer to, ie the vertex data
+/// defined before the face.
+#[derive(Debug, Clone, Copy)]
+pub struct FaceVertexData<'a> {
+    /// The vertex positions, used to triangulate faces with more than three
+    /// vertices.
+    pub positions: &'a [Position],
+    /// The number of vertex normals.
+    pub normal_count: usize,
+    /// The number of texture coordinates.
+    pub tex_coord_count: usize,
+}
+
+impl<'a> FaceVertexData<'a> {
+    #[inline]
+    pub fn new(positions: &'a [Position], normal_count: usize, tex_coord_count: usize) -> Self {
+        Self {
+            positions,
+            normal_count,
+            tex_coord_count,
+        }
+    }
+}
+
+/// Parse the material name of a line of the form `usemtl <material_name>`.
+pub(crate) fn parse_material_name(line: &str, line_number: usize) -> Result<&str, ParseError> {
+    let (name, _) = preceded(space0, terminated(tag("usemtl"), space1))(line)
+        .map_err(|e| syntax_error(line, line_number, e, "Expected `usemtl <material_name>`"))?;
+    let name = name.trim_end();
+    if name.is_empty() {
+        return Err(ParseError::new(
+            ParseErrorKind::Syntax,
+            line_number,
+            column(line, name),
+            "Expected material name",
+        ));
+    }
+    Ok(name)
 }
 
-/// Parse an u64 and decrement it by 1, and convert it to usize.
+/// Parse a face with three or more vertices, for example `f a/b/c d/e/f
+/// g/h/i`, and split it into triangles.
 ///
-/// ### Panics
-/// Panics if the parsed u64 is 0.
-fn usize_decrement(input: &str) -> IResult<&str, usize> {
-    let (input, num) = complete::u32(input)?;
-    Ok((input, (num - 1) as usize))
+/// ### Arguments
+/// - `line` - The line to parse.
+/// - `line_number` - The line number, used for errors.
+/// - `material_index` - The material of the face.
+/// - `vertex_data` - The vertex data the face refers to.
+/// - `triangles` - The triangles of the face are appended to this list.
+///
+/// ### Returns
+/// An error if the line is not a valid face or refers to vertex data that
+/// does not exist.
+pub(crate) fn parse_face(
+    line: &str,
+    line_number: usize,
+    material_index: usize,
+    vertex_data: &FaceVertexData,
+    triangles: &mut Vec<TriangleIndex>,
+) -> Result<(), ParseError> {
+    let (input, _) = preceded(space0, terminated(tag("f"), space1))(line).map_err(|e| {
+        syntax_error(
+            line,
+            line_number,
+            e,
+            "Expected face of the form `f v1 v2 v3 ...`",
+        )
+    })?;
+    let input = input.split('#').next().unwrap_or_default();
+
+    let mut face = Vec::new();
+    for token in input.split_whitespace() {
+        let (remaining, group) = parse_vertex_index_group(token).map_err(|e| {
+            syntax_error(
+                line,
+                line_number,
+                e,
+                "Expected vertex index group of the form `v`, `v/vt`, `v//vn` or `v/vt/vn`",
+            )
+        })?;
+        if !remaining.is_empty() {
+            return Err(ParseError::new(
+                ParseErrorKind::Syntax,
+                line_number,
+                column(line, remaining),
+                format!("Unexpected '{}' in vertex index group", remaining),
+            ));
+        }
+        let group = group.resolve(vertex_data).map_err(|description| {
+            ParseError::new(
+                ParseErrorKind::InvalidIndex,
+                line_number,
+                column(line, token),
+                description,
+            )
+        })?;
+        face.push((column(line, token), group));
+    }
+
+    if face.len() < 3 {
+        return Err(ParseError::new(
+            ParseErrorKind::Syntax,
+            line_number,
+            column(line, &input[input.trim_end().len()..]),
+            format!("Expected at least 3 vertices in face, found {}", face.len()),
+        ));
+    }
+    let first = face[0].1;
+    if let Some((col, _)) = face
+        .iter()
+        .find(|(_, v)| std::mem::discriminant(v) != std::mem::discriminant(&first))
+    {
+        return Err(ParseError::new(
+            ParseErrorKind::Syntax,
+            line_number,
+            *col,
+            "All vertices of a face must have the same format",
+        ));
+    }
+
+    if face.len() == 3 {
+        triangles.push(convert_to_triangle_index(
+            &face[0].1,
+            &face[1].1,
+            &face[2].1,
+            material_index,
+        ));
+        return Ok(());
+    }
+
+    let polygon: Vec<Position> = face
+        .iter()
+        .map(|(_, v)| vertex_data.positions[v.position_index()])
+        .collect();
+    for (a, b, c) in triangulate(&polygon) {
+        triangles.push(convert_to_triangle_index(
+            &face[a].1,
+            &face[b].1,
+            &face[c].1,
+            material_index,
+        ));
+    }
+    Ok(())
+}
+
+/// Parse an index, which can be negative.
+fn index(input: &str) -> IResult<&str, i64> {
+    complete::i64(input)
 }
 
 /// Parse a face vertex index group of type `a/b/c`, ie position, texture and normal.
-fn parse_pos_norm_tex_index(input: &str) -> IResult<&str, VertexIndexGroup> {
-    let (input, pos) = terminated(usize_decrement, tag("/"))(input)?;
-    let (input, tex) = terminated(usize_decrement, tag("/"))(input)?;
-    let (input, norm) = usize_decrement(input)?;
+fn parse_pos_norm_tex_index(input: &str) -> IResult<&str, VertexIndexGroup<i64>> {
+    let (input, pos) = terminated(index, tag("/"))(input)?;
+    let (input, tex) = terminated(index, tag("/"))(input)?;
+    let (input, norm) = index(input)?;
     Ok((input, VertexIndexGroup::PosNormTex(pos, tex, norm)))
 }
 
 /// Parse a face vertex index group of type `a//c`, ie only position and normal.
-fn parse_pos_norm_index(input: &str) -> IResult<&str, VertexIndexGroup> {
-    let (input, pos) = terminated(usize_decrement, tag("//"))(input)?;
-    let (input, norm) = usize_decrement(input)?;
+fn parse_pos_norm_index(input: &str) -> IResult<&str, VertexIndexGroup<i64>> {
+    let (input, pos) = terminated(index, tag("//"))(input)?;
+    let (input, norm) = index(input)?;
     Ok((input, VertexIndexGroup::PosNorm(pos, norm)))
 }
 
 /// Parse a face vertex index group of type `a/b`, ie only position and texture.
-fn parse_pos_tex_index(input: &str) -> IResult<&str, VertexIndexGroup> {
+fn parse_pos_tex_index(input: &str) -> IResult<&str, VertexIndexGroup<i64>> {
     // 1/2
-    let (input, pos) = terminated(usize_decrement, tag("/"))(input)?;
-    let (input, tex) = usize_decrement(input)?;
+    let (input, pos) = terminated(index, tag("/"))(input)?;
+    let (input, tex) = index(input)?;
     Ok((input, VertexIndexGroup::PosTex(pos, tex)))
 }
 
 /// Parse a face vertex index group of type `a`, ie only position.
-fn parse_pos_index(input: &str) -> IResult<&str, VertexIndexGroup> {
+fn parse_pos_index(input: &str) -> IResult<&str, VertexIndexGroup<i64>> {
     // 1
-    let (input, pos) = usize_decrement(input)?;
+    let (input, pos) = index(input)?;
     Ok((input, VertexIndexGroup::Pos(pos)))
 }
 
 /// Parse any of the supported face vertex index groups.
-fn parse_vertex_index_group(input: &str) -> IResult<&str, VertexIndexGroup> {
+fn parse_vertex_index_group(input: &str) -> IResult<&str, VertexIndexGroup<i64>> {
     alt((
         parse_pos_norm_tex_index,
         parse_pos_norm_index,
@@ -115,54 +285,31 @@ fn parse_vertex_index_group(input: &str) -> IResult<&str, VertexIndexGroup> {
     ))(input)
 }
 
-/// Parse a triangle face of type `f a/b/c d/e/f g/h/i` into TriangleIndexData
-fn parse_triangle_face(input: &str) -> IResult<&str, TriangleIndexData> {
-    let (input, _) = tag("f ")(input)?;
-    let (input, index_group_vec) = count(terminated(parse_vertex_index_group, space0), 3)(input)?;
-    let index_group: [VertexIndexGroup; 3] =
-        index_group_vec
-            .try_into()
-            .unwrap_or_else(|v: Vec<VertexIndexGroup>| {
-                panic!("Expected a Vec of length 3 but got {}", v.len())
-            });
-    Ok((input, TriangleIndexData::Face(index_group)))
-}
-
-/// Parse a material name of type `usemtl name` into TriangleIndexData.
-fn parse_material_name(input: &str) -> IResult<&str, TriangleIndexData> {
-    let (input, _) = tag("usemtl ")(input)?;
-    let (input, material) = take_till(|c| is_newline(c as u8))(input)?;
-    Ok((input, TriangleIndexData::MaterialName(material)))
-}
-
-/// Parse a line separated list of faces (triangles) indices and material names.
-fn parse_triangle_index_data(input: &str) -> IResult<&str, Vec<TriangleIndexData>> {
-    let (input, data) =
-        separated_list1(line_ending, alt((parse_triangle_face, parse_material_name)))(input)?;
-    Ok((input, data))
-}
-
-/// Converts three vertex index groups and material index into a triangle index.
-///
-/// ### Panics
-/// Panics if the three vertex index groups are not of the same type or if the
-/// vertex index groups are not valid or supported.
+/// Converts three vertex index groups of the same type and material index
+/// into a triangle index. Without normals the normal indices are
+/// `MISSING_NORMAL`.
 fn convert_to_triangle_index(
     v1: &VertexIndexGroup,
     v2: &VertexIndexGroup,
     v3: &VertexIndexGroup,
     material_index: usize,
 ) -> TriangleIndex {
-    use VertexIndexGroup::*;
-    let m = material_index;
-    match (v1, v2, v3) {
-        (PosNormTex(p1, _, n), PosNormTex(p2, _, _), PosNormTex(p3, _, _)) => {
-            TriangleIndex::new((*p1, *p2, *p3), *n, m)
-        }
-        (PosNorm(p1, n), PosNorm(p2, _), PosNorm(p3, _)) => {
-            TriangleIndex::new((*p1, *p2, *p3), *n, m)
-        }
-        // All other cases are valid but not currently supported.
-        _ => panic!("Invalid vertex index group"),
+    let normal = |v: &VertexIndexGroup| v.normal_index().unwrap_or(MISSING_NORMAL);
+    let mut triangle_index = TriangleIndex::with_normals(
+        (
+            v1.position_index(),
+            v2.position_index(),
+            v3.position_index(),
+        ),
+        (normal(v1), normal(v2), normal(v3)),
+        material_index,
+    );
+    if let (Some(t1), Some(t2), Some(t3)) = (
+        v1.tex_coord_index(),
+        v2.tex_coord_index(),
+        v3.tex_coord_index(),
+    ) {
+        triangle_index.set_tex_coord_indices(Some((t1, t2, t3)));
     }
+    triangle_index
 }
@@ -1,6 +1,97 @@
 use std::collections::HashMap;
 
-use super::{parser::parse_triangle_indices, TriangleIndex};
+use super::{
+    parser::{parse_face, parse_material_name, FaceVertexData, MISSING_NORMAL},
+    Normal, TriangleIndex,
+};
+
+use crate::{
+    error::{column, ParseError, ParseErrorKind},
+    primitives::trianglemesh::normals::generate_normals,
+};
+
+use glam::Vec3A;
+
+/// Vertex data with three positions and three normals and texture
+/// coordinates.
+fn vertex_data() -> FaceVertexData<'static> {
+    const POSITIONS: [Vec3A; 3] = [Vec3A::ZERO, Vec3A::X, Vec3A::Y];
+    FaceVertexData::new(&POSITIONS, 3, 3)
+}
+
+/// Creates a flat shaded triangle index where all vertices use the first
+/// texture coordinate, as in the faces `f 1/1/1 2/1/1 3/1/1`.
+fn textured(vertex_indices: (usize, usize, usize), material_index: usize) -> TriangleIndex {
+    let mut triangle_index = TriangleIndex::new(vertex_indices, 0, material_index);
+    triangle_index.set_tex_coord_indices(Some((0, 0, 0)));
+    triangle_index
+}
+
+/// Parse the faces and material usages of the input the way the obj parser
+/// does, and generate the normals of the faces without normals.
+///
+/// ### Returns
+/// The triangles of all faces and the generated normals, or the error of the
+/// first line that is not a face or material usage.
+fn parse_triangle_indices(
+    input: &str,
+    material_map: &HashMap<String, usize>,
+    vertex_data: &FaceVertexData,
+) -> Result<(Vec<TriangleIndex>, Vec<Normal>), ParseError> {
+    let mut triangle_indices = Vec::new();
+    let mut current_material_index = 0;
+    for (i, line) in input.lines().enumerate() {
+        let line_number = i + 1;
+        match line.split_whitespace().next() {
+            None => (),
+            Some("usemtl") => {
+                current_material_index = parse_material_usage(line, line_number, material_map)?;
+            }
+            Some(_) => parse_face(
+                line,
+                line_number,
+                current_material_index,
+                vertex_data,
+                &mut triangle_indices,
+            )?,
+        }
+    }
+
+    let missing: Vec<usize> = (0..triangle_indices.len())
+        .filter(|&i| triangle_indices[i].normal_indices().0 == MISSING_NORMAL)
+        .collect();
+    let faces: Vec<_> = missing
+        .iter()
+        .map(|&i| (triangle_indices[i].vertex_indices(), 0))
+        .collect();
+    let (normals, indices) = generate_normals(vertex_data.positions, &faces, None);
+    let offset = vertex_data.normal_count;
+    for (&i, (n1, n2, n3)) in missing.iter().zip(indices) {
+        triangle_indices[i].set_normal_indices((n1 + offset, n2 + offset, n3 + offset));
+    }
+    Ok((triangle_indices, normals))
+}
+
+/// Parse a material usage line of the form `usemtl <material_name>`.
+///
+/// ### Returns
+/// The index of the material in `material_map`, or an error if the line is
+/// not of the correct form or the material is not in the map.
+fn parse_material_usage(
+    line: &str,
+    line_number: usize,
+    material_map: &HashMap<String, usize>,
+) -> Result<usize, ParseError> {
+    let name = parse_material_name(line, line_number)?;
+    material_map.get(name).copied().ok_or_else(|| {
+        ParseError::new(
+            ParseErrorKind::UnknownMaterial,
+            line_number,
+            column(line, name),
+            format!("Material '{}' not found", name),
+        )
+    })
+}
 
 #[test]
 fn single_triangle_face_test() {
@@ -8,9 +99,9 @@ fn single_triangle_face_test() {
     let map = vec![("A".to_string(), 0)]
         .into_iter()
         .collect::<HashMap<String, usize>>();
-    let expected_index = vec![TriangleIndex::new((0, 1, 2), 0, 0)];
+    let expected_index = vec![textured((0, 1, 2), 0)];
 
-    let (_, index) = parse_triangle_indices(input, &map).expect("Test panic!");
+    let (index, _) = parse_triangle_indices(input, &map, &vertex_data()).expect("Test panic!");
     assert_eq!(index, expected_index);
 }
 
@@ -21,11 +112,11 @@ fn multiple_triangle_face_test() {
         .into_iter()
         .collect::<HashMap<String, usize>>();
     let expected_indices = vec![
-        TriangleIndex::new((0, 1, 2), 0, 0),
-        TriangleIndex::new((0, 1, 2), 0, 0),
+        textured((0, 1, 2), 0),
+        textured((0, 1, 2), 0),
     ];
 
-    let (_, indices) = parse_triangle_indices(input, &map).expect("Test panic!");
+    let (indices, _) = parse_triangle_indices(input, &map, &vertex_data()).expect("Test panic!");
     assert_eq!(indices, expected_indices);
 }
 
@@ -37,10 +128,139 @@ fn multiple_material_face_test() {
         .collect::<HashMap<String, usize>>();
 
     let expected_indices = vec![
-        TriangleIndex::new((0, 1, 2), 0, 0),
-        TriangleIndex::new((0, 1, 2), 0, 1),
+        textured((0, 1, 2), 0),
+        textured((0, 1, 2), 1),
+    ];
+
+    let (indices, _) = parse_triangle_indices(input, &map, &vertex_data()).expect("Test panic!");
+    assert_eq!(indices, expected_indices);
+}
+
+#[test]
+fn per_vertex_normal_face_test() {
+    let input = "usemtl A\nf 1/1/1 2/2/2 3/3/3\nf 1//3 2//2 3//1";
+    let map = vec![("A".to_string(), 0)]
+        .into_iter()
+        .collect::<HashMap<String, usize>>();
+    let mut expected_indices = vec![
+        TriangleIndex::with_normals((0, 1, 2), (0, 1, 2), 0),
+        TriangleIndex::with_normals((0, 1, 2), (2, 1, 0), 0),
+    ];
+    expected_indices[0].set_tex_coord_indices(Some((0, 1, 2)));
+
+    let (indices, _) = parse_triangle_indices(input, &map, &vertex_data()).expect("Test panic!");
+    assert_eq!(indices, expected_indices);
+    assert!(!indices[0].is_flat());
+    assert_eq!(indices[1].tex_coord_indices(), None);
+}
+
+#[test]
+fn quad_and_polygon_face_test() {
+    let positions = [
+        Vec3A::new(0.0, 0.0, 0.0),
+        Vec3A::new(1.0, 0.0, 0.0),
+        Vec3A::new(1.0, 1.0, 0.0),
+        Vec3A::new(0.0, 1.0, 0.0),
+        Vec3A::new(0.5, 0.5, 0.0),
     ];
+    let input = "usemtl A\nf 1//1 2//1 3//1 4//1\nf 1//1 2//1 3//1 5//1 4//1";
+    let map = vec![("A".to_string(), 0)]
+        .into_iter()
+        .collect::<HashMap<String, usize>>();
+
+    let (indices, _) = parse_triangle_indices(input, &map, &FaceVertexData::new(&positions, 1, 0))
+        .expect("Test panic!");
+    assert_eq!(
+        &indices[..2],
+        &[
+            TriangleIndex::new((0, 1, 2), 0, 0),
+            TriangleIndex::new((0, 2, 3), 0, 0),
+        ]
+    );
+    // The second face is concave at vertex 5, so it is split using ear
+    // clipping. All triangles face +z and together cover the face.
+    assert_eq!(indices.len(), 5);
+    let area: f32 = indices[2..]
+        .iter()
+        .map(|t| {
+            let (a, b, c) = t.vertex_indices();
+            let cross = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+            assert!(cross.z > 0.0);
+            cross.z * 0.5
+        })
+        .sum();
+    assert!((area - 0.75).abs() < 1e-6);
+}
+
+#[test]
+fn invalid_face_test() {
+    let map = vec![("A".to_string(), 0)]
+        .into_iter()
+        .collect::<HashMap<String, usize>>();
+    let parse_error =
+        |input: &str| parse_triangle_indices(input, &map, &vertex_data()).unwrap_err();
+
+    let error = parse_error("usemtl A\nusemtl B");
+    assert_eq!(error.kind(), ParseErrorKind::UnknownMaterial);
+    assert_eq!((error.line(), error.column()), (2, 8));
+
+    let error = parse_error("f 1//1 2//1 3/x/1");
+    assert_eq!(error.kind(), ParseErrorKind::Syntax);
+    assert_eq!((error.line(), error.column()), (1, 14));
+
+    let error = parse_error("f 1//1 2//1");
+    assert_eq!(error.kind(), ParseErrorKind::Syntax);
 
-    let (_, indices) = parse_triangle_indices(input, &map).expect("Test panic!");
+    let error = parse_error("f 1//1 2//1 3/1/1");
+    assert_eq!((error.kind(), error.column()), (ParseErrorKind::Syntax, 13));
+
+    let error = parse_error("f 1//1 2//1 3//1 4//1");
+    assert_eq!(error.kind(), ParseErrorKind::InvalidIndex);
+    assert_eq!(error.column(), 18);
+
+    let error = parse_error("f 1//1 2//4 3//1");
+    assert_eq!(
+        (error.kind(), error.column()),
+        (ParseErrorKind::InvalidIndex, 8)
+    );
+
+    let error = parse_error("f 0//1 2//1 3//1");
+    assert_eq!(
+        (error.kind(), error.column()),
+        (ParseErrorKind::InvalidIndex, 3)
+    );
+
+    let error = parse_error("f -1//1 -2//1 -4//1");
+    assert_eq!(
+        (error.kind(), error.column()),
+        (ParseErrorKind::InvalidIndex, 15)
+    );
+}
+
+#[test]
+fn relative_index_face_test() {
+    let input = "f -3/-3/-1 -2/-2/-1 -1/-1/-1\nf 1//-3 -2//2 3//-1";
+    let map = HashMap::new();
+    let mut expected_indices = vec![
+        TriangleIndex::new((0, 1, 2), 2, 0),
+        TriangleIndex::with_normals((0, 1, 2), (0, 1, 2), 0),
+    ];
+    expected_indices[0].set_tex_coord_indices(Some((0, 1, 2)));
+
+    let (indices, _) = parse_triangle_indices(input, &map, &vertex_data()).expect("Test panic!");
     assert_eq!(indices, expected_indices);
 }
+
+#[test]
+fn face_without_normals_test() {
+    let map = HashMap::new();
+    let (indices, normals) = parse_triangle_indices("f 1 2 3\nf 1/1 2/2 3/3", &map, &vertex_data())
+        .expect("Test panic!");
+
+    // Both faces get the same face normal, after the three vertex normals.
+    assert_eq!(normals, vec![Vec3A::Z]);
+    assert_eq!(indices[0].normal_indices(), (3, 3, 3));
+    assert_eq!(indices[0].tex_coord_indices(), None);
+    assert_eq!(indices[1].normal_indices(), (3, 3, 3));
+    assert_eq!(indices[1].tex_coord_indices(), Some((0, 1, 2)));
+}
@@ -0,0 +1,120 @@
+use super::Position;
+
+use glam::{Vec2, Vec3A};
+
+/// Split a polygon into triangles. Convex polygons are split into a fan around
+/// the first vertex, concave polygons are split using ear clipping. The
+/// triangles keep the winding order of the polygon.
+///
+/// ### Arguments
+/// - `polygon` - The vertex positions of the polygon, in order. The polygon is
+///   expected to be (roughly) planar and not self-intersecting.
+///
+/// ### Returns
+/// The triangles as indices into `polygon`. A polygon with `n` vertices gives
+/// `n - 2` triangles, and no triangles if there are less than three vertices.
+pub fn triangulate(polygon: &[Position]) -> Vec<(usize, usize, usize)> {
+    if polygon.len() < 3 {
+        return Vec::new();
+    }
+    let points = project(polygon);
+    if is_convex(&points) {
+        fan(polygon.len())
+    } else {
+        ear_clipping(&points)
+    }
+}
+
+/// Split a polygon with `n` vertices into a fan of triangles around the first
+/// vertex.
+fn fan(n: usize) -> Vec<(usize, usize, usize)> {
+    (1..n - 1).map(|i| (0, i, i + 1)).collect()
+}
+
+/// Project the polygon onto the coordinate plane it is most aligned with. The
+/// projection is flipped if needed, so the projected polygon is always counter
+/// clockwise.
+fn project(polygon: &[Position]) -> Vec<Vec2> {
+    // Newell's method, works for concave polygons as well.
+    let normal = polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .fold(Vec3A::ZERO, |n, (a, b)| {
+            n + Vec3A::new(
+                (a.y - b.y) * (a.z + b.z),
+                (a.z - b.z) * (a.x + b.x),
+                (a.x - b.x) * (a.y + b.y),
+            )
+        });
+    let abs = normal.abs();
+    let (x, y, sign) = if abs.x >= abs.y && abs.x >= abs.z {
+        (1, 2, normal.x)
+    } else if abs.y >= abs.z {
+        (2, 0, normal.y)
+    } else {
+        (0, 1, normal.z)
+    };
+    let flip = if sign < 0.0 { -1.0 } else { 1.0 };
+    polygon
+        .iter()
+        .map(|p| Vec2::new(p[x] * flip, p[y]))
+        .collect()
+}
+
+/// Twice the signed area of the triangle `abc`, positive if the triangle is
+/// counter clockwise.
+#[inline]
+fn orientation(a: Vec2, b: Vec2, c: Vec2) -> f32 {
+    (b - a).perp_dot(c - a)
+}
+
+/// Returns true if no corner of the (counter clockwise) polygon turns
+/// clockwise.
+fn is_convex(points: &[Vec2]) -> bool {
+    let n = points.len();
+    (0..n).all(|i| orientation(points[i], points[(i + 1) % n], points[(i + 2) % n]) >= 0.0)
+}
+
+/// Returns true if `p` lies inside or on the edges of the counter clockwise
+/// triangle `abc`.
+#[inline]
+fn in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    orientation(a, b, p) >= 0.0 && orientation(b, c, p) >= 0.0 && orientation(c, a, p) >= 0.0
+}
+
+/// Triangulate a counter clockwise polygon by repeatedly cutting off ears,
+/// ie convex corners whose triangle contains no other vertex of the polygon.
+fn ear_clipping(points: &[Vec2]) -> Vec<(usize, usize, usize)> {
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let (prev, curr, next) = (remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]);
+            let (a, b, c) = (points[prev], points[curr], points[next]);
+            orientation(a, b, c) > 0.0
+                && remaining
+                    .iter()
+                    .filter(|&&j| j != prev && j != curr && j != next)
+                    .all(|&j| !in_triangle(points[j], a, b, c))
+        });
+
+        match ear {
+            Some(i) => {
+                triangles.push((remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]));
+                remaining.remove(i);
+            }
+            // Degenerate or self-intersecting polygon, there might not be any
+            // ears left. Fall back to a fan of the remaining vertices.
+            None => break,
+        }
+    }
+
+    triangles.extend(
+        fan(remaining.len())
+            .into_iter()
+            .map(|(a, b, c)| (remaining[a], remaining[b], remaining[c])),
+    );
+    triangles
+}
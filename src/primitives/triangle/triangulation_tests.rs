@@ -0,0 +1,72 @@
+use super::triangulation::triangulate;
+
+use glam::Vec3A;
+
+/// Sum of the areas of the triangles, and asserts that all triangles have the
+/// same winding as the polygon (counter clockwise around `normal`).
+fn triangulated_area(polygon: &[Vec3A], normal: Vec3A) -> f32 {
+    triangulate(polygon)
+        .into_iter()
+        .map(|(a, b, c)| {
+            let cross = (polygon[b] - polygon[a]).cross(polygon[c] - polygon[a]);
+            assert!(cross.dot(normal) > 0.0, "Triangle ({a}, {b}, {c}) is flipped");
+            cross.length() * 0.5
+        })
+        .sum()
+}
+
+#[test]
+fn triangle_test() {
+    let polygon = [Vec3A::ZERO, Vec3A::X, Vec3A::Y];
+    assert_eq!(triangulate(&polygon), vec![(0, 1, 2)]);
+    assert!(triangulate(&polygon[..2]).is_empty());
+}
+
+#[test]
+fn convex_polygon_fan_test() {
+    let quad = [
+        Vec3A::new(0.0, 0.0, 0.0),
+        Vec3A::new(2.0, 0.0, 0.0),
+        Vec3A::new(2.0, 1.0, 0.0),
+        Vec3A::new(0.0, 1.0, 0.0),
+    ];
+    assert_eq!(triangulate(&quad), vec![(0, 1, 2), (0, 2, 3)]);
+
+    // Regular hexagon in the xz-plane, wound clockwise seen from above.
+    let hexagon: Vec<Vec3A> = (0..6)
+        .map(|i| {
+            let angle = -(i as f32) * std::f32::consts::PI / 3.0;
+            Vec3A::new(angle.cos(), 0.0, angle.sin())
+        })
+        .collect();
+    assert_eq!(triangulate(&hexagon).len(), 4);
+    let expected = 1.5 * 3f32.sqrt();
+    assert!((triangulated_area(&hexagon, Vec3A::Y) - expected).abs() < 1e-5);
+}
+
+#[test]
+fn concave_polygon_ear_clipping_test() {
+    // L-shape starting at a vertex next to the notch, so a fan around the
+    // first vertex would give a flipped triangle.
+    let l_shape = [
+        Vec3A::new(2.0, 1.0, 0.0),
+        Vec3A::new(1.0, 1.0, 0.0),
+        Vec3A::new(1.0, 2.0, 0.0),
+        Vec3A::new(0.0, 2.0, 0.0),
+        Vec3A::new(0.0, 0.0, 0.0),
+        Vec3A::new(2.0, 0.0, 0.0),
+    ];
+    assert_eq!(triangulate(&l_shape).len(), 4);
+    assert!((triangulated_area(&l_shape, Vec3A::Z) - 3.0).abs() < 1e-5);
+
+    // Chevron in the yz-plane with the notch as first vertex, wound clockwise
+    // seen from +x.
+    let chevron = [
+        Vec3A::new(0.0, 0.0, 0.0),
+        Vec3A::new(0.0, -2.0, -1.0),
+        Vec3A::new(0.0, 0.0, 3.0),
+        Vec3A::new(0.0, 2.0, -1.0),
+    ];
+    assert_eq!(triangulate(&chevron).len(), 2);
+    assert!((triangulated_area(&chevron, Vec3A::NEG_X) - 6.0).abs() < 1e-5);
+}
@@ -1,17 +1,26 @@
 use crate::{material::Material, traits::Intersectable};
 
-use super::{Hit, Normal, Position, Ray, TexCoord, Triangle, TriangleIndex};
+use super::{
+    triangle::IntersectionAlgorithm, Color, Hit, Normal, PacketHits, Position, Ray, RayPacket, TexCoord,
+    Triangle, TriangleIndex,
+};
 
+use glam::{Vec3A, Vec4};
+
+pub mod normals;
 pub mod parser;
 
-/// Triangle mesh primitive. Stores the vertices, triangles and normals that
-/// make up the mesh. Also stores all possible materials that can be applied
-/// to a triangle.
+/// Triangle mesh primitive. Stores the vertices, triangles, normals and
+/// texture coordinates that make up the mesh. Also stores all possible
+/// materials that can be applied to a triangle.
 #[derive(Debug, Clone, PartialEq)]
 pub struct TriangleMesh {
     vertex_positions: Vec<Position>,
     triangle_normals: Vec<Normal>,
+    tex_coords: Vec<TexCoord>,
+    vertex_colors: Vec<Color>,
     materials: Vec<Material>,
+    intersection_algorithm: IntersectionAlgorithm,
 }
 
 impl TriangleMesh {
@@ -21,26 +30,86 @@ impl TriangleMesh {
         vertex_positions: Vec<Position>,
         triangle_normals: Vec<Normal>,
         triangle_materials: Vec<Material>,
+    ) -> Self {
+        Self::with_tex_coords(
+            vertex_positions,
+            triangle_normals,
+            Vec::new(),
+            triangle_materials,
+        )
+    }
+
+    /// Create a new triangle mesh from the vertex positions, triangle normals,
+    /// texture coordinates and materials.
+    pub fn with_tex_coords(
+        vertex_positions: Vec<Position>,
+        triangle_normals: Vec<Normal>,
+        tex_coords: Vec<TexCoord>,
+        triangle_materials: Vec<Material>,
     ) -> Self {
         Self {
             vertex_positions,
             triangle_normals,
+            tex_coords,
+            vertex_colors: Vec::new(),
             materials: triangle_materials,
+            intersection_algorithm: IntersectionAlgorithm::default(),
         }
     }
 
+    /// Get the algorithm used for ray-triangle intersection tests.
+    #[inline]
+    pub fn intersection_algorithm(&self) -> IntersectionAlgorithm {
+        self.intersection_algorithm
+    }
+
+    /// Set the algorithm used for ray-triangle intersection tests. Defaults to
+    /// Möller–Trumbore.
+    #[inline]
+    pub fn set_intersection_algorithm(&mut self, algorithm: IntersectionAlgorithm) {
+        self.intersection_algorithm = algorithm;
+    }
+
     /// Get reference to the vertex positions.
     #[inline]
     pub fn vertex_positions(&self) -> &Vec<Position> {
         &self.vertex_positions
     }
 
-    /// Get reference to the triangle normals.
+    /// Get reference to the normals. Triangles reference one normal per
+    /// vertex, flat shaded triangles reference the same normal three times.
     #[inline]
     pub fn triangle_normals(&self) -> &Vec<Normal> {
         &self.triangle_normals
     }
 
+    /// Get reference to the texture coordinates.
+    #[inline]
+    pub fn tex_coords(&self) -> &Vec<TexCoord> {
+        &self.tex_coords
+    }
+
+    /// Get reference to the vertex colors. The colors are indexed like the
+    /// vertex positions, empty if the mesh has no vertex colors.
+    #[inline]
+    pub fn vertex_colors(&self) -> &Vec<Color> {
+        &self.vertex_colors
+    }
+
+    /// Set the colors of the vertices, one for every vertex position. The
+    /// diffuse color of the materials is multiplied with the vertex colors.
+    ///
+    /// ### Panics
+    /// Panics if the colors are not empty and there are not as many colors as
+    /// vertex positions.
+    pub fn set_vertex_colors(&mut self, vertex_colors: Vec<Color>) {
+        assert!(
+            vertex_colors.is_empty() || vertex_colors.len() == self.vertex_positions.len(),
+            "Expected one vertex color per vertex position"
+        );
+        self.vertex_colors = vertex_colors;
+    }
+
     /// Get reference to the materials.
     #[inline]
     pub fn materials(&self) -> &Vec<Material> {
@@ -58,24 +127,43 @@ impl TriangleMesh {
     /// - `triangle_index` - The triangle index.
     ///
     /// ### Returns
-    /// The triangle containing the vertex positions, normal and material of the
-    /// triangle.
+    /// The triangle containing the vertex positions, vertex normals, texture
+    /// coordinates, vertex colors and material of the triangle.
     ///
     /// ### Panics
     /// Panics if any of the indices in the `TriangleIndex` are out of invalid.
-    pub fn get_triangle(&self, triangle_index: &TriangleIndex) -> Triangle {
-        let (v1, v2, v3, n, _) = triangle_index.indices();
+    pub fn get_triangle(&self, triangle_index: &TriangleIndex) -> Triangle<'_> {
+        let (v1, v2, v3) = triangle_index.vertex_indices();
+        let (n1, n2, n3) = triangle_index.normal_indices();
         Triangle::new(
             (
                 self.vertex_positions.get(v1).expect("Invalid vertex index"),
                 self.vertex_positions.get(v2).expect("Invalid vertex index"),
                 self.vertex_positions.get(v3).expect("Invalid vertex index"),
             ),
-            self.triangle_normals
-                .get(n)
-                .expect("Invalid triangle index"),
+            (
+                self.triangle_normals.get(n1).expect("Invalid normal index"),
+                self.triangle_normals.get(n2).expect("Invalid normal index"),
+                self.triangle_normals.get(n3).expect("Invalid normal index"),
+            ),
+            triangle_index.tex_coord_indices().map(|(t1, t2, t3)| {
+                (
+                    self.tex_coords.get(t1).expect("Invalid texture coordinate index"),
+                    self.tex_coords.get(t2).expect("Invalid texture coordinate index"),
+                    self.tex_coords.get(t3).expect("Invalid texture coordinate index"),
+                )
+            }),
             *triangle_index,
         )
+        .with_vertex_colors(if self.vertex_colors.is_empty() {
+            None
+        } else {
+            Some((
+                &self.vertex_colors[v1],
+                &self.vertex_colors[v2],
+                &self.vertex_colors[v3],
+            ))
+        })
     }
 
     /// Test if the given ray intersects the given triangle in the mesh.
@@ -99,9 +187,124 @@ impl TriangleMesh {
         t_max: f32,
     ) -> Option<Hit> {
         let triangle = self.get_triangle(triangle_index);
-        triangle.intersect(ray, t_min, t_max)
+        triangle.intersect_with(self.intersection_algorithm, ray, t_min, t_max)
+    }
+
+    /// Test if the given ray intersects the given triangle in the mesh, without
+    /// computing the hit data.
+    ///
+    /// ### Arguments
+    /// - `ray` - The ray to test intersection with.
+    /// - `triangle_index` - The triangle index of the triangle to test
+    /// - `t_min` - The minimum distance along the ray to test for intersection.
+    /// - `t_max` - The maximum distance along the ray to test for intersection.
+    ///
+    /// ### Panics
+    /// Panics if any of the indices in the `TriangleIndex` are invalid.
+    pub fn triangle_intersected(
+        &self,
+        ray: &Ray,
+        triangle_index: &TriangleIndex,
+        t_min: f32,
+        t_max: f32,
+    ) -> bool {
+        let triangle = self.get_triangle(triangle_index);
+        triangle.intersected_with(self.intersection_algorithm, ray, t_min, t_max)
+    }
+
+    /// Test if the rays of the packet intersect the given triangle in the
+    /// mesh.
+    ///
+    /// ### Arguments
+    /// - `packet` - The rays to test intersection with.
+    /// - `triangle_index` - The triangle index of the triangle to test
+    /// - `t_min` - The minimum distance along the rays to test for intersection.
+    /// - `t_max` - The maximum distance along each ray to test for intersection.
+    ///
+    /// ### Returns
+    /// The hit for each ray that intersects the triangle.
+    ///
+    /// ### Panics
+    /// Panics if any of the indices in the `TriangleIndex` are invalid.
+    pub fn intersect_triangle_packet(
+        &self,
+        packet: &RayPacket,
+        triangle_index: &TriangleIndex,
+        t_min: f32,
+        t_max: Vec4,
+    ) -> PacketHits {
+        let triangle = self.get_triangle(triangle_index);
+        match self.intersection_algorithm {
+            IntersectionAlgorithm::MollerTrumbore => triangle.intersect_packet(packet, t_min, t_max),
+            // There is no packet version of the watertight test, so the rays
+            // are tested one at a time.
+            IntersectionAlgorithm::Watertight => {
+                let t_max = t_max.to_array();
+                let mut hits = PacketHits::default();
+                for (lane, ray) in packet.rays().iter().enumerate() {
+                    hits[lane] = triangle.intersect_watertight(ray, t_min, t_max[lane]);
+                }
+                hits
+            }
+        }
+    }
+
+    /// Perturb the shading normal of a hit on a triangle of the mesh with the
+    /// normal map and bump map of the material of the triangle. The hit is
+    /// returned unchanged if the material has neither, or the triangle has
+    /// no texture coordinates.
+    ///
+    /// The maps are applied along the texture coordinate derivatives of the
+    /// hit, so the hit can be in world space, for example a hit on an
+    /// instance.
+    pub fn apply_bump_map(&self, hit: Hit) -> Hit {
+        let material = hit.material(self);
+        if material.normal_map.is_none() && material.bump_map.is_none() {
+            return hit;
+        }
+        let (Some(tex_coord), Some((dp_du, dp_dv))) = (hit.tex_coord(), hit.tex_coord_derivatives)
+        else {
+            return hit;
+        };
+
+        let mut normal = hit.normal;
+        if let Some(normal_map) = &material.normal_map {
+            // Tangent space of the surface, with the tangent along increasing
+            // u and the bitangent along increasing v.
+            let tangent = (dp_du - normal * normal.dot(dp_du)).normalize_or_zero();
+            let bitangent = normal.cross(tangent);
+            let bitangent = if bitangent.dot(dp_dv) < 0.0 {
+                -bitangent
+            } else {
+                bitangent
+            };
+            let local = Vec3A::from(normal_map.sample(tex_coord).truncate()) * 2.0 - 1.0;
+            let mapped = (tangent * local.x + bitangent * local.y + normal * local.z)
+                .normalize_or_zero();
+            if mapped != Vec3A::ZERO {
+                normal = mapped;
+            }
+        }
+        if let Some(bump_map) = &material.bump_map {
+            // Displace the surface along the normal by the height of the bump
+            // map, and use the normal of the displaced surface.
+            let gradient = bump_map.height_gradient(tex_coord);
+            let bumped = (dp_du + normal * gradient.x)
+                .cross(dp_dv + normal * gradient.y)
+                .normalize_or_zero();
+            if bumped != Vec3A::ZERO {
+                normal = if bumped.dot(normal) < 0.0 {
+                    -bumped
+                } else {
+                    bumped
+                };
+            }
+        }
+        Hit { normal, ..hit }
     }
 }
 
+#[cfg(test)]
+mod normals_tests;
 #[cfg(test)]
 mod parser_tests;
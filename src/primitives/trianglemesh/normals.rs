@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use super::{Normal, Position};
+
+/// Vertex indices of a triangle that needs normals, and its smoothing group.
+/// Triangles in smoothing group 0 are not part of any smoothing group.
+pub type NormalFace = ((usize, usize, usize), u32);
+
+/// Generate normals for triangles without vertex normals.
+///
+/// Without a crease angle every triangle gets its geometric face normal.
+/// With a crease angle the triangles of a smoothing group get smooth vertex
+/// normals: the normal of a triangle corner is the average of the face
+/// normals of the triangles around the vertex, weighted by their angle at the
+/// vertex. Only triangles of the same smoothing group whose face normal
+/// differs by at most the crease angle from the face normal of the triangle
+/// are averaged, so hard edges stay sharp. Triangles outside of any smoothing
+/// group get their face normal.
+///
+/// ### Arguments
+/// - `positions` - The vertex positions the triangles refer to.
+/// - `faces` - The vertex indices and smoothing group of every triangle.
+/// - `crease_angle` - The crease angle in radians, or `None` for face
+///   normals only.
+///
+/// ### Returns
+/// The generated normals, and the indices into them of the corners of every
+/// triangle. Equal normals are only stored once. Degenerate triangles get a
+/// zero normal.
+pub fn generate_normals(
+    positions: &[Position],
+    faces: &[NormalFace],
+    crease_angle: Option<f32>,
+) -> (Vec<Normal>, Vec<(usize, usize, usize)>) {
+    let face_normals: Vec<Normal> = faces
+        .iter()
+        .map(|&((v1, v2, v3), _)| {
+            let (p1, p2, p3) = (positions[v1], positions[v2], positions[v3]);
+            (p2 - p1).cross(p3 - p1).normalize_or_zero()
+        })
+        .collect();
+
+    let mut normals = NormalSet::default();
+    let Some(crease_angle) = crease_angle else {
+        let indices = face_normals
+            .iter()
+            .map(|&normal| {
+                let index = normals.insert(normal);
+                (index, index, index)
+            })
+            .collect();
+        return (normals.normals, indices);
+    };
+
+    // The corners (face and corner index) around every vertex.
+    let mut vertex_corners: HashMap<usize, Vec<(usize, usize)>> = HashMap::new();
+    for (face, &((v1, v2, v3), _)) in faces.iter().enumerate() {
+        for (corner, vertex) in [v1, v2, v3].into_iter().enumerate() {
+            vertex_corners
+                .entry(vertex)
+                .or_default()
+                .push((face, corner));
+        }
+    }
+
+    let min_cos = crease_angle.cos();
+    let corner_normal = |face: usize, vertex: usize| {
+        let (_, group) = faces[face];
+        let face_normal = face_normals[face];
+        if group == 0 || face_normal == Normal::ZERO {
+            return face_normal;
+        }
+        let normal: Normal = vertex_corners[&vertex]
+            .iter()
+            .filter(|&&(other, _)| {
+                let other_normal = face_normals[other];
+                other == face
+                    || (faces[other].1 == group
+                        && other_normal != Normal::ZERO
+                        && face_normal.dot(other_normal) >= min_cos)
+            })
+            .map(|&(other, corner)| {
+                face_normals[other] * corner_angle(positions, faces[other].0, corner)
+            })
+            .sum();
+        normal.try_normalize().unwrap_or(face_normal)
+    };
+
+    let indices = faces
+        .iter()
+        .enumerate()
+        .map(|(face, &((v1, v2, v3), _))| {
+            (
+                normals.insert(corner_normal(face, v1)),
+                normals.insert(corner_normal(face, v2)),
+                normals.insert(corner_normal(face, v3)),
+            )
+        })
+        .collect();
+    (normals.normals, indices)
+}
+
+/// Angle of a triangle at one of its corners, in radians.
+fn corner_angle(positions: &[Position], (v1, v2, v3): (usize, usize, usize), corner: usize) -> f32 {
+    let (p, a, b) = match corner {
+        0 => (v1, v2, v3),
+        1 => (v2, v3, v1),
+        _ => (v3, v1, v2),
+    };
+    (positions[a] - positions[p]).angle_between(positions[b] - positions[p])
+}
+
+/// Normals that are stored only once, by their bit pattern.
+#[derive(Default)]
+struct NormalSet {
+    normals: Vec<Normal>,
+    indices: HashMap<[u32; 3], usize>,
+}
+
+impl NormalSet {
+    /// Get the index of the normal, the normal is added if it is not in the
+    /// set yet.
+    fn insert(&mut self, normal: Normal) -> usize {
+        let key = normal.to_array().map(f32::to_bits);
+        *self.indices.entry(key).or_insert_with(|| {
+            self.normals.push(normal);
+            self.normals.len() - 1
+        })
+    }
+}
@@ -0,0 +1,77 @@
+use super::normals::generate_normals;
+
+use glam::Vec3A;
+
+/// Two triangles of a unit square in the z = 0 plane, and a triangle folded
+/// up at a right angle along the edge x = 1.
+fn folded_positions() -> Vec<Vec3A> {
+    vec![
+        Vec3A::ZERO,
+        Vec3A::X,
+        Vec3A::new(1.0, 1.0, 0.0),
+        Vec3A::Y,
+        Vec3A::new(1.0, 0.0, 1.0),
+    ]
+}
+
+#[test]
+fn face_normals_test() {
+    let faces = [((0, 1, 2), 1), ((0, 2, 3), 1), ((1, 4, 2), 1)];
+    let (normals, indices) = generate_normals(&folded_positions(), &faces, None);
+
+    // The coplanar triangles share their normal.
+    assert_eq!(normals, vec![Vec3A::Z, Vec3A::NEG_X]);
+    assert_eq!(indices, vec![(0, 0, 0), (0, 0, 0), (1, 1, 1)]);
+}
+
+/// Assert that a generated normal is the expected normal, up to rounding.
+fn assert_normal(normal: Vec3A, expected: Vec3A) {
+    assert!(
+        normal.abs_diff_eq(expected, 1e-6),
+        "Expected {}, found {}",
+        expected,
+        normal
+    );
+}
+
+#[test]
+fn smooth_normals_test() {
+    let positions = folded_positions();
+    let faces = [((0, 1, 2), 1), ((0, 2, 3), 1), ((1, 4, 2), 1)];
+
+    // The fold is sharper than the crease angle, so it stays sharp.
+    let (normals, indices) = generate_normals(&positions, &faces, Some(45f32.to_radians()));
+    let (n1, n2, n3) = indices[0];
+    for n in [n1, n2, n3] {
+        assert_normal(normals[n], Vec3A::Z);
+    }
+    assert_normal(normals[indices[2].2], Vec3A::NEG_X);
+
+    // Below the crease angle the normals at the fold are averaged, weighted
+    // by the angles of the triangles at the vertices. All three triangles
+    // have an angle of 45 degrees at (1, 1, 0).
+    let (normals, indices) = generate_normals(&positions, &faces, Some(100f32.to_radians()));
+    let (n1, _, n3) = indices[0];
+    assert_normal(normals[n1], Vec3A::Z);
+    assert_normal(normals[n3], Vec3A::new(-1.0, 0.0, 2.0).normalize());
+    assert_eq!(indices[1].1, n3);
+    assert_eq!(indices[2].2, n3);
+
+    // Triangles of other smoothing groups, or without a smoothing group, are
+    // not averaged.
+    for group in [0, 2] {
+        let faces = [faces[0], faces[1], ((1, 4, 2), group)];
+        let (normals, indices) = generate_normals(&positions, &faces, Some(100f32.to_radians()));
+        assert_normal(normals[indices[0].2], Vec3A::Z);
+        assert_normal(normals[indices[2].2], Vec3A::NEG_X);
+    }
+}
+
+#[test]
+fn degenerate_normals_test() {
+    let positions = folded_positions();
+    let faces = [((0, 1, 2), 1), ((0, 1, 1), 1)];
+    let (normals, indices) = generate_normals(&positions, &faces, Some(180f32.to_radians()));
+    assert_normal(normals[indices[0].0], Vec3A::Z);
+    assert_eq!(normals[indices[1].0], Vec3A::ZERO);
+}
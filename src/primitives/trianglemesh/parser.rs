@@ -1,27 +1,28 @@
-use crate::primitives::Position;
+use crate::{
+    error::{column, expect_end_of_line, syntax_error, ParseError, ParseErrorKind},
+    primitives::Position,
+};
 
-use super::{Normal, TexCoord};
+use super::{Color, Normal, TexCoord};
 
 use glam::{Vec2, Vec3A};
 
 use nom::{
-    self,
-    branch::alt,
-    bytes::complete::tag,
-    character::complete::{line_ending, space1},
-    multi::separated_list1,
-    number::complete::float,
-    IResult,
+    self, bytes::complete::tag, character::complete::space1, combinator::opt, multi::many0,
+    number::complete::float, sequence::preceded, IResult,
 };
 
-enum VertexParseResult {
-    Position(Position),
+pub(crate) enum VertexParseResult {
+    /// Position and the optional vertex color after it.
+    Position(Position, Option<Color>),
     Normal(Normal),
     TexCoord(TexCoord),
 }
 
 type VertexData = (Vec<Position>, Vec<Normal>, Vec<TexCoord>);
 
+type VertexParser = fn(&str) -> IResult<&str, VertexParseResult>;
+
 /// Parse line separated list of vertex data into a vector of
 /// `VertexParseResult`.  Vertex data includes position, normal and texture
 /// coordinate, and each data is formatted in the following way:
@@ -31,75 +32,112 @@ type VertexData = (Vec<Position>, Vec<Normal>, Vec<TexCoord>);
 ///
 /// ### Arguments
 /// - `input` - The input string to parse. Should be a line separated list of
-/// vertex data in the format specified above. The vertex data can be in any
-/// order.
+///   vertex data in the format specified above. The vertex data can be in any
+///   order. Empty lines are skipped.
 ///
 /// ### Returns
-/// A tuple containing the remaining input string slice and a tuple of vectors
-/// of positions and normals. The positions and normals are in the same order as
-/// they were in the input string.
-pub fn parse_vertex_data(
-    input: &str,
-) -> IResult<&str, VertexData> {
-    let (input, parsed_vertex_data) = parse_vertex_data_list(input)?;
-
+/// A tuple of vectors of positions, normals and texture coordinates. The data
+/// is in the same order as in the input string. An error with the line and
+/// column of the first line that is not valid vertex data otherwise.
+pub fn parse_vertex_data(input: &str) -> Result<VertexData, ParseError> {
     let mut positions = Vec::new();
     let mut normals = Vec::new();
     let mut tex_coords = Vec::new();
-    //let mut tex_coords = Vec::new();
-    parsed_vertex_data
-        .into_iter()
-        .for_each(|vertex_data| match vertex_data {
-            VertexParseResult::Position(position) => positions.push(position),
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_vertex_line(line, i + 1)? {
+            VertexParseResult::Position(position, _) => positions.push(position),
             VertexParseResult::Normal(normal) => normals.push(normal),
             VertexParseResult::TexCoord(tex_coord) => tex_coords.push(tex_coord),
-        });
+        }
+    }
+    Ok((positions, normals, tex_coords))
+}
+
+/// Parse a single line of vertex data, see `parse_vertex_data`.
+///
+/// ### Arguments
+/// - `line` - The line to parse.
+/// - `line_number` - The line number, used for the error.
+pub(crate) fn parse_vertex_line(
+    line: &str,
+    line_number: usize,
+) -> Result<VertexParseResult, ParseError> {
+    let input = line.trim_start();
+    let (parser, format): (VertexParser, _) = match input.split_whitespace().next() {
+        Some("v") => (parse_vertex_position, "`v x y z`"),
+        Some("vn") => (parse_vertex_normal, "`vn x y z`"),
+        Some("vt") => (parse_vertex_texture_coordinate, "`vt u v`"),
+        _ => {
+            return Err(ParseError::new(
+                ParseErrorKind::Syntax,
+                line_number,
+                column(line, input),
+                "Expected vertex data (`v`, `vn` or `vt`)",
+            ))
+        }
+    };
+    let (remaining, vertex_data) = parser(input).map_err(|e| {
+        syntax_error(
+            line,
+            line_number,
+            e,
+            &format!("Expected vertex data of the form {}", format),
+        )
+    })?;
+    expect_end_of_line(line, line_number, remaining)?;
+    Ok(vertex_data)
+}
 
-    Ok((input, (positions, normals, tex_coords)))
+/// Parse three whitespace separated floats.
+fn vec3(input: &str) -> IResult<&str, Vec3A> {
+    let (input, x) = preceded(space1, float)(input)?;
+    let (input, y) = preceded(space1, float)(input)?;
+    let (input, z) = preceded(space1, float)(input)?;
+    Ok((input, Vec3A::new(x, y, z)))
 }
 
-/// Parses a single vertex. Format: `v x y z`. Te x, y, z values are parsed as floats.
+/// Parses a single vertex. Format: `v x y z`. Te x, y, z values are parsed as
+/// floats. Three values after the position are a vertex color (`v x y z r g
+/// b`), any other number of values, like a weight, is ignored.
 fn parse_vertex_position(input: &str) -> IResult<&str, VertexParseResult> {
-    let (input, _) = tag("v ")(input)?;
-    let (input, a) = separated_list1(space1, float)(input)?;
-    let pos = Vec3A::from_slice(&a);
-    Ok((input, VertexParseResult::Position(pos)))
+    let (input, _) = tag("v")(input)?;
+    let (input, pos) = vec3(input)?;
+    let (input, rest) = many0(preceded(space1, float))(input)?;
+    let color = match rest[..] {
+        [r, g, b] => Some(Color::new(r, g, b)),
+        _ => None,
+    };
+    Ok((input, VertexParseResult::Position(pos, color)))
 }
 
 /// Parses a single normal vector with format: `vn x y z`. The x, y and z values
 /// are parsed as floats and the resulting vector (x, y, z) is also normalized.
+/// Normals that are normalized already are kept as they are, so normalizing
+/// them again does not change them by rounding errors.
 fn parse_vertex_normal(input: &str) -> IResult<&str, VertexParseResult> {
-    let (input, _) = tag("vn ")(input)?;
-    let (input, a) = separated_list1(space1, float)(input)?;
-    let pos = Vec3A::from_slice(&a).normalize();
-    Ok((
-        input,
-        VertexParseResult::Normal(pos))
-    )
+    let (input, _) = tag("vn")(input)?;
+    let (input, normal) = vec3(input)?;
+    let normal = if normal.is_normalized() {
+        normal
+    } else {
+        normal.normalize_or_zero()
+    };
+    Ok((input, VertexParseResult::Normal(normal)))
 }
 
 /// Parses a single texture coordinate. Format: `vt x y`. The x and y values are
-/// parsed as floats and should be in the range [0, 1].
+/// parsed as floats and should be in the range [0, 1]. The y value is optional
+/// and defaults to 0, an optional depth value is ignored.
 fn parse_vertex_texture_coordinate(input: &str) -> IResult<&str, VertexParseResult> {
-    let (input, _) = tag("vt ")(input)?;
-    let (input, a) = separated_list1(space1, float)(input)?;
-    Ok((input, VertexParseResult::TexCoord(Vec2::from_slice(&a))))
-}
-
-/// Parses any vertex data and returns the parsed result as a `VertexParseResult`.
-/// Vertex data includes:
-/// - Vertex positions (v x y z)
-/// - Vertex normals (vn x y z)
-/// - Texture coordinates (vt x y)
-fn parse_single_vertex_data(input: &str) -> IResult<&str, VertexParseResult> {
-    alt((
-        parse_vertex_position,
-        parse_vertex_normal,
-        parse_vertex_texture_coordinate,
-    ))(input)
+    let (input, _) = tag("vt")(input)?;
+    let (input, x) = preceded(space1, float)(input)?;
+    let (input, y) = opt(preceded(space1, float))(input)?;
+    let (input, _) = opt(preceded(space1, float))(input)?;
+    Ok((
+        input,
+        VertexParseResult::TexCoord(Vec2::new(x, y.unwrap_or(0.0))),
+    ))
 }
-
-/// Parses a line separated list of vertex data into a vector of `VertexParseResult`.
-fn parse_vertex_data_list(input: &str) -> IResult<&str, Vec<VertexParseResult>> {
-    separated_list1(line_ending, parse_single_vertex_data)(input)
-}
\ No newline at end of file
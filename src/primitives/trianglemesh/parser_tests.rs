@@ -6,7 +6,7 @@ use glam::{Vec2, Vec3A};
 fn parse_single_vertex_position_test() {
     let position = "v 0.0 0.0 0.0";
 
-    let (_, (p, n, m)) = parser::parse_vertex_data(position).unwrap();
+    let (p, n, m) = parser::parse_vertex_data(position).unwrap();
     assert_eq!(p.len(), 1);
     assert_eq!(n.len(), 0);
     assert_eq!(m.len(), 0);
@@ -19,7 +19,7 @@ fn parse_single_vertex_position_test() {
 fn parse_single_vertex_normal_test() {
     let normal = "vn 1.0 0.0 0.0";
 
-    let (_, (p, n, m)) = parser::parse_vertex_data(normal).unwrap();
+    let (p, n, m) = parser::parse_vertex_data(normal).unwrap();
     assert_eq!(p.len(), 0);
     assert_eq!(n.len(), 1);
     assert_eq!(m.len(), 0);
@@ -32,7 +32,7 @@ fn parse_single_vertex_normal_test() {
 fn parse_single_vertex_texture_test() {
     let texture = "vt 0.0 0.0";
 
-    let (_, (p, n, m)) = parser::parse_vertex_data(texture).unwrap();
+    let (p, n, m) = parser::parse_vertex_data(texture).unwrap();
     assert_eq!(p.len(), 0);
     assert_eq!(n.len(), 0);
     assert_eq!(m.len(), 1);
@@ -50,7 +50,7 @@ vn -0.0000 -0.0000 1.0000
 vt 0.625000 0.500000
 vt 0.375000 0.500000";
 
-    let (_, (p, n, m)) = parser::parse_vertex_data(vertex_data).unwrap();
+    let (p, n, m) = parser::parse_vertex_data(vertex_data).unwrap();
     assert_eq!(p.len(), 2);
     assert_eq!(n.len(), 2);
     assert_eq!(m.len(), 2);
@@ -71,4 +71,30 @@ vt 0.375000 0.500000";
     let expected_texture = vec![Vec2::new(0.625000, 0.500000), Vec2::new(0.375000, 0.500000)];
 
     assert_eq!(m, expected_texture);
-}
\ No newline at end of file
+}
+
+#[test]
+fn invalid_vertex_data_test() {
+    let vertex_data = "v 1.0 1.0 1.0\n\nvn 0.0 1.0\nvt 0.5 0.5";
+
+    let error = parser::parse_vertex_data(vertex_data).unwrap_err();
+    assert_eq!(error.kind(), crate::error::ParseErrorKind::Syntax);
+    assert_eq!((error.line(), error.column()), (3, 11));
+
+    let error = parser::parse_vertex_data("v 1.0 2.0 3.0 4.0 x").unwrap_err();
+    assert_eq!((error.line(), error.column()), (1, 19));
+}
+
+#[test]
+fn parse_vertex_color_test() {
+    let vertex = parser::parse_vertex_line("v 1 2 3 0.5 0.25 1", 1).unwrap();
+    let parser::VertexParseResult::Position(position, color) = vertex else {
+        panic!("Expected a vertex position");
+    };
+    assert_eq!(position, Vec3A::new(1.0, 2.0, 3.0));
+    assert_eq!(color, Some(Vec3A::new(0.5, 0.25, 1.0)));
+
+    // A weight is not a color.
+    let vertex = parser::parse_vertex_line("v 1 2 3 0.5", 1).unwrap();
+    assert!(matches!(vertex, parser::VertexParseResult::Position(_, None)));
+}
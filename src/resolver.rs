@@ -0,0 +1,167 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::{Component, Path, PathBuf},
+};
+
+/// Source of the files of a scene, for example the obj file and the material
+/// and texture files it refers to.
+///
+/// Paths are `/` separated and relative to the root of the resolver. Files
+/// referenced by another file are resolved relative to the directory of that
+/// file, see `join_path`.
+pub trait AssetResolver: Send + Sync {
+    /// Open the file at `path` for reading.
+    fn open(&self, path: &str) -> io::Result<Box<dyn BufRead + '_>>;
+
+    /// Read the whole file at `path`.
+    ///
+    /// Default implementation reads everything from `open`.
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        self.open(path)?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Describe where the file at `path` is located, used in error messages.
+    ///
+    /// Default implementation returns the path itself.
+    fn location(&self, path: &str) -> String {
+        path.to_string()
+    }
+}
+
+/// Resolves files relative to a directory of the file system. Absolute paths
+/// are opened as they are.
+#[derive(Debug, Clone)]
+pub struct FileSystemResolver {
+    root: PathBuf,
+    sandboxed: bool,
+}
+
+impl FileSystemResolver {
+    /// Creates a resolver for the files in `root`. Relative paths may leave
+    /// the directory, for example `../textures/wood.png`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            sandboxed: false,
+        }
+    }
+
+    /// Creates a resolver that only opens the files inside of `root`.
+    /// Absolute paths and paths with `..` components that leave the directory
+    /// are rejected.
+    pub fn sandboxed(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            sandboxed: true,
+        }
+    }
+
+    /// Get the file system path of the file at `path`, or an error if the
+    /// resolver is sandboxed and the file is not inside of the root.
+    fn resolve(&self, path: &str) -> io::Result<PathBuf> {
+        let path = join_path("", path);
+        if !self.sandboxed
+            || Path::new(&path)
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+        {
+            Ok(self.root.join(path))
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("'{}' is outside of '{}'", path, self.root.display()),
+            ))
+        }
+    }
+}
+
+impl AssetResolver for FileSystemResolver {
+    fn open(&self, path: &str) -> io::Result<Box<dyn BufRead + '_>> {
+        let file = File::open(self.resolve(path)?)?;
+        Ok(Box::new(BufReader::new(file)))
+    }
+
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        std::fs::read(self.resolve(path)?)
+    }
+
+    fn location(&self, path: &str) -> String {
+        self.root.join(path).display().to_string()
+    }
+}
+
+/// Resolves files from buffers in memory, for example embedded assets or
+/// files created in tests.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryResolver {
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl MemoryResolver {
+    /// Creates a resolver without any files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file, replacing any file with the same path. `.` and `..`
+    /// components of the path are resolved.
+    pub fn insert(&mut self, path: &str, data: impl Into<Vec<u8>>) {
+        self.files.insert(join_path("", path), data.into());
+    }
+
+    /// Adds a file, see `insert`.
+    pub fn with_file(mut self, path: &str, data: impl Into<Vec<u8>>) -> Self {
+        self.insert(path, data);
+        self
+    }
+
+    fn get(&self, path: &str) -> io::Result<&[u8]> {
+        self.files
+            .get(&join_path("", path))
+            .map(Vec::as_slice)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File not found"))
+    }
+}
+
+impl AssetResolver for MemoryResolver {
+    fn open(&self, path: &str) -> io::Result<Box<dyn BufRead + '_>> {
+        Ok(Box::new(self.get(path)?))
+    }
+
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        self.get(path).map(<[u8]>::to_vec)
+    }
+}
+
+/// Get the path of a file referenced as `path` from a file in `directory`.
+/// Absolute paths are returned unchanged, `.` and `..` components of relative
+/// paths are resolved.
+///
+/// ### Examples
+/// - `join_path("models", "car.mtl")` is `models/car.mtl`.
+/// - `join_path("models", "../textures/car.png")` is `textures/car.png`.
+pub fn join_path(directory: &str, path: &str) -> String {
+    if path.starts_with('/') {
+        return path.to_string();
+    }
+    let mut components: Vec<&str> = Vec::new();
+    for component in directory.split('/').chain(path.split('/')) {
+        match component {
+            "" | "." => (),
+            ".." if components.last().is_some_and(|c| *c != "..") => {
+                components.pop();
+            }
+            _ => components.push(component),
+        }
+    }
+    components.join("/")
+}
+
+/// Get the directory of the file at `path`, empty for files in the root.
+pub fn parent_directory(path: &str) -> &str {
+    path.rfind('/').map_or("", |i| &path[..i])
+}
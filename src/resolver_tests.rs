@@ -0,0 +1,78 @@
+use crate::{
+    error::ParseErrorKind,
+    resolver::{join_path, parent_directory, AssetResolver, FileSystemResolver, MemoryResolver},
+    scene::parser::load_obj,
+};
+
+#[test]
+fn join_path_test() {
+    assert_eq!(join_path("", "scene.mtl"), "scene.mtl");
+    assert_eq!(join_path("models", "./car.mtl"), "models/car.mtl");
+    assert_eq!(
+        join_path("models/car", "../textures/car.png"),
+        "models/textures/car.png"
+    );
+    assert_eq!(join_path("", "../car.mtl"), "../car.mtl");
+    assert_eq!(
+        join_path("models", "/textures/car.png"),
+        "/textures/car.png"
+    );
+
+    assert_eq!(parent_directory("models/car.obj"), "models");
+    assert_eq!(parent_directory("car.obj"), "");
+}
+
+#[test]
+fn file_system_resolver_test() {
+    let resolver = FileSystemResolver::new("test");
+    assert!(resolver.read("test.obj").is_ok());
+    assert!(resolver.read("textures/../test.obj").is_ok());
+    assert!(resolver.read("../Cargo.toml").is_ok());
+    assert!(resolver.read("textures/../../Cargo.toml").is_ok());
+    let manifest = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+    assert!(resolver.open(manifest).is_ok());
+}
+
+#[test]
+fn sandboxed_file_system_resolver_test() {
+    let resolver = FileSystemResolver::sandboxed("test");
+    assert!(resolver.read("test.obj").is_ok());
+    assert!(resolver.read("textures/../test.obj").is_ok());
+
+    // Files outside of the root can't be opened.
+    for path in ["../Cargo.toml", "textures/../../Cargo.toml", "/etc/hosts"] {
+        let error = resolver.open(path).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput, "{}", path);
+        let error = resolver.read(path).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput, "{}", path);
+    }
+}
+
+#[test]
+fn memory_resolver_test() {
+    let obj = "mtllib ../materials/red.mtl
+o Triangle
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vn 0 0 1
+usemtl Red
+f 1//1 2//1 3//1";
+    let resolver = MemoryResolver::new()
+        .with_file("models/triangle.obj", obj)
+        .with_file("./materials/red.mtl", "newmtl Red\nKd 1 0 0");
+    assert_eq!(
+        resolver.read("materials/red.mtl").unwrap(),
+        b"newmtl Red\nKd 1 0 0"
+    );
+
+    // Material libraries are relative to the obj file.
+    let (mesh, material_map, object_map) = load_obj(&resolver, "models/triangle.obj").unwrap();
+    let triangle = object_map["Triangle"][0];
+    assert_eq!(triangle.material_index(), material_map["Red"]);
+    assert_eq!(mesh.materials()[material_map["Red"]].diffuse_color.x, 1.0);
+
+    let error = load_obj(&resolver, "models/missing.obj").unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::Io);
+    assert_eq!(error.file(), "models/missing.obj");
+}
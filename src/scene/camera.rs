@@ -1,18 +1,15 @@
 use glam::{Mat4, Vec2, Vec3A, Vec4, Vec3, Vec4Swizzles};
 
-use crate::primitives::Ray;
+use crate::primitives::{Ray, RayPacket};
 
 pub struct Camera {
-    projection: Mat4,
     inverse_projection: Mat4,
-    view: Mat4,
     inverse_view: Mat4,
     position: Vec3A,
     target: Vec3A,
     z_near: f32,
     z_far: f32,
     vertical_fov: f32,
-    aspect_ratio: f32,
     width: u32,
     height: u32,
 }
@@ -35,16 +32,13 @@ impl Camera {
         let view = Mat4::look_at_rh(position.into(), target.into(), Vec3::Y);
         let inverse_view = view.inverse();
         Self {
-            projection,
             inverse_projection,
-            view,
             inverse_view,
             position,
             target,
             z_near,
             z_far,
             vertical_fov,
-            aspect_ratio,
             width,
             height,
         }
@@ -67,6 +61,30 @@ impl Camera {
         (self.width, self.height)
     }
 
+    /// Get the position of the camera.
+    #[inline]
+    pub fn position(&self) -> Vec3A {
+        self.position
+    }
+
+    /// Get the point the camera is looking at.
+    #[inline]
+    pub fn target(&self) -> Vec3A {
+        self.target
+    }
+
+    /// Get the near and far clipping plane distances.
+    #[inline]
+    pub fn clipping_planes(&self) -> (f32, f32) {
+        (self.z_near, self.z_far)
+    }
+
+    /// Get the vertical field of view in degrees.
+    #[inline]
+    pub fn vertical_fov(&self) -> f32 {
+        self.vertical_fov
+    }
+
     /// Get a ray from the camera to the given pixel.
     #[inline]
     pub fn get_ray(&self, x: u32, y: u32) -> Ray {
@@ -94,6 +112,29 @@ impl Camera {
         Ray::new(self.position, ray_dir.into())
     }
 
+    /// Get a packet with the rays of the 2x2 pixel tile whose top left pixel
+    /// is (x, y). The rays are ordered (x, y), (x + 1, y), (x, y + 1) and
+    /// (x + 1, y + 1).
+    #[inline]
+    pub fn get_ray_packet(&self, x: u32, y: u32) -> RayPacket {
+        RayPacket::new([
+            self.get_ray(x, y),
+            self.get_ray(x + 1, y),
+            self.get_ray(x, y + 1),
+            self.get_ray(x + 1, y + 1),
+        ])
+    }
+
+    /// Same as `get_ray_packet`, but every ray is jittered within its pixel.
+    #[inline]
+    pub fn get_jittered_ray_packet(&self, x: u32, y: u32) -> RayPacket {
+        RayPacket::new([
+            self.get_jittered_ray(x, y),
+            self.get_jittered_ray(x + 1, y),
+            self.get_jittered_ray(x, y + 1),
+            self.get_jittered_ray(x + 1, y + 1),
+        ])
+    }
 }
 
 impl Default for Camera {
@@ -176,6 +217,20 @@ impl CameraBuilder {
         self
     }
 
+    /// Use the position, target, field of view and clipping planes of
+    /// `camera`, for example a camera of a glTF file, if neither the position
+    /// nor the target are set. The resolution is kept.
+    pub(crate) fn or_camera(self, camera: &CameraBuilder) -> Self {
+        if self.position.is_some() || self.target.is_some() {
+            return self;
+        }
+        Self {
+            width: self.width,
+            height: self.height,
+            ..camera.clone()
+        }
+    }
+
     /// Build the camera, based on the parameters set.
     #[inline]
     pub fn build(self) -> Camera {
@@ -0,0 +1,1002 @@
+use std::{collections::HashMap, f32::consts::FRAC_PI_4, sync::Arc};
+
+use glam::{Mat3A, Mat4, Vec3A};
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_till1},
+    character::complete::{self, space1},
+    combinator::{opt, value},
+    number::complete::float,
+    sequence::{pair, preceded, tuple},
+    IResult,
+};
+
+use crate::{
+    error::{column, expect_end_of_line, syntax_error, ParseError, ParseErrorKind},
+    material::{self, Material, Texture},
+    primitives::{IntersectionAlgorithm, Normal, Position, TexCoord, TriangleIndex, TriangleMesh},
+    resolver::{self, AssetResolver},
+};
+
+use super::{
+    camera::CameraBuilder, gltf, parser, parser::ObjectMap, ply, stl, InstanceDescription, Light,
+    LightKind,
+};
+
+/// Geometry loaded from a file of any of the supported formats.
+#[derive(Debug)]
+pub struct Geometry {
+    pub mesh: TriangleMesh,
+    /// Index of every named material in the materials of the mesh.
+    pub material_map: HashMap<String, usize>,
+    pub object_map: ObjectMap,
+    /// Objects that are only rendered through `instances`, see
+    /// `gltf::GltfScene::prototypes`.
+    pub prototypes: ObjectMap,
+    pub instances: Vec<InstanceDescription>,
+    pub lights: Vec<Light>,
+    /// Cameras of the file, only glTF files have cameras.
+    pub cameras: Vec<CameraBuilder>,
+}
+
+/// Load the geometry of a file. The format is chosen by the extension of the
+/// file: glTF (`.gltf` and `.glb`), ply (`.ply`) and stl (`.stl`). Files with
+/// any other extension are loaded as obj files.
+///
+/// ### Arguments
+/// - `resolver` - The source of the file and the files it refers to.
+/// - `file` - The path of the file in the resolver.
+/// - `stl_material` - The material of stl files, see `stl::load_stl`.
+/// - `crease_angle` - The crease angle in radians of the smooth normals of
+///   obj faces without normals, see `parser::load_obj_with_crease_angle`.
+pub fn load_geometry(
+    resolver: &dyn AssetResolver,
+    file: &str,
+    stl_material: Option<Material>,
+    crease_angle: Option<f32>,
+) -> Result<Geometry, ParseError> {
+    let extension = file.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase());
+    let geometry = match extension.as_deref() {
+        Some("gltf" | "glb") => {
+            let gltf = gltf::load_gltf(resolver, file)?;
+            Geometry {
+                mesh: gltf.mesh,
+                material_map: gltf.material_map,
+                object_map: gltf.object_map,
+                prototypes: gltf.prototypes,
+                instances: gltf.instances,
+                lights: gltf.lights,
+                cameras: gltf.cameras,
+            }
+        }
+        Some("ply") => {
+            let (mesh, object_map) = ply::load_ply(resolver, file)?;
+            Geometry {
+                mesh,
+                material_map: HashMap::new(),
+                object_map,
+                prototypes: ObjectMap::new(),
+                instances: Vec::new(),
+                lights: Vec::new(),
+                cameras: Vec::new(),
+            }
+        }
+        Some("stl") => {
+            let (mesh, object_map) = stl::load_stl(resolver, file, stl_material)?;
+            Geometry {
+                mesh,
+                material_map: HashMap::new(),
+                object_map,
+                prototypes: ObjectMap::new(),
+                instances: Vec::new(),
+                lights: Vec::new(),
+                cameras: Vec::new(),
+            }
+        }
+        _ => {
+            let (mesh, material_map, object_map) =
+                parser::load_obj_with_crease_angle(resolver, file, crease_angle)?;
+            Geometry {
+                mesh,
+                material_map,
+                object_map,
+                prototypes: ObjectMap::new(),
+                instances: Vec::new(),
+                lights: Vec::new(),
+                cameras: Vec::new(),
+            }
+        }
+    };
+    Ok(geometry)
+}
+
+/// Render settings of a scene description. Settings that are `None` are left
+/// to the renderer.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderSettings {
+    pub sample_count: Option<u32>,
+    pub recursion_depth: Option<u32>,
+    pub intersection_algorithm: Option<IntersectionAlgorithm>,
+}
+
+/// A file included in a scene description.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Include {
+    /// Path of the file in the resolver.
+    pub path: String,
+    /// Transform from the space of the file to world space.
+    pub transform: Mat4,
+    /// Material of the scene description used for all triangles of the file.
+    pub material: Option<usize>,
+    /// Materials of the scene description replacing materials of the file,
+    /// by the name of the material in the file.
+    pub overrides: HashMap<String, MaterialOverride>,
+    /// Crease angle in radians of the smooth normals generated for obj faces
+    /// without normals, see `load_geometry`.
+    pub crease_angle: Option<f32>,
+}
+
+/// Material of a scene description replacing a material of an included file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaterialOverride {
+    /// Index of the material in the materials of the scene description.
+    pub material: usize,
+    /// Line of the `override` statement in the scene file.
+    pub line: usize,
+    /// Column of the name of the replaced material in the `override`
+    /// statement.
+    pub column: usize,
+}
+
+/// Scene described by a scene file, see `load_scene_description`.
+#[derive(Debug, Clone, Default)]
+pub struct SceneDescription {
+    /// Location of the scene file, used in error messages. Empty if the
+    /// description was not loaded from a file.
+    pub file: String,
+    pub camera: CameraBuilder,
+    pub settings: RenderSettings,
+    /// Materials defined by the scene file or loaded from its material
+    /// libraries.
+    pub materials: Vec<Material>,
+    /// Index of every material in `materials`, by name.
+    pub material_map: HashMap<String, usize>,
+    pub lights: Vec<Light>,
+    pub includes: Vec<Include>,
+}
+
+/// Load a scene description file from `resolver`. A scene file is made of
+/// blocks, each starting with a statement of the following list. The lines
+/// after the statement set the properties of the block, up to the next
+/// block. Empty lines and comments starting with `#` are skipped.
+///
+/// - `camera` - The camera, with the properties `position x y z`,
+///   `target x y z`, `fov degrees` (vertical field of view), `near distance`,
+///   `far distance` and `resolution width height`.
+/// - `render` - The render settings, with the properties `samples count`,
+///   `depth count` (recursion depth) and `intersection moller_trumbore` or
+///   `intersection watertight`.
+/// - `mtllib file` - Load the materials of an mtl file. The statement has no
+///   properties.
+/// - `material name` - A material, with the properties of mtl files (`Kd`,
+///   `map_Kd`, `illum`, ...).
+/// - `light point`, `light spot` or `light directional` - A light, with the
+///   properties `position x y z`, `direction x y z`, `color r g b`,
+///   `intensity value`, `range distance` and, for spot lights,
+///   `cone inner outer` (cone angles in degrees).
+/// - `include file` - Geometry loaded with `load_geometry`, with the
+///   properties `translate x y z`, `rotate degrees x y z` (around the axis
+///   x y z), `scale factor` or `scale x y z`, `usemtl name` to use a material
+///   of the scene file for all triangles and `override file_material name`
+///   to replace a material of the file and `smooth degrees` to generate
+///   smooth normals with a crease angle for obj faces without normals.
+///   Transforms are applied in the order they are listed.
+///
+/// Files are resolved relative to the scene file. Materials must be defined
+/// before they are used.
+///
+/// ### Arguments
+/// - `resolver` - The source of the scene file and the files it refers to.
+/// - `path` - The path of the scene file in the resolver.
+///
+/// ### Returns
+/// The scene description, with the materials and textures loaded. Included
+/// files are loaded by `SceneDescription::load`. An error is returned if the
+/// scene file or its material libraries can't be read or parsed.
+pub fn load_scene_description(
+    resolver: &dyn AssetResolver,
+    path: &str,
+) -> Result<SceneDescription, ParseError> {
+    let location = resolver.location(path);
+    let mut input = String::new();
+    resolver
+        .open(path)
+        .and_then(|mut reader| reader.read_to_string(&mut input))
+        .map_err(|e| ParseError::io(&location, e))?;
+    let description = parse_in(&input, resolver, resolver::parent_directory(path))
+        .map_err(|e| e.in_file(&location))?;
+    Ok(SceneDescription {
+        file: location,
+        ..description
+    })
+}
+
+/// Parse the contents of a scene file that is already in memory. Files are
+/// loaded from `resolver`, relative to its root. See `load_scene_description`.
+pub fn parse_scene_description(
+    input: &str,
+    resolver: &dyn AssetResolver,
+) -> Result<SceneDescription, ParseError> {
+    parse_in(input, resolver, "")
+}
+
+fn parse_in(
+    input: &str,
+    resolver: &dyn AssetResolver,
+    directory: &str,
+) -> Result<SceneDescription, ParseError> {
+    let mut parser = DescriptionParser {
+        resolver,
+        directory,
+        description: SceneDescription::default(),
+        inline_materials: Vec::new(),
+        textures: HashMap::new(),
+        block: Block::None,
+    };
+    for (i, line) in input.lines().enumerate() {
+        parser.parse_line(line, i + 1)?;
+    }
+    parser.finish(input)
+}
+
+/// Block of a scene file that properties are added to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    None,
+    Camera,
+    Render,
+    Material(usize),
+    Light(usize),
+    Include(usize),
+}
+
+/// State of a scene file while it is parsed line by line.
+struct DescriptionParser<'a> {
+    resolver: &'a dyn AssetResolver,
+    /// Directory of the scene file in the resolver.
+    directory: &'a str,
+    description: SceneDescription,
+    /// Materials defined in the scene file, whose textures are resolved
+    /// relative to the scene file.
+    inline_materials: Vec<usize>,
+    textures: HashMap<String, Arc<Texture>>,
+    block: Block,
+}
+
+impl<'a> DescriptionParser<'a> {
+    fn parse_line(&mut self, line: &str, line_number: usize) -> Result<(), ParseError> {
+        let keyword = match line.split_whitespace().next() {
+            Some(keyword) if !keyword.starts_with('#') => keyword,
+            _ => return Ok(()),
+        };
+        let input = &line.trim_start()[keyword.len()..];
+        let description = &mut self.description;
+
+        match keyword {
+            "camera" | "render" => {
+                expect_end_of_line(line, line_number, input)?;
+                self.block = if keyword == "camera" {
+                    Block::Camera
+                } else {
+                    Block::Render
+                };
+            }
+            "mtllib" => {
+                let file = arguments(line, line_number, input, "mtllib file", argument)?;
+                let path = resolver::join_path(self.directory, file);
+                let (materials, material_map) =
+                    parser::load_materials(self.resolver, &path, &mut self.textures)?;
+                let offset = description.materials.len();
+                description.materials.extend(materials);
+                description
+                    .material_map
+                    .extend(material_map.into_iter().map(|(name, i)| (name, i + offset)));
+                self.block = Block::None;
+            }
+            "material" => {
+                let name = arguments(line, line_number, input, "material name", argument)?;
+                let index = description.materials.len();
+                description.material_map.insert(name.to_owned(), index);
+                description.materials.push(Material::default());
+                self.inline_materials.push(index);
+                self.block = Block::Material(index);
+            }
+            "light" => {
+                let kind = arguments(
+                    line,
+                    line_number,
+                    input,
+                    "light point|spot|directional",
+                    light_kind,
+                )?;
+                description.lights.push(Light::new(kind));
+                self.block = Block::Light(description.lights.len() - 1);
+            }
+            "include" => {
+                let file = arguments(line, line_number, input, "include file", argument)?;
+                description.includes.push(Include {
+                    path: resolver::join_path(self.directory, file),
+                    transform: Mat4::IDENTITY,
+                    material: None,
+                    overrides: HashMap::new(),
+                    crease_angle: None,
+                });
+                self.block = Block::Include(description.includes.len() - 1);
+            }
+            _ => self.parse_property(line, line_number, keyword, input)?,
+        }
+        Ok(())
+    }
+
+    /// Parse a property of the current block.
+    fn parse_property(
+        &mut self,
+        line: &str,
+        line_number: usize,
+        keyword: &str,
+        input: &str,
+    ) -> Result<(), ParseError> {
+        let description = &mut self.description;
+        let unknown = |block: &str| {
+            ParseError::new(
+                ParseErrorKind::Syntax,
+                line_number,
+                column(line, keyword),
+                format!("Unknown property '{}' of {}", keyword, block),
+            )
+        };
+
+        match self.block {
+            Block::None => {
+                return Err(ParseError::new(
+                    ParseErrorKind::Syntax,
+                    line_number,
+                    column(line, keyword),
+                    format!("Unknown statement '{}'", keyword),
+                ))
+            }
+            Block::Camera => {
+                let camera = std::mem::take(&mut description.camera);
+                description.camera = match keyword {
+                    "position" => camera.with_position(arguments(
+                        line,
+                        line_number,
+                        input,
+                        "position x y z",
+                        vector,
+                    )?),
+                    "target" => camera.with_target(arguments(
+                        line,
+                        line_number,
+                        input,
+                        "target x y z",
+                        vector,
+                    )?),
+                    "fov" => camera.with_vertical_fov(arguments(
+                        line,
+                        line_number,
+                        input,
+                        "fov degrees",
+                        scalar,
+                    )?),
+                    "near" => camera.with_z_near(arguments(
+                        line,
+                        line_number,
+                        input,
+                        "near distance",
+                        scalar,
+                    )?),
+                    "far" => camera.with_z_far(arguments(
+                        line,
+                        line_number,
+                        input,
+                        "far distance",
+                        scalar,
+                    )?),
+                    "resolution" => {
+                        let (width, height) = arguments(
+                            line,
+                            line_number,
+                            input,
+                            "resolution width height",
+                            pair(integer, integer),
+                        )?;
+                        camera.with_width(width).with_height(height)
+                    }
+                    _ => return Err(unknown("camera")),
+                };
+            }
+            Block::Render => {
+                let settings = &mut description.settings;
+                match keyword {
+                    "samples" => {
+                        settings.sample_count = Some(arguments(
+                            line,
+                            line_number,
+                            input,
+                            "samples count",
+                            integer,
+                        )?)
+                    }
+                    "depth" => {
+                        settings.recursion_depth =
+                            Some(arguments(line, line_number, input, "depth count", integer)?)
+                    }
+                    "intersection" => {
+                        settings.intersection_algorithm = Some(arguments(
+                            line,
+                            line_number,
+                            input,
+                            "intersection moller_trumbore|watertight",
+                            intersection_algorithm,
+                        )?)
+                    }
+                    _ => return Err(unknown("render settings")),
+                }
+            }
+            Block::Material(index) => material::parser::parse_property(
+                &mut description.materials[index],
+                line,
+                line_number,
+                keyword,
+            )?,
+            Block::Light(index) => {
+                let light = &mut description.lights[index];
+                match keyword {
+                    "position" => {
+                        light.position =
+                            arguments(line, line_number, input, "position x y z", vector)?
+                    }
+                    "direction" => {
+                        light.direction =
+                            arguments(line, line_number, input, "direction x y z", vector)?
+                                .normalize_or_zero()
+                    }
+                    "color" => {
+                        light.color = arguments(line, line_number, input, "color r g b", vector)?
+                    }
+                    "intensity" => {
+                        light.intensity =
+                            arguments(line, line_number, input, "intensity value", scalar)?
+                    }
+                    "range" => {
+                        light.range = Some(arguments(
+                            line,
+                            line_number,
+                            input,
+                            "range distance",
+                            scalar,
+                        )?)
+                    }
+                    "cone" => {
+                        let (inner, outer) = arguments(
+                            line,
+                            line_number,
+                            input,
+                            "cone inner outer",
+                            pair(scalar, scalar),
+                        )?;
+                        let LightKind::Spot {
+                            inner_cone_angle,
+                            outer_cone_angle,
+                        } = &mut light.kind
+                        else {
+                            return Err(ParseError::new(
+                                ParseErrorKind::Syntax,
+                                line_number,
+                                column(line, keyword),
+                                "Only spot lights have a cone",
+                            ));
+                        };
+                        *inner_cone_angle = inner.to_radians();
+                        *outer_cone_angle = outer.to_radians();
+                    }
+                    _ => return Err(unknown("light")),
+                }
+            }
+            Block::Include(index) => {
+                let transform = match keyword {
+                    "translate" => Mat4::from_translation(
+                        arguments(line, line_number, input, "translate x y z", vector)?.into(),
+                    ),
+                    "rotate" => {
+                        let (angle, axis) = arguments(
+                            line,
+                            line_number,
+                            input,
+                            "rotate degrees x y z",
+                            pair(scalar, vector),
+                        )?;
+                        let axis = axis.try_normalize().ok_or_else(|| {
+                            ParseError::new(
+                                ParseErrorKind::Syntax,
+                                line_number,
+                                column(line, keyword),
+                                "Rotation axis must not be zero",
+                            )
+                        })?;
+                        Mat4::from_axis_angle(axis.into(), angle.to_radians())
+                    }
+                    "scale" => Mat4::from_scale(
+                        arguments(line, line_number, input, "scale x [y z]", scale)?.into(),
+                    ),
+                    "usemtl" => {
+                        let name = arguments(line, line_number, input, "usemtl name", argument)?;
+                        let material = material_index(description, line, line_number, name)?;
+                        description.includes[index].material = Some(material);
+                        return Ok(());
+                    }
+                    "override" => {
+                        let (file_material, name) = arguments(
+                            line,
+                            line_number,
+                            input,
+                            "override file_material name",
+                            pair(word, word),
+                        )?;
+                        let material = material_index(description, line, line_number, name)?;
+                        description.includes[index].overrides.insert(
+                            file_material.to_owned(),
+                            MaterialOverride {
+                                material,
+                                line: line_number,
+                                column: column(line, file_material),
+                            },
+                        );
+                        return Ok(());
+                    }
+                    "smooth" => {
+                        let angle =
+                            arguments(line, line_number, input, "smooth crease_angle", scalar)?;
+                        description.includes[index].crease_angle = Some(angle.to_radians());
+                        return Ok(());
+                    }
+                    _ => return Err(unknown("include")),
+                };
+                let include = &mut description.includes[index];
+                include.transform = transform * include.transform;
+            }
+        }
+        Ok(())
+    }
+
+    /// Load the textures of the materials defined in the scene file, whose
+    /// contents are `input`.
+    fn finish(mut self, input: &str) -> Result<SceneDescription, ParseError> {
+        for &index in &self.inline_materials {
+            parser::load_texture_maps(
+                self.resolver,
+                self.directory,
+                std::slice::from_mut(&mut self.description.materials[index]),
+                &mut self.textures,
+                input,
+            )?;
+        }
+        Ok(self.description)
+    }
+}
+
+impl SceneDescription {
+    /// Load the included files and combine them into a single mesh. The
+    /// transforms of the includes are applied to the positions, normals and
+    /// lights of the files, and the materials of the files are replaced as
+    /// the includes specify.
+    ///
+    /// A file included more than once with the same crease angle is loaded
+    /// only once. Its objects are combined into a prototype, one for every
+    /// combination of materials the includes use, and every include places
+    /// an instance of the prototype named `path.index`, after the index of
+    /// the include.
+    ///
+    /// ### Returns
+    /// The combined geometry. The materials of the mesh start with the
+    /// materials of the scene description, so `material_map` is the material
+    /// map of the scene description. The lights are the lights of the scene
+    /// description followed by the lights of the included files. Objects with
+    /// the same name in multiple files are combined, prototypes with the same
+    /// name are renamed. The cameras of the files are not used, the scene
+    /// description has a camera of its own. An error is returned if an
+    /// included file can't be loaded, or if an overridden material is not in
+    /// the file. The error is reported at the `override` statement in the
+    /// scene file.
+    ///
+    /// ### Arguments
+    /// - `resolver` - The source of the included files.
+    /// - `stl_material` - The material of included stl files, see
+    ///   `load_geometry`. Replaced by `usemtl` of the include.
+    pub fn load(
+        &self,
+        resolver: &dyn AssetResolver,
+        stl_material: Option<&Material>,
+    ) -> Result<Geometry, ParseError> {
+        let mut combined = CombinedGeometry {
+            materials: self.materials.clone(),
+            lights: self.lights.clone(),
+            ..CombinedGeometry::default()
+        };
+
+        // Files are identified by their path and the crease angle they are
+        // loaded with.
+        let key =
+            |include: &Include| (include.path.clone(), include.crease_angle.map(f32::to_bits));
+        let mut include_counts = HashMap::new();
+        for include in &self.includes {
+            *include_counts.entry(key(include)).or_insert(0) += 1;
+        }
+        let mut shared_files = HashMap::new();
+        // Name of the prototype of every shared file and the names of the
+        // prototypes of the file itself, by file and material indices.
+        let mut shared_prototypes = HashMap::new();
+
+        for (i, include) in self.includes.iter().enumerate() {
+            let key = key(include);
+            let shared = include_counts[&key] > 1;
+            let single_file;
+            let (geometry, offsets) = if shared {
+                if !shared_files.contains_key(&key) {
+                    let geometry = load_geometry(
+                        resolver,
+                        &include.path,
+                        stl_material.cloned(),
+                        include.crease_angle,
+                    )?;
+                    let offsets = combined.add_vertex_data(&geometry.mesh, Mat4::IDENTITY);
+                    shared_files.insert(key.clone(), (geometry, offsets));
+                }
+                &shared_files[&key]
+            } else {
+                let geometry = load_geometry(
+                    resolver,
+                    &include.path,
+                    stl_material.cloned(),
+                    include.crease_angle,
+                )?;
+                let offsets = combined.add_vertex_data(&geometry.mesh, include.transform);
+                single_file = (geometry, offsets);
+                &single_file
+            };
+
+            // Index of every material of the file in the combined materials.
+            let mut material_indices: Vec<usize> =
+                (offsets.material..offsets.material + geometry.mesh.materials().len()).collect();
+            for (name, material_override) in &include.overrides {
+                let Some(&index) = geometry.material_map.get(name) else {
+                    return Err(ParseError::new(
+                        ParseErrorKind::UnknownMaterial,
+                        material_override.line,
+                        material_override.column,
+                        format!(
+                            "Overridden material '{}' is not in '{}'",
+                            name, include.path
+                        ),
+                    )
+                    .in_file(&self.file));
+                };
+                material_indices[index] = material_override.material;
+            }
+            if let Some(material) = include.material {
+                material_indices.fill(material);
+            }
+            let triangles = |triangles: &[TriangleIndex]| {
+                triangles
+                    .iter()
+                    .map(|t| offsets.triangle(t, &material_indices))
+                    .collect::<Vec<_>>()
+            };
+
+            // The prototypes are added once for every combination of
+            // materials of a file.
+            let (file_prototype, prototype_names) = shared_prototypes
+                .entry((key, material_indices.clone()))
+                .or_insert_with(|| {
+                    let names: HashMap<String, String> = geometry
+                        .prototypes
+                        .iter()
+                        .map(|(name, prototype)| {
+                            (
+                                name.clone(),
+                                combined.add_prototype(name, triangles(prototype)),
+                            )
+                        })
+                        .collect();
+                    let file_prototype = if shared {
+                        let file_triangles: Vec<TriangleIndex> =
+                            geometry.object_map.values().flatten().copied().collect();
+                        (!file_triangles.is_empty()).then(|| {
+                            combined.add_prototype(&include.path, triangles(&file_triangles))
+                        })
+                    } else {
+                        None
+                    };
+                    (file_prototype, names)
+                });
+
+            if shared {
+                if let Some(prototype) = file_prototype {
+                    combined.instances.push(InstanceDescription {
+                        identifier: format!("{}.{}", include.path, i),
+                        prototype: prototype.clone(),
+                        transform: include.transform,
+                    });
+                }
+            } else {
+                for (name, object) in &geometry.object_map {
+                    combined
+                        .object_map
+                        .entry(name.clone())
+                        .or_default()
+                        .extend(triangles(object));
+                }
+            }
+            // The prototypes of the file are in the space of its vertex data,
+            // which is transformed by the include if it is not shared.
+            let inverse_transform = offsets.transform.inverse();
+            combined
+                .instances
+                .extend(geometry.instances.iter().filter_map(|instance| {
+                    Some(InstanceDescription {
+                        identifier: instance.identifier.clone(),
+                        prototype: prototype_names.get(&instance.prototype)?.clone(),
+                        transform: include.transform * instance.transform * inverse_transform,
+                    })
+                }));
+
+            let transform = include.transform;
+            combined.lights.extend(geometry.lights.iter().map(|light| {
+                Light {
+                    position: transform.transform_point3a(light.position),
+                    direction: transform
+                        .transform_vector3a(light.direction)
+                        .normalize_or_zero(),
+                    ..light.clone()
+                }
+            }));
+        }
+        Ok(combined.finish(self.material_map.clone()))
+    }
+}
+
+/// Geometry of the included files of a scene description while they are
+/// combined, see `SceneDescription::load`.
+#[derive(Default)]
+struct CombinedGeometry {
+    positions: Vec<Position>,
+    normals: Vec<Normal>,
+    tex_coords: Vec<TexCoord>,
+    colors: Vec<Vec3A>,
+    materials: Vec<Material>,
+    object_map: ObjectMap,
+    prototypes: ObjectMap,
+    instances: Vec<InstanceDescription>,
+    lights: Vec<Light>,
+}
+
+impl CombinedGeometry {
+    /// Add the vertex data and the materials of a mesh, with the positions
+    /// and normals transformed by `transform`.
+    fn add_vertex_data(&mut self, mesh: &TriangleMesh, transform: Mat4) -> FileOffsets {
+        let offsets = FileOffsets {
+            vertex: self.positions.len(),
+            normal: self.normals.len(),
+            tex_coord: self.tex_coords.len(),
+            material: self.materials.len(),
+            transform,
+        };
+        let normal_transform = Mat3A::from_mat4(transform.inverse()).transpose();
+        self.positions.extend(
+            mesh.vertex_positions()
+                .iter()
+                .map(|&p| transform.transform_point3a(p)),
+        );
+        self.normals.extend(
+            mesh.triangle_normals()
+                .iter()
+                .map(|&n| (normal_transform * n).normalize_or_zero()),
+        );
+        self.tex_coords.extend(mesh.tex_coords());
+        // Files without vertex colors are white, so they keep the colors of
+        // their materials.
+        if !mesh.vertex_colors().is_empty() {
+            self.colors.resize(offsets.vertex, Vec3A::ONE);
+            self.colors.extend(mesh.vertex_colors());
+        }
+        self.materials.extend(mesh.materials().iter().cloned());
+        offsets
+    }
+
+    /// Add a prototype, renamed if there already is a prototype with the
+    /// same name.
+    ///
+    /// ### Returns
+    /// The name of the prototype.
+    fn add_prototype(&mut self, name: &str, triangles: Vec<TriangleIndex>) -> String {
+        let mut name = name.to_owned();
+        while self.prototypes.contains_key(&name) {
+            name.push('_');
+        }
+        self.prototypes.insert(name.clone(), triangles);
+        name
+    }
+
+    /// Create the combined mesh.
+    fn finish(mut self, material_map: HashMap<String, usize>) -> Geometry {
+        if !self.colors.is_empty() {
+            self.colors.resize(self.positions.len(), Vec3A::ONE);
+        }
+        let mut mesh = TriangleMesh::with_tex_coords(
+            self.positions,
+            self.normals,
+            self.tex_coords,
+            self.materials,
+        );
+        mesh.set_vertex_colors(self.colors);
+        Geometry {
+            mesh,
+            material_map,
+            object_map: self.object_map,
+            prototypes: self.prototypes,
+            instances: self.instances,
+            lights: self.lights,
+            cameras: Vec::new(),
+        }
+    }
+}
+
+/// Where the vertex data and the materials of a file start in the combined
+/// geometry, and the transform of its positions and normals.
+#[derive(Debug, Clone, Copy)]
+struct FileOffsets {
+    vertex: usize,
+    normal: usize,
+    tex_coord: usize,
+    material: usize,
+    transform: Mat4,
+}
+
+impl FileOffsets {
+    /// Get a triangle of the file in the combined geometry.
+    ///
+    /// ### Arguments
+    /// - `triangle` - The triangle in the file.
+    /// - `material_indices` - The index of every material of the file in the
+    ///   combined materials.
+    fn triangle(&self, triangle: &TriangleIndex, material_indices: &[usize]) -> TriangleIndex {
+        // Mirroring transforms turn the triangles inside out, so the winding
+        // is flipped to keep the faces pointing along the normals.
+        let flip = self.transform.determinant() < 0.0;
+        let offset = |(a, b, c): (usize, usize, usize), offset: usize| {
+            if flip {
+                (a + offset, c + offset, b + offset)
+            } else {
+                (a + offset, b + offset, c + offset)
+            }
+        };
+        let mut combined = TriangleIndex::with_normals(
+            offset(triangle.vertex_indices(), self.vertex),
+            offset(triangle.normal_indices(), self.normal),
+            material_indices[triangle.material_index()],
+        );
+        combined.set_tex_coord_indices(
+            triangle
+                .tex_coord_indices()
+                .map(|i| offset(i, self.tex_coord)),
+        );
+        combined
+    }
+}
+
+/// Get the index of a material of the scene description, or an error if the
+/// material is not defined (yet).
+fn material_index(
+    description: &SceneDescription,
+    line: &str,
+    line_number: usize,
+    name: &str,
+) -> Result<usize, ParseError> {
+    description.material_map.get(name).copied().ok_or_else(|| {
+        ParseError::new(
+            ParseErrorKind::UnknownMaterial,
+            line_number,
+            column(line, name),
+            format!("Unknown material '{}'", name),
+        )
+    })
+}
+
+/// Parse the arguments of a statement or property with `parser`, and check
+/// that nothing but a comment follows them.
+///
+/// ### Arguments
+/// - `line` - The whole line.
+/// - `line_number` - The number of the line, for errors.
+/// - `input` - The rest of the line after the keyword.
+/// - `format` - The expected form of the line, for errors.
+/// - `parser` - The parser of the arguments.
+fn arguments<'a, T>(
+    line: &'a str,
+    line_number: usize,
+    input: &'a str,
+    format: &str,
+    mut parser: impl FnMut(&'a str) -> IResult<&'a str, T>,
+) -> Result<T, ParseError> {
+    let (remaining, value) = parser(input)
+        .map_err(|e| syntax_error(line, line_number, e, &format!("Expected `{}`", format)))?;
+    expect_end_of_line(line, line_number, remaining)?;
+    Ok(value)
+}
+
+/// Parse an argument that is the rest of the line up to a comment, like a
+/// file or material name that may contain spaces.
+fn argument(input: &str) -> IResult<&str, &str> {
+    let (remaining, argument) = preceded(space1, take_till1(|c| c == '#'))(input)?;
+    Ok((remaining, argument.trim_end()))
+}
+
+/// Parse a single word argument.
+fn word(input: &str) -> IResult<&str, &str> {
+    preceded(space1, take_till1(|c: char| c.is_whitespace() || c == '#'))(input)
+}
+
+fn scalar(input: &str) -> IResult<&str, f32> {
+    preceded(space1, float)(input)
+}
+
+fn integer(input: &str) -> IResult<&str, u32> {
+    preceded(space1, complete::u32)(input)
+}
+
+fn vector(input: &str) -> IResult<&str, Vec3A> {
+    let (input, (x, y, z)) = tuple((scalar, scalar, scalar))(input)?;
+    Ok((input, Vec3A::new(x, y, z)))
+}
+
+/// Parse a scale of the form `x y z`, or `s` for a uniform scale.
+fn scale(input: &str) -> IResult<&str, Vec3A> {
+    let (input, x) = scalar(input)?;
+    let (input, yz) = opt(pair(scalar, scalar))(input)?;
+    let scale = match yz {
+        Some((y, z)) => Vec3A::new(x, y, z),
+        None => Vec3A::splat(x),
+    };
+    Ok((input, scale))
+}
+
+/// Parse the kind of a light. Spot lights have the default cone of glTF
+/// spot lights.
+fn light_kind(input: &str) -> IResult<&str, LightKind> {
+    let spot = LightKind::Spot {
+        inner_cone_angle: 0.0,
+        outer_cone_angle: FRAC_PI_4,
+    };
+    preceded(
+        space1,
+        alt((
+            value(LightKind::Point, tag("point")),
+            value(spot, tag("spot")),
+            value(LightKind::Directional, tag("directional")),
+        )),
+    )(input)
+}
+
+fn intersection_algorithm(input: &str) -> IResult<&str, IntersectionAlgorithm> {
+    preceded(
+        space1,
+        alt((
+            value(
+                IntersectionAlgorithm::MollerTrumbore,
+                tag("moller_trumbore"),
+            ),
+            value(IntersectionAlgorithm::Watertight, tag("watertight")),
+        )),
+    )(input)
+}
@@ -0,0 +1,287 @@
+use super::description::{load_scene_description, parse_scene_description};
+use super::light::LightKind;
+
+use crate::{
+    error::ParseErrorKind,
+    material::Material,
+    primitives::IntersectionAlgorithm,
+    resolver::{FileSystemResolver, MemoryResolver},
+};
+
+use glam::{Mat4, Vec3, Vec3A};
+
+/// Resolver with a triangle obj file using the material `Red` of its mtl
+/// file.
+fn triangle_resolver() -> MemoryResolver {
+    MemoryResolver::new()
+        .with_file("models/red.mtl", "newmtl Red\nKd 1 0 0\n")
+        .with_file(
+            "models/triangle.obj",
+            "mtllib red.mtl\no Triangle\nv 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\n\
+             usemtl Red\nf 1//1 2//1 3//1\n",
+        )
+}
+
+#[test]
+fn scene_description_test() {
+    let input = "# Test scene
+camera
+    position 0 1 5
+    target 0 1 0   # Looking along -z
+    fov 45
+    resolution 320 240
+
+render
+    samples 16
+    depth 3
+    intersection watertight
+
+material Blue Metal
+    Kd 0 0 1
+    Pm 1
+
+light spot
+    position 0 4 0
+    direction 0 -2 0
+    cone 10 20
+
+include models/triangle.obj
+    scale 2
+    translate 1 0 0
+    usemtl Blue Metal
+    smooth 30
+";
+    let description = parse_scene_description(input, &MemoryResolver::new()).unwrap();
+
+    let camera = description.camera.clone().build();
+    assert_eq!(camera.position(), Vec3A::new(0.0, 1.0, 5.0));
+    assert_eq!(camera.vertical_fov(), 45.0);
+    assert_eq!(camera.get_dimensions(), (320, 240));
+    let settings = &description.settings;
+    assert_eq!(
+        (settings.sample_count, settings.recursion_depth),
+        (Some(16), Some(3))
+    );
+    assert_eq!(
+        settings.intersection_algorithm,
+        Some(IntersectionAlgorithm::Watertight)
+    );
+
+    let material = &description.materials[description.material_map["Blue Metal"]];
+    assert_eq!(material.diffuse_color, Vec3A::Z);
+    assert_eq!(material.physical.unwrap().metallic, 1.0);
+
+    let light = &description.lights[0];
+    assert_eq!(light.direction, Vec3A::NEG_Y);
+    let LightKind::Spot {
+        inner_cone_angle,
+        outer_cone_angle,
+    } = light.kind
+    else {
+        panic!("Expected spot light, found {:?}", light.kind);
+    };
+    assert_eq!(
+        (inner_cone_angle, outer_cone_angle),
+        (10f32.to_radians(), 20f32.to_radians())
+    );
+
+    // Transforms are applied in order, scale first.
+    let include = &description.includes[0];
+    assert_eq!(include.path, "models/triangle.obj");
+    assert_eq!(
+        include.transform.transform_point3a(Vec3A::X),
+        Vec3A::new(3.0, 0.0, 0.0)
+    );
+    assert_eq!(include.material, Some(0));
+    assert_eq!(include.crease_angle, Some(30f32.to_radians()));
+}
+
+#[test]
+fn scene_description_load_test() {
+    let resolver = triangle_resolver()
+        .with_file(
+            "models/copy.obj",
+            "mtllib red.mtl\no Triangle\nv 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\n\
+             usemtl Red\nf 1//1 2//1 3//1\n",
+        )
+        .with_file(
+            "scenes/test.scene",
+            "material Green
+    Kd 0 1 0
+light point
+    position 0 0 1
+include ../models/triangle.obj
+    translate 0 0 -1
+include ../models/copy.obj
+    scale -1 1 1
+    override Red Green
+",
+        );
+    let description = load_scene_description(&resolver, "scenes/test.scene").unwrap();
+    let geometry = description.load(&resolver, None).unwrap();
+    let mesh = &geometry.mesh;
+
+    // The objects of both files are combined into one object, with the
+    // materials of the scene description first.
+    let triangles = &geometry.object_map["Triangle"];
+    assert_eq!(triangles.len(), 2);
+    assert_eq!(mesh.vertex_positions().len(), 6);
+    assert_eq!(geometry.material_map["Green"], 0);
+    assert_eq!(geometry.lights.len(), 1);
+
+    let translated = mesh.get_triangle(&triangles[0]);
+    assert_eq!(*translated.vertex_positions.1, Vec3A::new(1.0, 0.0, -1.0));
+    assert_eq!(
+        mesh.materials()[triangles[0].material_index()].diffuse_color,
+        Vec3A::X
+    );
+
+    // The mirrored triangle has its winding flipped and its material
+    // overridden.
+    let mirrored = mesh.get_triangle(&triangles[1]);
+    let (p1, p2, p3) = mirrored.vertex_positions;
+    assert_eq!(*p3, Vec3A::new(-1.0, 0.0, 0.0));
+    assert!((*p2 - *p1).cross(*p3 - *p1).dot(*mirrored.vertex_normals.0) > 0.0);
+    assert_eq!(triangles[1].material_index(), 0);
+}
+
+#[test]
+fn scene_description_instance_test() {
+    let resolver = triangle_resolver().with_file(
+        "test.scene",
+        "material Green
+    Kd 0 1 0
+include models/triangle.obj
+    translate 0 0 -1
+include models/triangle.obj
+    scale -1 1 1
+    override Red Green
+include models/triangle.obj
+    translate 0 0 1
+",
+    );
+    let description = load_scene_description(&resolver, "test.scene").unwrap();
+    let geometry = description.load(&resolver, None).unwrap();
+    let mesh = &geometry.mesh;
+
+    // The file is loaded once, with a prototype for every combination of
+    // materials, and placed by an instance for every include.
+    assert_eq!(mesh.vertex_positions().len(), 3);
+    assert!(geometry.object_map.is_empty());
+    assert_eq!(geometry.prototypes.len(), 2);
+    let red = "models/triangle.obj";
+    let green = "models/triangle.obj_";
+    let material = |prototype: &str| {
+        let triangle = geometry.prototypes[prototype][0];
+        mesh.materials()[triangle.material_index()].diffuse_color
+    };
+    assert_eq!((material(red), material(green)), (Vec3A::X, Vec3A::Y));
+
+    let instances: Vec<_> = geometry
+        .instances
+        .iter()
+        .map(|i| (i.identifier.as_str(), i.prototype.as_str(), i.transform))
+        .collect();
+    assert_eq!(
+        instances,
+        vec![
+            (
+                "models/triangle.obj.0",
+                red,
+                Mat4::from_translation(Vec3::new(0.0, 0.0, -1.0))
+            ),
+            (
+                "models/triangle.obj.1",
+                green,
+                Mat4::from_scale(Vec3::new(-1.0, 1.0, 1.0))
+            ),
+            (
+                "models/triangle.obj.2",
+                red,
+                Mat4::from_translation(Vec3::Z)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn scene_file_test() {
+    let resolver = FileSystemResolver::new("test");
+    let description = load_scene_description(&resolver, "test_scene.scene").unwrap();
+    assert_eq!(
+        description.camera.clone().build().get_dimensions(),
+        (512, 512)
+    );
+    assert_eq!(description.includes[0].transform, Mat4::IDENTITY);
+    let geometry = description.load(&resolver, None).unwrap();
+    assert!(!geometry.object_map.is_empty());
+}
+
+#[test]
+fn scene_description_error_test() {
+    let resolver = MemoryResolver::new();
+    let error = |input: &str| parse_scene_description(input, &resolver).unwrap_err();
+
+    let e = error("camera\n  position 0 1\n");
+    assert_eq!(
+        (e.kind(), e.line(), e.column()),
+        (ParseErrorKind::Syntax, 2, 15)
+    );
+
+    let e = error("# Properties need a block\nsamples 4\n");
+    assert_eq!((e.line(), e.column()), (2, 1));
+
+    let e = error("render\n  fov 40\n");
+    assert_eq!((e.line(), e.column()), (2, 3));
+
+    let e = error("light point\n  cone 10 20\n");
+    assert_eq!((e.kind(), e.line()), (ParseErrorKind::Syntax, 2));
+
+    let e = error("include a.obj\n  usemtl Missing\n");
+    assert_eq!(
+        (e.kind(), e.line(), e.column()),
+        (ParseErrorKind::UnknownMaterial, 2, 10)
+    );
+
+    let e = error("mtllib missing.mtl\n");
+    assert_eq!((e.kind(), e.file()), (ParseErrorKind::Io, "missing.mtl"));
+
+    // Overridden materials are checked when the file is loaded, the error is
+    // reported at the override in the scene file.
+    let resolver = triangle_resolver().with_file(
+        "scenes/test.scene",
+        "material Green\ninclude ../models/triangle.obj\n  override Blue Green\n",
+    );
+    let description = load_scene_description(&resolver, "scenes/test.scene").unwrap();
+    let e = description.load(&resolver, None).unwrap_err();
+    assert_eq!(
+        (e.kind(), e.file(), e.line(), e.column()),
+        (ParseErrorKind::UnknownMaterial, "scenes/test.scene", 3, 12)
+    );
+}
+
+#[test]
+fn scene_description_stl_material_test() {
+    let stl = "solid part\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\n\
+               vertex 0 1 0\nendloop\nendfacet\nendsolid part\n";
+    let resolver = MemoryResolver::new().with_file("part.stl", stl);
+    let input = "material Green\nKd 0 1 0\ninclude part.stl\ninclude part.stl\n  usemtl Green\n";
+    let description = parse_scene_description(input, &resolver).unwrap();
+    let blue = Material {
+        diffuse_color: Vec3A::Z,
+        ..Material::default()
+    };
+    let geometry = description.load(&resolver, Some(&blue)).unwrap();
+
+    // The stl material is used, unless the include sets a material.
+    let mesh = &geometry.mesh;
+    let colors: Vec<Vec3A> = geometry
+        .instances
+        .iter()
+        .map(|instance| {
+            let triangle = geometry.prototypes[&instance.prototype][0];
+            mesh.materials()[triangle.material_index()].diffuse_color
+        })
+        .collect();
+    assert_eq!(colors, [Vec3A::Z, Vec3A::Y]);
+}
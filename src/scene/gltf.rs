@@ -0,0 +1,683 @@
+use std::{collections::HashMap, sync::Arc};
+
+use ::gltf::{
+    buffer, camera::Projection, image, khr_lights_punctual::Kind, material::AlphaMode, mesh::Mode,
+    texture, Gltf, Node,
+};
+use base64::Engine;
+use glam::{Mat3A, Mat4, Vec2, Vec3A};
+
+use crate::{
+    error::{ParseError, ParseErrorKind},
+    material::{Material, PhysicalProperties, Texture, TextureMap},
+    primitives::{Normal, Position, TexCoord, TriangleIndex, TriangleMesh},
+    resolver::{self, AssetResolver},
+    scene::{
+        camera::CameraBuilder,
+        instance::InstanceDescription,
+        light::{Light, LightKind},
+        parser::ObjectMap,
+    },
+};
+
+/// Contents of a glTF file, see `load_gltf`.
+#[derive(Debug)]
+pub struct GltfScene {
+    /// Triangles of all meshes in the scene, in world space.
+    pub mesh: TriangleMesh,
+    /// Map from material name to the index of the material in the mesh.
+    pub material_map: HashMap<String, usize>,
+    /// Map from object name to the triangles of the object. Every node with
+    /// a mesh that no other node uses is an object.
+    pub object_map: ObjectMap,
+    /// Map from mesh name to the triangles of meshes used by more than one
+    /// node, in the space of the mesh. They are only rendered through
+    /// `instances`.
+    pub prototypes: ObjectMap,
+    /// Instances of the prototypes, one for every node with a mesh used by
+    /// more than one node.
+    pub instances: Vec<InstanceDescription>,
+    /// Perspective cameras of the scene. The resolution is left to the
+    /// caller.
+    pub cameras: Vec<CameraBuilder>,
+    /// Punctual lights of the scene (`KHR_lights_punctual`).
+    pub lights: Vec<Light>,
+}
+
+/// Load a glTF file, either JSON (`.gltf`) or binary (`.glb`), and the
+/// buffers and images it refers to from `resolver`. External files are
+/// resolved relative to the directory of the glTF file.
+///
+/// The default scene of the file is loaded, or the first scene if there is
+/// no default. Meshes are transformed into world space by the transforms of
+/// their nodes. A mesh used by multiple nodes is loaded once in the space of
+/// the mesh, and every node places an instance of it.
+/// Triangle lists, strips and fans are loaded, points and lines are
+/// skipped. Primitives without normals get flat normals.
+///
+/// Materials use the metallic-roughness model. The base color, emissive and
+/// normal textures are loaded if they use the first set of texture
+/// coordinates, the metallic-roughness texture is not supported and only its
+/// factors are used.
+///
+/// Cameras and lights get the position and direction of their nodes.
+/// Orthographic cameras are skipped, as they are not supported by `Camera`.
+///
+/// ### Arguments
+/// - `resolver` - The source of the glTF file and the files it refers to.
+/// - `path` - The path of the glTF file in the resolver.
+///
+/// ### Returns
+/// The contents of the file, or an error if the file or one of its buffers
+/// or images can't be read or parsed.
+pub fn load_gltf(resolver: &dyn AssetResolver, path: &str) -> Result<GltfScene, ParseError> {
+    let location = resolver.location(path);
+    let data = resolver
+        .read(path)
+        .map_err(|e| ParseError::io(&location, e))?;
+    let directory = resolver::parent_directory(path);
+    read_gltf(&data, resolver, directory).map_err(|e| e.in_file(&location))
+}
+
+/// Parse the contents of a glTF file that is already in memory. External
+/// files are loaded from `resolver`, relative to its root. See `load_gltf`.
+pub fn parse_gltf(data: &[u8], resolver: &dyn AssetResolver) -> Result<GltfScene, ParseError> {
+    read_gltf(data, resolver, "")
+}
+
+/// Parse a glTF file with external files relative to `directory` in the
+/// resolver.
+fn read_gltf(
+    data: &[u8],
+    resolver: &dyn AssetResolver,
+    directory: &str,
+) -> Result<GltfScene, ParseError> {
+    let Gltf { document, blob } = Gltf::from_slice(data).map_err(gltf_error)?;
+    let mut blob = blob;
+
+    let mut buffers = Vec::new();
+    for buffer in document.buffers() {
+        let data = match buffer.source() {
+            buffer::Source::Bin => blob.take().ok_or_else(|| {
+                ParseError::new(
+                    ParseErrorKind::Syntax,
+                    0,
+                    0,
+                    "Buffer refers to a missing binary chunk",
+                )
+            })?,
+            buffer::Source::Uri(uri) => load_uri(resolver, directory, uri)?,
+        };
+        if data.len() < buffer.length() {
+            return Err(ParseError::new(
+                ParseErrorKind::Syntax,
+                0,
+                0,
+                format!(
+                    "Buffer {} is {} bytes long, expected at least {} bytes",
+                    buffer.index(),
+                    data.len(),
+                    buffer.length()
+                ),
+            ));
+        }
+        buffers.push(data);
+    }
+
+    let mut loader = GltfLoader {
+        resolver,
+        directory,
+        buffers,
+        textures: vec![None; document.images().len()],
+        positions: Vec::new(),
+        normals: Vec::new(),
+        tex_coords: Vec::new(),
+        materials: Vec::new(),
+        default_material: None,
+        objects: Vec::new(),
+        shared_meshes: shared_meshes(&document),
+        prototypes: vec![None; document.meshes().len()],
+        prototype_objects: Vec::new(),
+        instances: Vec::new(),
+        cameras: Vec::new(),
+        lights: Vec::new(),
+        visited: vec![false; document.nodes().len()],
+    };
+    let mut material_map = HashMap::new();
+    for material in document.materials() {
+        if let Some(name) = material.name() {
+            material_map.insert(name.to_owned(), loader.materials.len());
+        }
+        let material = loader.material(&material)?;
+        loader.materials.push(material);
+    }
+    if let Some(scene) = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        for node in scene.nodes() {
+            loader.add_node(&node, Mat4::IDENTITY)?;
+        }
+    }
+    Ok(loader.finish(material_map))
+}
+
+/// State of a glTF file while its nodes are loaded.
+struct GltfLoader<'a> {
+    resolver: &'a dyn AssetResolver,
+    /// Directory of the glTF file in the resolver.
+    directory: &'a str,
+    buffers: Vec<Vec<u8>>,
+    /// Textures by image index, loaded when a material first uses them.
+    textures: Vec<Option<Arc<Texture>>>,
+    positions: Vec<Position>,
+    normals: Vec<Normal>,
+    tex_coords: Vec<TexCoord>,
+    materials: Vec<Material>,
+    /// Index of the material of primitives without a material, if any.
+    default_material: Option<usize>,
+    objects: Vec<(String, Vec<TriangleIndex>)>,
+    /// Whether each mesh is used by more than one node.
+    shared_meshes: Vec<bool>,
+    /// Name of the prototype of every shared mesh, once it is loaded.
+    prototypes: Vec<Option<String>>,
+    prototype_objects: Vec<(String, Vec<TriangleIndex>)>,
+    instances: Vec<InstanceDescription>,
+    cameras: Vec<CameraBuilder>,
+    lights: Vec<Light>,
+    /// Nodes that are already loaded, to detect nodes with multiple parents.
+    visited: Vec<bool>,
+}
+
+impl<'a> GltfLoader<'a> {
+    /// Load a node and its children.
+    ///
+    /// ### Arguments
+    /// - `node` - The node to load.
+    /// - `parent` - The transform from the space of the parent node to world
+    ///   space.
+    fn add_node(&mut self, node: &Node, parent: Mat4) -> Result<(), ParseError> {
+        if std::mem::replace(&mut self.visited[node.index()], true) {
+            return Err(ParseError::new(
+                ParseErrorKind::Syntax,
+                0,
+                0,
+                format!("Node {} has more than one parent", node.index()),
+            ));
+        }
+        let transform = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+        let position = transform.transform_point3a(Vec3A::ZERO);
+        // Cameras and lights look along the -z axis of their node.
+        let direction = transform
+            .transform_vector3a(Vec3A::NEG_Z)
+            .normalize_or_zero();
+
+        if let Some(mesh) = node.mesh() {
+            let name = node
+                .name()
+                .or_else(|| mesh.name())
+                .map_or_else(|| format!("node{}", node.index()), str::to_owned);
+            let taken = self.objects.iter().any(|(n, _)| *n == name)
+                || self.instances.iter().any(|i| i.identifier == name);
+            let name = if taken {
+                format!("{}.{}", name, node.index())
+            } else {
+                name
+            };
+            if self.shared_meshes[mesh.index()] {
+                let prototype = self.prototype(&mesh)?;
+                self.instances.push(InstanceDescription {
+                    identifier: name,
+                    prototype,
+                    transform,
+                });
+            } else {
+                let mut triangles = Vec::new();
+                for primitive in mesh.primitives() {
+                    self.add_primitive(&primitive, transform, &mut triangles)?;
+                }
+                self.objects.push((name, triangles));
+            }
+        }
+        if let Some(camera) = node.camera() {
+            if let Projection::Perspective(perspective) = camera.projection() {
+                let mut builder = CameraBuilder::new()
+                    .with_position(position)
+                    .with_target(position + direction)
+                    .with_vertical_fov(perspective.yfov().to_degrees())
+                    .with_z_near(perspective.znear());
+                if let Some(z_far) = perspective.zfar() {
+                    builder = builder.with_z_far(z_far);
+                }
+                self.cameras.push(builder);
+            }
+        }
+        if let Some(light) = node.light() {
+            let kind = match light.kind() {
+                Kind::Directional => LightKind::Directional,
+                Kind::Point => LightKind::Point,
+                Kind::Spot {
+                    inner_cone_angle,
+                    outer_cone_angle,
+                } => LightKind::Spot {
+                    inner_cone_angle,
+                    outer_cone_angle,
+                },
+            };
+            self.lights.push(Light {
+                kind,
+                position,
+                direction,
+                color: Vec3A::from(light.color()),
+                intensity: light.intensity(),
+                range: light.range(),
+            });
+        }
+
+        for child in node.children() {
+            self.add_node(&child, transform)?;
+        }
+        Ok(())
+    }
+
+    /// Get the name of the prototype of a shared mesh, loading the mesh in
+    /// its own space on first use.
+    fn prototype(&mut self, mesh: &::gltf::Mesh) -> Result<String, ParseError> {
+        if let Some(name) = &self.prototypes[mesh.index()] {
+            return Ok(name.clone());
+        }
+        let name = mesh
+            .name()
+            .map_or_else(|| format!("mesh{}", mesh.index()), str::to_owned);
+        let name = if self.prototype_objects.iter().any(|(n, _)| *n == name) {
+            format!("{}.{}", name, mesh.index())
+        } else {
+            name
+        };
+        let mut triangles = Vec::new();
+        for primitive in mesh.primitives() {
+            self.add_primitive(&primitive, Mat4::IDENTITY, &mut triangles)?;
+        }
+        self.prototype_objects.push((name.clone(), triangles));
+        self.prototypes[mesh.index()] = Some(name.clone());
+        Ok(name)
+    }
+
+    /// Add the triangles of a primitive, transformed into world space.
+    fn add_primitive(
+        &mut self,
+        primitive: &::gltf::Primitive,
+        transform: Mat4,
+        triangles: &mut Vec<TriangleIndex>,
+    ) -> Result<(), ParseError> {
+        let material_index = match primitive.material().index() {
+            Some(index) => index,
+            None => match self.default_material {
+                Some(index) => index,
+                None => {
+                    let material = self.material(&primitive.material())?;
+                    self.materials.push(material);
+                    *self.default_material.insert(self.materials.len() - 1)
+                }
+            },
+        };
+
+        let buffers = &self.buffers;
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+        let Some(positions) = reader.read_positions() else {
+            return Err(ParseError::new(
+                ParseErrorKind::Unsupported,
+                0,
+                0,
+                format!("Primitive {} has no positions", primitive.index()),
+            ));
+        };
+        let positions: Vec<Position> = positions
+            .map(|p| transform.transform_point3a(Vec3A::from(p)))
+            .collect();
+        let vertex_count = positions.len();
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+            None => (0..vertex_count).collect(),
+        };
+        if let Some(index) = indices.iter().find(|&&i| i >= vertex_count) {
+            return Err(ParseError::new(
+                ParseErrorKind::InvalidIndex,
+                0,
+                0,
+                format!(
+                    "Index {} of primitive {} is out of range, there are {} vertices",
+                    index,
+                    primitive.index(),
+                    vertex_count
+                ),
+            ));
+        }
+        let Some(faces) = triangulate(primitive.mode(), &indices) else {
+            // Points and lines have no surface to render.
+            return Ok(());
+        };
+
+        // Transforms that mirror the mesh flip the winding of the triangles.
+        let mirrored = transform.determinant() < 0.0;
+        let vertex_offset = self.positions.len();
+        let normal_offset = self.normals.len();
+        let tex_coord_offset = self.tex_coords.len();
+        let has_normals = match reader.read_normals() {
+            Some(normals) => {
+                let normal_matrix = Mat3A::from_mat4(transform).inverse().transpose();
+                self.normals.extend(
+                    normals
+                        .take(vertex_count)
+                        .map(|n| (normal_matrix * Vec3A::from(n)).normalize_or_zero()),
+                );
+                self.normals.len() - normal_offset == vertex_count
+            }
+            None => false,
+        };
+        // glTF texture coordinates start at the top left of the image, while
+        // textures are sampled from the bottom left.
+        let has_tex_coords = match reader.read_tex_coords(0) {
+            Some(tex_coords) => {
+                self.tex_coords.extend(
+                    tex_coords
+                        .into_f32()
+                        .take(vertex_count)
+                        .map(|[u, v]| Vec2::new(u, 1.0 - v)),
+                );
+                self.tex_coords.len() - tex_coord_offset == vertex_count
+            }
+            None => false,
+        };
+        if !has_normals {
+            self.normals.truncate(normal_offset);
+        }
+        if !has_tex_coords {
+            self.tex_coords.truncate(tex_coord_offset);
+        }
+
+        for [a, b, c] in faces {
+            let (a, b, c) = if mirrored { (a, c, b) } else { (a, b, c) };
+            let vertex_indices = (a + vertex_offset, b + vertex_offset, c + vertex_offset);
+            let mut triangle = if has_normals {
+                TriangleIndex::with_normals(
+                    vertex_indices,
+                    (a + normal_offset, b + normal_offset, c + normal_offset),
+                    material_index,
+                )
+            } else {
+                let (p1, p2, p3) = (positions[a], positions[b], positions[c]);
+                self.normals
+                    .push((p2 - p1).cross(p3 - p1).normalize_or_zero());
+                TriangleIndex::new(vertex_indices, self.normals.len() - 1, material_index)
+            };
+            if has_tex_coords {
+                triangle.set_tex_coord_indices(Some((
+                    a + tex_coord_offset,
+                    b + tex_coord_offset,
+                    c + tex_coord_offset,
+                )));
+            }
+            triangles.push(triangle);
+        }
+        self.positions.extend(positions);
+        Ok(())
+    }
+
+    /// Convert a metallic-roughness material.
+    fn material(&mut self, material: &::gltf::Material) -> Result<Material, ParseError> {
+        let pbr = material.pbr_metallic_roughness();
+        let [r, g, b, a] = pbr.base_color_factor();
+        let diffuse_map = match pbr.base_color_texture() {
+            Some(info) => self.texture_map(&info.texture(), info.tex_coord())?,
+            None => None,
+        };
+        let emissive_map = match material.emissive_texture() {
+            Some(info) => self.texture_map(&info.texture(), info.tex_coord())?,
+            None => None,
+        };
+        let normal_map = match material.normal_texture() {
+            Some(normal) => self.texture_map(&normal.texture(), normal.tex_coord())?,
+            None => None,
+        };
+        Ok(Material {
+            diffuse_color: Vec3A::new(r, g, b),
+            emissive_color: Vec3A::from(material.emissive_factor()),
+            transparency: if material.alpha_mode() == AlphaMode::Blend {
+                a
+            } else {
+                1.0
+            },
+            index_of_refraction: 1.5,
+            illumination_model: 2,
+            diffuse_map,
+            emissive_map,
+            normal_map,
+            physical: Some(PhysicalProperties {
+                roughness: pbr.roughness_factor(),
+                metallic: pbr.metallic_factor(),
+                ..PhysicalProperties::default()
+            }),
+            ..Material::default()
+        })
+    }
+
+    /// Create a texture map of a texture, loading its image if it is not
+    /// loaded yet.
+    ///
+    /// ### Returns
+    /// `None` if the texture uses another set of texture coordinates than
+    /// the first.
+    fn texture_map(
+        &mut self,
+        texture: &texture::Texture,
+        tex_coord: u32,
+    ) -> Result<Option<TextureMap>, ParseError> {
+        if tex_coord != 0 {
+            return Ok(None);
+        }
+        let image = texture.source();
+        let path = match image.source() {
+            image::Source::Uri { uri, .. } if !uri.starts_with("data:") => uri,
+            _ => image.name().unwrap_or_default(),
+        };
+        let mut map = TextureMap::new(path);
+        map.clamp = texture.sampler().wrap_s() == texture::WrappingMode::ClampToEdge;
+        map.texture = Some(self.texture(&image)?);
+        Ok(Some(map))
+    }
+
+    /// Get the texture of an image, loading it on first use.
+    fn texture(&mut self, image: &image::Image) -> Result<Arc<Texture>, ParseError> {
+        if let Some(texture) = &self.textures[image.index()] {
+            return Ok(texture.clone());
+        }
+        let (data, location) = match image.source() {
+            image::Source::View { view, .. } => {
+                let data = self.buffers[view.buffer().index()]
+                    .get(view.offset()..view.offset() + view.length())
+                    .ok_or_else(|| {
+                        ParseError::new(
+                            ParseErrorKind::InvalidIndex,
+                            0,
+                            0,
+                            format!("Buffer view {} is out of range", view.index()),
+                        )
+                    })?;
+                (data.to_vec(), String::new())
+            }
+            image::Source::Uri { uri, .. } => {
+                let location = if uri.starts_with("data:") {
+                    String::new()
+                } else {
+                    self.resolver
+                        .location(&resolver::join_path(self.directory, &percent_decode(uri)))
+                };
+                (load_uri(self.resolver, self.directory, uri)?, location)
+            }
+        };
+        let texture = Texture::from_memory(&data).map_err(|e| {
+            ParseError::new(
+                ParseErrorKind::InvalidImage,
+                0,
+                0,
+                format!("Image {}: {}", image.index(), e),
+            )
+            .in_file(&location)
+        })?;
+        let texture = Arc::new(texture);
+        self.textures[image.index()] = Some(texture.clone());
+        Ok(texture)
+    }
+
+    /// Create the triangle mesh and object map.
+    fn finish(self, material_map: HashMap<String, usize>) -> GltfScene {
+        GltfScene {
+            mesh: TriangleMesh::with_tex_coords(
+                self.positions,
+                self.normals,
+                self.tex_coords,
+                self.materials,
+            ),
+            material_map,
+            object_map: self.objects.into_iter().collect(),
+            prototypes: self.prototype_objects.into_iter().collect(),
+            instances: self.instances,
+            cameras: self.cameras,
+            lights: self.lights,
+        }
+    }
+}
+
+/// Find the meshes that are used by more than one node of the file.
+fn shared_meshes(document: &::gltf::Document) -> Vec<bool> {
+    let mut node_counts = vec![0; document.meshes().len()];
+    for mesh in document.nodes().filter_map(|node| node.mesh()) {
+        node_counts[mesh.index()] += 1;
+    }
+    node_counts.into_iter().map(|count| count > 1).collect()
+}
+
+/// Split the vertices of a primitive into triangles.
+///
+/// ### Returns
+/// The vertex indices of the triangles, or `None` if the primitive is not
+/// made of triangles.
+fn triangulate(mode: Mode, indices: &[usize]) -> Option<Vec<[usize; 3]>> {
+    let triangles = match mode {
+        Mode::Triangles => indices
+            .chunks_exact(3)
+            .map(|t| [t[0], t[1], t[2]])
+            .collect(),
+        // Every other triangle of a strip is reversed to keep the winding.
+        Mode::TriangleStrip => indices
+            .windows(3)
+            .enumerate()
+            .map(|(i, t)| {
+                if i % 2 == 0 {
+                    [t[0], t[1], t[2]]
+                } else {
+                    [t[1], t[0], t[2]]
+                }
+            })
+            .collect(),
+        Mode::TriangleFan => indices
+            .windows(2)
+            .skip(1)
+            .map(|t| [indices[0], t[0], t[1]])
+            .collect(),
+        _ => return None,
+    };
+    Some(triangles)
+}
+
+/// Load the data of a buffer or image URI, either a base64 `data:` URI or
+/// the path of a file relative to `directory` in the resolver.
+fn load_uri(
+    resolver: &dyn AssetResolver,
+    directory: &str,
+    uri: &str,
+) -> Result<Vec<u8>, ParseError> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (header, data) = data.split_once(',').unwrap_or((data, ""));
+        if !header.ends_with(";base64") {
+            return Err(ParseError::new(
+                ParseErrorKind::Unsupported,
+                0,
+                0,
+                "Only base64 encoded data URIs are supported",
+            ));
+        }
+        return base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| {
+                ParseError::new(
+                    ParseErrorKind::Syntax,
+                    0,
+                    0,
+                    format!("Invalid base64 data URI: {}", e),
+                )
+            });
+    }
+    let path = resolver::join_path(directory, &percent_decode(uri));
+    resolver
+        .read(&path)
+        .map_err(|e| ParseError::io(&resolver.location(&path), e))
+}
+
+/// Decode the percent-encoded characters of a URI, for example `%20` for a
+/// space.
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Convert an error of the glTF parser. JSON errors keep their position in
+/// the file.
+fn gltf_error(error: ::gltf::Error) -> ParseError {
+    match error {
+        ::gltf::Error::Deserialize(error) => {
+            // The position is part of the error itself, so it is removed
+            // from the description.
+            let description = error.to_string();
+            let description = match description.rfind(" at line ") {
+                Some(end) => description[..end].to_string(),
+                None => description,
+            };
+            ParseError::new(
+                ParseErrorKind::Syntax,
+                error.line(),
+                error.column(),
+                description,
+            )
+        }
+        ::gltf::Error::Validation(errors) => {
+            let description = errors
+                .iter()
+                .map(|(path, error)| format!("{}: {}", path, error))
+                .collect::<Vec<_>>()
+                .join(", ");
+            ParseError::new(ParseErrorKind::Syntax, 0, 0, description)
+        }
+        ::gltf::Error::Io(error) => ParseError::new(ParseErrorKind::Io, 0, 0, error.to_string()),
+        error => ParseError::new(ParseErrorKind::Syntax, 0, 0, error.to_string()),
+    }
+}
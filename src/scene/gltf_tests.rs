@@ -0,0 +1,267 @@
+use super::camera::CameraBuilder;
+use super::description::parse_scene_description;
+use super::gltf::{load_gltf, parse_gltf};
+use super::light::LightKind;
+
+use crate::{error::ParseErrorKind, resolver::MemoryResolver};
+
+use base64::Engine;
+use glam::{Mat4, Vec2, Vec3, Vec3A};
+
+/// Buffer with a single triangle: positions, normals, texture coordinates
+/// and 16 bit indices.
+fn triangle_buffer() -> Vec<u8> {
+    let floats: [f32; 24] = [
+        0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, // positions
+        0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, // normals
+        0.0, 0.0, 1.0, 0.0, 0.0, 1.0, // texture coordinates
+    ];
+    let mut buffer: Vec<u8> = floats.iter().flat_map(|f| f.to_le_bytes()).collect();
+    buffer.extend([0u16, 1, 2].iter().flat_map(|i| i.to_le_bytes()));
+    buffer
+}
+
+/// glTF file with a triangle mesh used by a node and by its mirrored child
+/// node, a second triangle mesh used by a single mirrored node, a metallic
+/// material, a camera and a point light. `uri` is the uri of the buffer,
+/// `None` for the binary chunk of a glb file.
+fn triangle_gltf(uri: Option<&str>) -> String {
+    let uri = uri.map_or(String::new(), |uri| format!(r#", "uri": "{}""#, uri));
+    format!(
+        r#"{{
+  "asset": {{ "version": "2.0" }},
+  "extensionsUsed": ["KHR_lights_punctual"],
+  "extensions": {{
+    "KHR_lights_punctual": {{
+      "lights": [{{ "type": "point", "color": [1, 0.5, 0.25], "intensity": 10, "range": 5 }}]
+    }}
+  }},
+  "scene": 0,
+  "scenes": [{{ "nodes": [0, 2, 3, 4] }}],
+  "nodes": [
+    {{ "name": "Parent", "translation": [1, 2, 3], "children": [1], "mesh": 0 }},
+    {{ "mesh": 0, "scale": [-1, 1, 1] }},
+    {{ "camera": 0, "translation": [0, 0, 5] }},
+    {{ "translation": [0, 3, 0], "extensions": {{ "KHR_lights_punctual": {{ "light": 0 }} }} }},
+    {{ "name": "Mirrored", "mesh": 1, "translation": [1, 2, 3], "scale": [-1, 1, 1] }}
+  ],
+  "meshes": [
+    {{
+      "name": "Triangle",
+      "primitives": [{{
+        "attributes": {{ "POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2 }},
+        "indices": 3,
+        "material": 0
+      }}]
+    }},
+    {{
+      "name": "Single",
+      "primitives": [{{ "attributes": {{ "POSITION": 0, "NORMAL": 1 }}, "indices": 3 }}]
+    }}
+  ],
+  "materials": [{{
+    "name": "Gold",
+    "pbrMetallicRoughness": {{
+      "baseColorFactor": [1, 0.8, 0.2, 1], "metallicFactor": 1, "roughnessFactor": 0.3
+    }}
+  }}],
+  "cameras": [{{ "type": "perspective", "perspective": {{ "yfov": 0.8, "znear": 0.1, "zfar": 50 }} }}],
+  "buffers": [{{ "byteLength": 102{} }}],
+  "bufferViews": [
+    {{ "buffer": 0, "byteOffset": 0, "byteLength": 36 }},
+    {{ "buffer": 0, "byteOffset": 36, "byteLength": 36 }},
+    {{ "buffer": 0, "byteOffset": 72, "byteLength": 24 }},
+    {{ "buffer": 0, "byteOffset": 96, "byteLength": 6 }}
+  ],
+  "accessors": [
+    {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0] }},
+    {{ "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3" }},
+    {{ "bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC2" }},
+    {{ "bufferView": 3, "componentType": 5123, "count": 3, "type": "SCALAR" }}
+  ]
+}}"#,
+        uri
+    )
+}
+
+/// Pack a glTF file and its binary buffer into a glb file.
+fn glb(json: &str, buffer: &[u8]) -> Vec<u8> {
+    let mut json = json.as_bytes().to_vec();
+    json.resize(json.len().next_multiple_of(4), b' ');
+    let mut buffer = buffer.to_vec();
+    buffer.resize(buffer.len().next_multiple_of(4), 0);
+
+    let mut glb = Vec::new();
+    glb.extend(b"glTF");
+    glb.extend(2u32.to_le_bytes());
+    glb.extend(((12 + 8 + json.len() + 8 + buffer.len()) as u32).to_le_bytes());
+    glb.extend((json.len() as u32).to_le_bytes());
+    glb.extend(b"JSON");
+    glb.extend(json);
+    glb.extend((buffer.len() as u32).to_le_bytes());
+    glb.extend(b"BIN\0");
+    glb.extend(buffer);
+    glb
+}
+
+#[test]
+fn gltf_test() {
+    let uri = format!(
+        "data:application/octet-stream;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(triangle_buffer())
+    );
+    let gltf = parse_gltf(triangle_gltf(Some(&uri)).as_bytes(), &MemoryResolver::new()).unwrap();
+    let mesh = &gltf.mesh;
+
+    // The mesh shared by the parent and its child is loaded once, and
+    // placed by an instance for every node.
+    assert_eq!(gltf.prototypes.len(), 1);
+    let prototype = gltf.prototypes["Triangle"][0];
+    let positions = mesh.vertex_positions();
+    let (v1, v2, v3) = prototype.vertex_indices();
+    assert_eq!(
+        (positions[v1], positions[v2], positions[v3]),
+        (Vec3A::ZERO, Vec3A::X, Vec3A::Y)
+    );
+    assert_eq!(
+        mesh.tex_coords()[prototype.tex_coord_indices().unwrap().2],
+        Vec2::ZERO
+    );
+    let parent = Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0));
+    let instances: Vec<_> = gltf
+        .instances
+        .iter()
+        .map(|i| (i.identifier.as_str(), i.prototype.as_str(), i.transform))
+        .collect();
+    assert_eq!(
+        instances,
+        vec![
+            ("Parent", "Triangle", parent),
+            (
+                "Triangle",
+                "Triangle",
+                parent * Mat4::from_scale(Vec3::new(-1.0, 1.0, 1.0))
+            )
+        ]
+    );
+
+    // Other meshes are transformed into world space. The mirrored node has
+    // its winding flipped, so the face still points along its normals.
+    assert_eq!(gltf.object_map.len(), 1);
+    let mirrored = mesh.get_triangle(&gltf.object_map["Mirrored"][0]);
+    let (p1, p2, p3) = mirrored.vertex_positions;
+    assert_eq!(*p2, Vec3A::new(1.0, 3.0, 3.0));
+    assert!((*p2 - *p1).cross(*p3 - *p1).dot(*mirrored.vertex_normals.0) > 0.0);
+
+    assert_eq!(gltf.material_map["Gold"], prototype.material_index());
+    let material = &mesh.materials()[prototype.material_index()];
+    assert_eq!(material.diffuse_color, Vec3A::new(1.0, 0.8, 0.2));
+    let physical = material.physical.unwrap();
+    assert_eq!((physical.metallic, physical.roughness), (1.0, 0.3));
+
+    let camera = gltf.cameras[0].clone().build();
+    assert_eq!(camera.position(), Vec3A::new(0.0, 0.0, 5.0));
+    assert_eq!(camera.target(), Vec3A::new(0.0, 0.0, 4.0));
+    assert!((camera.vertical_fov() - 0.8f32.to_degrees()).abs() < 1e-4);
+    assert_eq!(camera.clipping_planes(), (0.1, 50.0));
+
+    // The camera of the file is used if no position or target is set.
+    let camera = CameraBuilder::new()
+        .with_width(64)
+        .or_camera(&gltf.cameras[0])
+        .build();
+    assert_eq!(camera.position(), Vec3A::new(0.0, 0.0, 5.0));
+    assert_eq!(camera.get_dimensions(), (64, 600));
+    let camera = CameraBuilder::new()
+        .with_target(Vec3A::X)
+        .or_camera(&gltf.cameras[0])
+        .build();
+    assert_eq!(camera.position(), Vec3A::ZERO);
+
+    let light = &gltf.lights[0];
+    assert_eq!(light.kind, LightKind::Point);
+    assert_eq!(light.position, Vec3A::new(0.0, 3.0, 0.0));
+    assert_eq!(light.color, Vec3A::new(1.0, 0.5, 0.25));
+    assert_eq!((light.intensity, light.range), (10.0, Some(5.0)));
+}
+
+#[test]
+fn gltf_external_buffer_test() {
+    let resolver = MemoryResolver::new()
+        .with_file(
+            "models/triangle.gltf",
+            triangle_gltf(Some("triangle%20data.bin")),
+        )
+        .with_file("models/triangle data.bin", triangle_buffer());
+    let gltf = load_gltf(&resolver, "models/triangle.gltf").unwrap();
+    assert_eq!(gltf.mesh.vertex_positions().len(), 6);
+
+    let resolver =
+        MemoryResolver::new().with_file("triangle.gltf", triangle_gltf(Some("missing.bin")));
+    let error = load_gltf(&resolver, "triangle.gltf").unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::Io);
+    assert_eq!(error.file(), "missing.bin");
+}
+
+#[test]
+fn gltf_include_test() {
+    let resolver = MemoryResolver::new()
+        .with_file("triangle.gltf", triangle_gltf(Some("triangle.bin")))
+        .with_file("triangle.bin", triangle_buffer());
+    let instance_points = |scene: &str| {
+        let description = parse_scene_description(scene, &resolver).unwrap();
+        let geometry = description.load(&resolver, None).unwrap();
+        let positions = geometry.mesh.vertex_positions();
+        geometry
+            .instances
+            .iter()
+            .map(|instance| {
+                let triangle = geometry.prototypes[&instance.prototype][0];
+                let position = positions[triangle.vertex_indices().0];
+                (
+                    instance.identifier.clone(),
+                    instance.transform.transform_point3a(position),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // The instances of the file are placed by the transform of the include,
+    // whether the file is included once or more often.
+    let points = instance_points("include triangle.gltf\n  translate 0 0 -1\n");
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[0], ("Parent".to_string(), Vec3A::new(1.0, 2.0, 2.0)));
+    let points = instance_points(
+        "include triangle.gltf\n  translate 0 0 -1\ninclude triangle.gltf\n  translate 0 0 1\n",
+    );
+    assert_eq!(points.len(), 6);
+    assert!(points.contains(&("Parent".to_string(), Vec3A::new(1.0, 2.0, 2.0))));
+    assert!(points.contains(&("Parent".to_string(), Vec3A::new(1.0, 2.0, 4.0))));
+    assert!(points.contains(&("triangle.gltf.1".to_string(), Vec3A::new(1.0, 2.0, 4.0))));
+}
+
+#[test]
+fn glb_test() {
+    let data = glb(&triangle_gltf(None), &triangle_buffer());
+    let gltf = parse_gltf(&data, &MemoryResolver::new()).unwrap();
+    assert_eq!(gltf.mesh.vertex_positions().len(), 6);
+    assert_eq!(gltf.lights.len(), 1);
+}
+
+#[test]
+fn gltf_error_test() {
+    let resolver = MemoryResolver::new().with_file("broken.gltf", "{\n  \"asset\": {\n    oops");
+    let error = load_gltf(&resolver, "broken.gltf").unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::Syntax);
+    assert_eq!(
+        (error.file(), error.line(), error.column()),
+        ("broken.gltf", 3, 5)
+    );
+
+    // Index 7 of a triangle with 3 vertices.
+    let mut buffer = triangle_buffer();
+    buffer[98] = 7;
+    let data = glb(&triangle_gltf(None), &buffer);
+    let error = parse_gltf(&data, &MemoryResolver::new()).unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::InvalidIndex);
+}
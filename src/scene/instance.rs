@@ -0,0 +1,244 @@
+use crate::error::{ParseError, ParseErrorKind};
+use crate::primitives::{BoundingBox, Bvh, Hit, Ray, TriangleMesh};
+#[cfg(not(feature = "libm"))]
+use crate::primitives::{PacketHits, RayPacket};
+
+use crate::traits::{Bounded, Intersectable};
+
+use super::object::Object;
+
+#[cfg(not(feature = "libm"))]
+use glam::Vec4;
+use glam::{Mat3A, Mat4, Vec3A};
+
+/// An instance of an object, or of several objects like all objects of a
+/// mesh, placed in the scene with a transform. Instances share the triangles
+/// (and the bounding volume hierarchies) of the objects they refer to, so
+/// many copies of the same objects only cost one transform each.
+///
+/// Rays are transformed into the local space of the object using the inverse
+/// transform, and hits are transformed back into world space.
+#[derive(Debug)]
+pub struct Instance<'a> {
+    pub identifier: String,
+    objects: &'a [Object<'a>],
+    /// Hierarchy over the object space bounding boxes of the objects.
+    bvh: Bvh,
+    transform: Mat4,
+    inverse_transform: Mat4,
+    normal_transform: Mat3A,
+    bounding_box: BoundingBox,
+}
+
+impl<'a> Instance<'a> {
+    /// Creates a new instance of the object with the given transform, from
+    /// object (local) space to world space.
+    pub fn new(identifier: String, object: &'a Object<'a>, transform: Mat4) -> Self {
+        Self::with_objects(identifier, std::slice::from_ref(object), transform)
+    }
+
+    /// Creates a new instance of several objects with the given transform,
+    /// for example of all objects of a mesh.
+    ///
+    /// ### Arguments
+    /// - `identifier` - The identifier of the instance.
+    /// - `objects` - The instanced objects.
+    /// - `transform` - The transform from object (local) space to world space.
+    ///
+    /// ### Panics
+    /// If the objects are not all part of the same triangle mesh, as the
+    /// materials of hits are looked up in a single mesh.
+    pub fn with_objects(identifier: String, objects: &'a [Object<'a>], transform: Mat4) -> Self {
+        if let Some((first, rest)) = objects.split_first() {
+            assert!(
+                rest.iter().all(|o| std::ptr::eq(o.mesh(), first.mesh())),
+                "Objects of instance '{}' are not part of the same mesh",
+                identifier
+            );
+        }
+        let bounding_boxes: Vec<BoundingBox> = objects.iter().map(|o| o.bounding_box()).collect();
+        let mut instance = Self {
+            identifier,
+            objects,
+            bvh: Bvh::new(&bounding_boxes),
+            transform: Mat4::IDENTITY,
+            inverse_transform: Mat4::IDENTITY,
+            normal_transform: Mat3A::IDENTITY,
+            bounding_box: BoundingBox::empty(),
+        };
+        instance.set_transform(transform);
+        instance
+    }
+
+    /// Get reference to the instanced objects.
+    #[inline]
+    pub fn objects(&self) -> &'a [Object<'a>] {
+        self.objects
+    }
+
+    /// Get reference to the triangle mesh the instanced objects are part of,
+    /// `None` if the instance has no objects.
+    #[inline]
+    pub fn mesh(&self) -> Option<&'a TriangleMesh> {
+        self.objects.first().map(|o| o.mesh())
+    }
+
+    /// Get the transform from object space to world space.
+    #[inline]
+    pub fn transform(&self) -> Mat4 {
+        self.transform
+    }
+
+    /// Get the transform from world space to object space.
+    #[inline]
+    pub fn inverse_transform(&self) -> Mat4 {
+        self.inverse_transform
+    }
+
+    /// Set the transform from object space to world space. The inverse
+    /// transform and the world space bounding box are updated accordingly.
+    pub fn set_transform(&mut self, transform: Mat4) {
+        self.transform = transform;
+        self.inverse_transform = transform.inverse();
+        self.normal_transform = Mat3A::from_mat4(self.inverse_transform).transpose();
+        self.bounding_box = Self::transform_bounding_box(&self.bvh.bounding_box(), &transform);
+    }
+
+    /// Transform all corners of the bounding box and return the bounding box
+    /// enclosing the transformed corners.
+    fn transform_bounding_box(bounding_box: &BoundingBox, transform: &Mat4) -> BoundingBox {
+        if bounding_box.is_empty() {
+            return BoundingBox::empty();
+        }
+        let (min, max) = (bounding_box.min(), bounding_box.max());
+        (0..8).fold(BoundingBox::empty(), |b, corner| {
+            let point = Vec3A::new(
+                if corner & 1 == 0 { min.x } else { max.x },
+                if corner & 2 == 0 { min.y } else { max.y },
+                if corner & 4 == 0 { min.z } else { max.z },
+            );
+            b.grow(transform.transform_point3a(point))
+        })
+    }
+
+    /// Transform a world space ray into object space. The direction is not
+    /// normalized, so distances along the ray are the same in both spaces.
+    #[inline]
+    fn to_object_space(&self, ray: &Ray) -> Ray {
+        Ray::new(
+            self.inverse_transform.transform_point3a(ray.origin),
+            self.inverse_transform.transform_vector3a(ray.direction),
+        )
+    }
+
+    /// Transform an object space hit into a world space hit.
+    #[inline]
+    fn to_world_space(&self, ray: &Ray, hit: Hit) -> Hit {
+        Hit {
+            hit_point: ray.at(hit.distance),
+            incoming: *ray,
+            normal: (self.normal_transform * hit.normal).normalize(),
+            geometric_normal: (self.normal_transform * hit.geometric_normal).normalize(),
+            tex_coord_derivatives: hit.tex_coord_derivatives.map(|(dp_du, dp_dv)| {
+                (
+                    self.transform.transform_vector3a(dp_du),
+                    self.transform.transform_vector3a(dp_dv),
+                )
+            }),
+            ..hit
+        }
+    }
+}
+
+impl<'a> Bounded for Instance<'a> {
+    #[inline]
+    fn bounding_box(&self) -> BoundingBox {
+        self.bounding_box
+    }
+}
+
+impl<'a> Intersectable for Instance<'a> {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+        let local_ray = self.to_object_space(ray);
+        self.bvh
+            .intersect(&local_ray, t_min, t_max, |i, ray, t_min, t_max| {
+                self.objects[i].intersect(ray, t_min, t_max)
+            })
+            .map(|hit| self.to_world_space(ray, hit))
+    }
+
+    fn intersected(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.bvh.intersected(
+            &self.to_object_space(ray),
+            t_min,
+            t_max,
+            |i, ray, t_min, t_max| self.objects[i].intersected(ray, t_min, t_max),
+        )
+    }
+
+    #[cfg(not(feature = "libm"))]
+    fn intersect_packet(&self, packet: &RayPacket, t_min: f32, t_max: Vec4) -> PacketHits {
+        let local_packet = RayPacket::new(packet.rays().map(|ray| self.to_object_space(&ray)));
+        let mut hits =
+            self.bvh
+                .intersect_packet(&local_packet, t_min, t_max, |i, packet, t_min, t_max| {
+                    self.objects[i].intersect_packet(packet, t_min, t_max)
+                });
+        for (hit, ray) in hits.iter_mut().zip(packet.rays()) {
+            *hit = hit.take().map(|hit| self.to_world_space(ray, hit));
+        }
+        hits
+    }
+}
+
+/// An instance as loaded from a file, before the objects of the scene are
+/// created. See `get_instances`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstanceDescription {
+    /// The identifier of the instance.
+    pub identifier: String,
+    /// The name of the instanced object in the object map of the prototypes.
+    pub prototype: String,
+    /// The transform from the space of the prototype to world space.
+    pub transform: Mat4,
+}
+
+/// Create the instances of instance descriptions.
+///
+/// ### Arguments
+/// - `prototypes` - The objects the instances refer to, usually created with
+///   `parser::get_objects` from the object map of the prototypes.
+/// - `descriptions` - The instances to create.
+///
+/// ### Returns
+/// The instances. An error naming the prototype is returned if a description
+/// refers to an object that is not in `prototypes`.
+pub fn get_instances<'a>(
+    prototypes: &'a [Object<'a>],
+    descriptions: &[InstanceDescription],
+) -> Result<Vec<Instance<'a>>, ParseError> {
+    descriptions
+        .iter()
+        .map(|description| {
+            let object = prototypes
+                .iter()
+                .find(|o| o.identifier == description.prototype)
+                .ok_or_else(|| {
+                    ParseError::new(
+                        ParseErrorKind::InvalidIndex,
+                        0,
+                        0,
+                        format!(
+                            "Instance '{}' refers to unknown prototype '{}'",
+                            description.identifier, description.prototype
+                        ),
+                    )
+                })?;
+            Ok(Instance::new(
+                description.identifier.clone(),
+                object,
+                description.transform,
+            ))
+        })
+        .collect()
+}
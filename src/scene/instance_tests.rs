@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+use super::{
+    instance::{get_instances, InstanceDescription},
+    object::Object,
+    Instance, Scene,
+};
+
+use crate::{
+    error::ParseErrorKind,
+    material::{Material, Texture, TextureMap},
+    primitives::{Ray, TriangleIndex, TriangleMesh},
+    traits::{Bounded, Intersectable},
+};
+
+use glam::{Mat4, Vec2, Vec3, Vec3A};
+use image::{Rgba, RgbaImage};
+
+/// Single triangle in the xy-plane facing +z.
+fn triangle_mesh() -> TriangleMesh {
+    TriangleMesh::new(
+        vec![
+            Vec3A::new(-1.0, -1.0, 0.0),
+            Vec3A::new(1.0, -1.0, 0.0),
+            Vec3A::new(0.0, 1.0, 0.0),
+        ],
+        vec![Vec3A::Z],
+        vec![Material::default()],
+    )
+}
+
+#[test]
+fn translated_instance_test() {
+    let mesh = triangle_mesh();
+    let object = Object::new("T".to_string(), vec![TriangleIndex::new((0, 1, 2), 0, 0)], &mesh);
+    let instance = Instance::new(
+        "T.001".to_string(),
+        &object,
+        Mat4::from_translation(Vec3::new(5.0, 0.0, 0.0)),
+    );
+
+    // The original location is empty.
+    let ray = Ray::new(Vec3A::new(0.0, 0.0, 5.0), Vec3A::NEG_Z);
+    assert!(instance.intersect(&ray, 0.0, f32::INFINITY).is_none());
+
+    let ray = Ray::new(Vec3A::new(5.0, 0.0, 5.0), Vec3A::NEG_Z);
+    let hit = instance.intersect(&ray, 0.0, f32::INFINITY).unwrap();
+    assert_eq!(hit.hit_point, Vec3A::new(5.0, 0.0, 0.0));
+    assert_eq!(hit.distance, 5.0);
+    assert_eq!(hit.normal, Vec3A::Z);
+    assert_eq!(instance.bounding_box().min(), Vec3A::new(4.0, -1.0, 0.0));
+}
+
+#[test]
+fn rotated_and_scaled_instance_test() {
+    let mesh = triangle_mesh();
+    let object = Object::new("T".to_string(), vec![TriangleIndex::new((0, 1, 2), 0, 0)], &mesh);
+    // Rotate so the triangle faces +x, then scale it up.
+    let transform = Mat4::from_scale(Vec3::splat(2.0))
+        * Mat4::from_rotation_y(std::f32::consts::FRAC_PI_2);
+    let instance = Instance::new("T.001".to_string(), &object, transform);
+
+    let ray = Ray::new(Vec3A::new(10.0, 1.5, 0.0), Vec3A::NEG_X);
+    let hit = instance.intersect(&ray, 0.0, f32::INFINITY).unwrap();
+    assert!((hit.distance - 10.0).abs() < 1e-5);
+    assert!((hit.hit_point - Vec3A::new(0.0, 1.5, 0.0)).length() < 1e-5);
+    assert!((hit.normal - Vec3A::X).length() < 1e-5);
+}
+
+#[test]
+fn mesh_instance_test() {
+    // Two objects of the mesh, the second one behind and right of the first.
+    let offset = Vec3A::new(2.0, 0.0, -1.0);
+    let positions = triangle_mesh().vertex_positions().clone();
+    let positions = positions
+        .iter()
+        .copied()
+        .chain(positions.iter().map(|&p| p + offset))
+        .collect();
+    let mesh = TriangleMesh::new(positions, vec![Vec3A::Z], vec![Material::default()]);
+    let objects = vec![
+        Object::new("A".to_string(), vec![TriangleIndex::new((0, 1, 2), 0, 0)], &mesh),
+        Object::new("B".to_string(), vec![TriangleIndex::new((3, 4, 5), 0, 0)], &mesh),
+    ];
+    let transform = Mat4::from_translation(Vec3::new(0.0, 10.0, 0.0));
+    let instance = Instance::with_objects("Mesh.001".to_string(), &objects, transform);
+    assert_eq!(instance.objects().len(), 2);
+    assert_eq!(instance.bounding_box().min(), Vec3A::new(-1.0, 9.0, -1.0));
+    assert_eq!(instance.bounding_box().max(), Vec3A::new(3.0, 11.0, 0.0));
+
+    // Both objects are hit through the instance, the closest one first.
+    let ray = Ray::new(Vec3A::new(0.0, 10.0, 5.0), Vec3A::NEG_Z);
+    assert_eq!(instance.intersect(&ray, 0.0, f32::INFINITY).unwrap().distance, 5.0);
+    let ray = Ray::new(Vec3A::new(2.0, 10.0, 5.0), Vec3A::NEG_Z);
+    assert_eq!(instance.intersect(&ray, 0.0, f32::INFINITY).unwrap().distance, 6.0);
+    assert!(instance.intersected(&ray, 0.0, 6.5));
+    assert!(!instance.intersected(&ray, 0.0, 5.5));
+}
+
+#[test]
+fn instance_normal_map_test() {
+    // A normal map that tilts the normal halfway towards the tangent.
+    let image = RgbaImage::from_pixel(1, 1, Rgba([255, 128, 255, 255]));
+    let material = Material {
+        normal_map: Some(TextureMap {
+            texture: Some(Arc::new(Texture::new(image))),
+            ..TextureMap::new("normal.png")
+        }),
+        ..Material::default()
+    };
+    let tex_coords = vec![Vec2::ZERO, Vec2::X, Vec2::new(0.5, 1.0)];
+    let triangle = triangle_mesh();
+    let mesh = TriangleMesh::with_tex_coords(
+        triangle.vertex_positions().clone(),
+        vec![Vec3A::Z],
+        tex_coords,
+        vec![material],
+    );
+    let mut triangle_index = TriangleIndex::new((0, 1, 2), 0, 0);
+    triangle_index.set_tex_coord_indices(Some((0, 1, 2)));
+    let object = Object::new("T".to_string(), vec![triangle_index], &mesh);
+    let transform = Mat4::from_rotation_y(std::f32::consts::FRAC_PI_2);
+    let instance = Instance::new("T.001".to_string(), &object, transform);
+
+    // Intersections keep the interpolated normal, the map is applied when
+    // the hit is shaded.
+    let ray = Ray::new(Vec3A::new(0.0, 0.0, 5.0), Vec3A::NEG_Z);
+    let hit = object.intersect(&ray, 0.0, f32::INFINITY).unwrap();
+    assert_eq!(hit.normal, Vec3A::Z);
+    let normal = mesh.apply_bump_map(hit).normal;
+    assert!(normal.abs_diff_eq(Vec3A::new(1.0, 0.0, 1.0).normalize(), 1e-2));
+
+    // The map is applied in world space to hits on instances.
+    let ray = Ray::new(Vec3A::new(5.0, 0.0, 0.0), Vec3A::NEG_X);
+    let hit = instance.intersect(&ray, 0.0, f32::INFINITY).unwrap();
+    let world_normal = mesh.apply_bump_map(hit).normal;
+    assert!(world_normal.abs_diff_eq(transform.transform_vector3a(normal), 1e-5));
+}
+
+#[test]
+fn get_instances_test() {
+    let mesh = triangle_mesh();
+    let prototypes = vec![Object::new(
+        "T".to_string(),
+        vec![TriangleIndex::new((0, 1, 2), 0, 0)],
+        &mesh,
+    )];
+    let description = |identifier: &str, prototype: &str| InstanceDescription {
+        identifier: identifier.to_string(),
+        prototype: prototype.to_string(),
+        transform: Mat4::from_translation(Vec3::new(5.0, 0.0, 0.0)),
+    };
+
+    let instances = get_instances(&prototypes, &[description("A", "T")]).unwrap();
+    assert_eq!(instances.len(), 1);
+    assert_eq!(instances[0].identifier, "A");
+    let ray = Ray::new(Vec3A::new(5.0, 0.0, 5.0), Vec3A::NEG_Z);
+    assert!(instances[0].intersected(&ray, 0.0, f32::INFINITY));
+
+    // Instances of missing prototypes are an error.
+    let error = get_instances(&prototypes, &[description("A", "T"), description("B", "U")])
+        .err()
+        .unwrap();
+    assert_eq!(error.kind(), ParseErrorKind::InvalidIndex);
+    assert!(error.description().contains("'U'"));
+}
+
+#[test]
+#[should_panic(expected = "not part of the triangle mesh")]
+fn instance_of_other_mesh_test() {
+    let mesh = triangle_mesh();
+    let other_mesh = triangle_mesh();
+    let object = Object::new(
+        "T".to_string(),
+        vec![TriangleIndex::new((0, 1, 2), 0, 0)],
+        &other_mesh,
+    );
+    let mut scene = Scene::new(&mesh, Vec::new());
+    scene.add_instance(Instance::new("T.001".to_string(), &object, Mat4::IDENTITY));
+}
@@ -0,0 +1,108 @@
+use glam::Vec3A;
+
+/// Kind of a punctual light, see `Light`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    /// Light infinitely far away, shining along the direction of the light.
+    /// The intensity is in lux.
+    Directional,
+    /// Light shining in all directions from the position of the light. The
+    /// intensity is in candela.
+    Point,
+    /// Light shining in a cone along the direction of the light. The light
+    /// falls off between the inner and outer cone angle, in radians. The
+    /// intensity is in candela.
+    Spot {
+        inner_cone_angle: f32,
+        outer_cone_angle: f32,
+    },
+}
+
+/// Infinitely small light source, for example the punctual lights of a glTF
+/// file. The light is not part of the geometry of the scene, so it can't be
+/// hit by rays and only contributes direct light to the surfaces it reaches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    /// Position of point and spot lights.
+    pub position: Vec3A,
+    /// Normalized direction directional and spot lights shine in.
+    pub direction: Vec3A,
+    /// Linear RGB color of the light.
+    pub color: Vec3A,
+    /// Brightness of the light, in the unit of its kind.
+    pub intensity: f32,
+    /// Distance at which the light of point and spot lights reaches zero,
+    /// `None` for inverse square falloff without a cutoff.
+    pub range: Option<f32>,
+}
+
+impl Light {
+    /// Creates a white light of the given kind with intensity 1, at the
+    /// origin and shining along -z.
+    pub fn new(kind: LightKind) -> Self {
+        Self {
+            kind,
+            position: Vec3A::ZERO,
+            direction: Vec3A::NEG_Z,
+            color: Vec3A::ONE,
+            intensity: 1.0,
+            range: None,
+        }
+    }
+
+    /// Get the light arriving at a point from this light.
+    ///
+    /// ### Arguments
+    /// - `point` - The point that is lit.
+    ///
+    /// ### Returns
+    /// The normalized direction from the point towards the light, the
+    /// distance to the light and the light arriving at the point. `None` if
+    /// no light arrives at the point, for example outside of the cone of a
+    /// spot light.
+    pub fn illuminate(&self, point: Vec3A) -> Option<(Vec3A, f32, Vec3A)> {
+        if self.kind == LightKind::Directional {
+            return Some((-self.direction, f32::INFINITY, self.color * self.intensity));
+        }
+
+        let to_light = self.position - point;
+        let distance_squared = to_light.length_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        let direction = to_light / distance;
+
+        // Inverse square falloff, smoothly going to zero at the range of the
+        // light as recommended by the glTF specification.
+        let mut attenuation = 1.0 / distance_squared;
+        if let Some(range) = self.range {
+            attenuation *= (1.0 - (distance / range).powi(4)).clamp(0.0, 1.0).powi(2);
+        }
+        if let LightKind::Spot {
+            inner_cone_angle,
+            outer_cone_angle,
+        } = self.kind
+        {
+            let (cos_inner, cos_outer) = (inner_cone_angle.cos(), outer_cone_angle.cos());
+            let cos_angle = self.direction.dot(-direction);
+            let falloff = if cos_inner > cos_outer {
+                ((cos_angle - cos_outer) / (cos_inner - cos_outer)).clamp(0.0, 1.0)
+            } else if cos_angle >= cos_outer {
+                1.0
+            } else {
+                0.0
+            };
+            attenuation *= falloff * falloff;
+        }
+        if attenuation <= 0.0 {
+            return None;
+        }
+        Some((
+            direction,
+            distance,
+            self.color * self.intensity * attenuation,
+        ))
+    }
+}
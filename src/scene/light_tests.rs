@@ -0,0 +1,50 @@
+use super::light::{Light, LightKind};
+
+use glam::Vec3A;
+
+#[test]
+fn point_light_test() {
+    let light = Light {
+        position: Vec3A::new(0.0, 2.0, 0.0),
+        intensity: 8.0,
+        ..Light::new(LightKind::Point)
+    };
+    let (direction, distance, color) = light.illuminate(Vec3A::ZERO).unwrap();
+    assert_eq!((direction, distance), (Vec3A::Y, 2.0));
+    assert_eq!(color, Vec3A::splat(2.0));
+
+    // No light reaches beyond the range.
+    let light = Light {
+        range: Some(1.5),
+        ..light
+    };
+    assert!(light.illuminate(Vec3A::ZERO).is_none());
+}
+
+#[test]
+fn spot_light_test() {
+    let light = Light {
+        position: Vec3A::new(0.0, 1.0, 0.0),
+        direction: Vec3A::NEG_Y,
+        ..Light::new(LightKind::Spot {
+            inner_cone_angle: 0.2,
+            outer_cone_angle: 0.4,
+        })
+    };
+    assert_eq!(light.illuminate(Vec3A::ZERO).unwrap().2, Vec3A::ONE);
+    // Between the cones the light falls off, outside it is dark.
+    let (_, _, color) = light
+        .illuminate(Vec3A::new(0.3f32.tan(), 0.0, 0.0))
+        .unwrap();
+    assert!(color.x > 0.0 && color.x < 1.0);
+    assert!(light.illuminate(Vec3A::new(1.0, 0.0, 0.0)).is_none());
+}
+
+#[test]
+fn directional_light_test() {
+    let light = Light::new(LightKind::Directional);
+    let (direction, distance, color) = light.illuminate(Vec3A::new(5.0, 5.0, 5.0)).unwrap();
+    assert_eq!(direction, Vec3A::Z);
+    assert_eq!(distance, f32::INFINITY);
+    assert_eq!(color, Vec3A::ONE);
+}
@@ -1,31 +1,87 @@
 pub mod camera;
+pub mod description;
+pub mod gltf;
+pub mod instance;
+pub mod light;
 pub mod object;
 pub mod parser;
+pub mod ply;
 pub mod renderer;
+pub mod stl;
+pub mod writer;
 
 use crate::{
     material::Material,
-    primitives::{Hit, Ray, TriangleMesh},
-    traits::Intersectable,
+    primitives::{Bvh, Hit, Ray, Shape, TriangleMesh},
+    traits::{Bounded, Intersectable},
 };
 
 use object::Object;
 
+use glam::Mat4;
+#[cfg(not(feature = "libm"))]
+use {
+    crate::primitives::{
+        packet::{hit_distances, merge_hits},
+        PacketHits, RayPacket,
+    },
+    glam::Vec4,
+};
+
 pub use camera::Camera;
+pub use description::{load_geometry, Geometry, SceneDescription};
+pub use instance::{Instance, InstanceDescription};
+pub use light::{Light, LightKind};
 pub use renderer::SceneRenderer;
 
+/// Member of the top-level hierarchy of the scene, with the index into the
+/// list of the corresponding kind.
+#[derive(Debug, Clone, Copy)]
+enum Member {
+    Object(usize),
+    Instance(usize),
+    Shape(usize),
+}
+
+/// The scene to be rendered. Intersection queries use a two-level
+/// acceleration structure: every object owns a bounding volume hierarchy over
+/// its triangles and the scene keeps a top-level hierarchy over the bounding
+/// boxes of the objects, instances and shapes. Adding or moving an object only
+/// rebuilds the top level. Unbounded shapes, like planes, are kept outside of
+/// the hierarchy and are always tested.
 pub struct Scene<'this> {
     objects: Vec<Object<'this>>,
+    instances: Vec<Instance<'this>>,
+    shapes: Vec<Shape>,
+    lights: Vec<Light>,
     triangle_mesh: &'this TriangleMesh,
+    top_level: Bvh,
+    members: Vec<Member>,
+    unbounded_shapes: Vec<usize>,
 }
 
 impl<'this> Scene<'this> {
+    /// Creates a scene with the objects of the triangle mesh.
+    ///
+    /// ### Panics
+    /// If an object is not part of `triangle_mesh`, see `add_object`.
     #[inline]
     pub fn new(triangle_mesh: &'this TriangleMesh, objects: Vec<Object<'this>>) -> Self {
-        Self {
+        for object in &objects {
+            Self::assert_mesh(triangle_mesh, object.mesh(), &object.identifier);
+        }
+        let mut scene = Self {
             objects,
+            instances: Vec::new(),
+            shapes: Vec::new(),
+            lights: Vec::new(),
             triangle_mesh,
-        }
+            top_level: Bvh::default(),
+            members: Vec::new(),
+            unbounded_shapes: Vec::new(),
+        };
+        scene.rebuild_top_level();
+        scene
     }
 
     #[inline]
@@ -38,20 +94,223 @@ impl<'this> Scene<'this> {
     pub fn triangle_mesh(&self) -> &'this TriangleMesh {
         self.triangle_mesh
     }
-}
 
-impl<'this> Intersectable for Scene<'this> {
-    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+    /// Get reference to the objects in the scene.
+    #[inline]
+    pub fn objects(&self) -> &[Object<'this>] {
+        &self.objects
+    }
+
+    /// Get reference to the instances in the scene.
+    #[inline]
+    pub fn instances(&self) -> &[Instance<'this>] {
+        &self.instances
+    }
+
+    /// Get reference to the analytic shapes in the scene.
+    #[inline]
+    pub fn shapes(&self) -> &[Shape] {
+        &self.shapes
+    }
+
+    /// Get reference to the punctual lights in the scene.
+    #[inline]
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    /// Add a punctual light to the scene. Lights are not part of the
+    /// hierarchy, as they can't be hit by rays.
+    #[inline]
+    pub fn add_light(&mut self, light: Light) {
+        self.lights.push(light);
+    }
+
+    /// Add an object to the scene. Only the top-level hierarchy is rebuilt,
+    /// the hierarchies of the other objects are left untouched.
+    ///
+    /// ### Panics
+    /// If the object is not part of the triangle mesh of the scene, as the
+    /// materials of hits are looked up in that mesh.
+    pub fn add_object(&mut self, object: Object<'this>) {
+        Self::assert_mesh(self.triangle_mesh, object.mesh(), &object.identifier);
+        self.objects.push(object);
+        self.rebuild_top_level();
+    }
+
+    /// Remove the object with the given identifier from the scene, returning
+    /// it if it was found.
+    pub fn remove_object(&mut self, identifier: &str) -> Option<Object<'this>> {
+        let index = self
+            .objects
+            .iter()
+            .position(|o| o.identifier == identifier)?;
+        let object = self.objects.remove(index);
+        self.rebuild_top_level();
+        Some(object)
+    }
+
+    /// Add an instance to the scene. The instanced object must outlive the
+    /// scene, so it is typically stored next to the mesh rather than in the
+    /// scene itself.
+    ///
+    /// ### Panics
+    /// If the instanced objects are not part of the triangle mesh of the
+    /// scene, see `add_object`.
+    pub fn add_instance(&mut self, instance: Instance<'this>) {
+        self.add_instances(std::iter::once(instance));
+    }
+
+    /// Add several instances to the scene, rebuilding the top-level hierarchy
+    /// only once. See `add_instance`.
+    pub fn add_instances(&mut self, instances: impl IntoIterator<Item = Instance<'this>>) {
+        for instance in instances {
+            if let Some(mesh) = instance.mesh() {
+                Self::assert_mesh(self.triangle_mesh, mesh, &instance.identifier);
+            }
+            self.instances.push(instance);
+        }
+        self.rebuild_top_level();
+    }
+
+    /// Panics if `mesh`, the mesh of the object or instance `identifier`, is
+    /// not the triangle mesh of the scene.
+    fn assert_mesh(triangle_mesh: &TriangleMesh, mesh: &TriangleMesh, identifier: &str) {
+        assert!(
+            std::ptr::eq(triangle_mesh, mesh),
+            "'{}' is not part of the triangle mesh of the scene",
+            identifier
+        );
+    }
+
+    /// Set the transform of the instance with the given identifier. Returns
+    /// false if there is no such instance.
+    pub fn set_instance_transform(&mut self, identifier: &str, transform: Mat4) -> bool {
+        match self.instances.iter_mut().find(|i| i.identifier == identifier) {
+            Some(instance) => {
+                instance.set_transform(transform);
+                self.rebuild_top_level();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Add an analytic shape, like a sphere or a plane, to the scene. The
+    /// shape is shaded with its own material.
+    pub fn add_shape(&mut self, shape: impl Into<Shape>) {
+        self.shapes.push(shape.into());
+        self.rebuild_top_level();
+    }
+
+    /// Rebuild the top-level hierarchy from the bounding boxes of the objects,
+    /// instances and bounded shapes.
+    fn rebuild_top_level(&mut self) {
+        self.members.clear();
+        self.unbounded_shapes.clear();
+        let mut bounding_boxes = Vec::new();
+
+        for (i, object) in self.objects.iter().enumerate() {
+            self.members.push(Member::Object(i));
+            bounding_boxes.push(object.bounding_box());
+        }
+        for (i, instance) in self.instances.iter().enumerate() {
+            self.members.push(Member::Instance(i));
+            bounding_boxes.push(instance.bounding_box());
+        }
+        for (i, shape) in self.shapes.iter().enumerate() {
+            if shape.is_bounded() {
+                self.members.push(Member::Shape(i));
+                bounding_boxes.push(shape.bounding_box());
+            } else {
+                self.unbounded_shapes.push(i);
+            }
+        }
+        self.top_level = Bvh::new(&bounding_boxes);
+    }
+
+    /// Get the member of the top-level hierarchy at the given index.
+    #[inline]
+    fn member(&self, index: usize) -> &dyn Intersectable {
+        match self.members[index] {
+            Member::Object(i) => &self.objects[i],
+            Member::Instance(i) => &self.instances[i],
+            Member::Shape(i) => &self.shapes[i],
+        }
+    }
+
+    /// Closest intersection with the shapes that are not part of the top-level
+    /// hierarchy.
+    #[inline]
+    fn intersect_unbounded(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
         let mut closest_hit: Option<Hit> = None;
-        for object in &self.objects {
-            if let Some(hit) = object.intersect(ray, t_min, t_max) {
-                if let Some(closest) = closest_hit {
-                    closest_hit = Some(hit.closest_hit(closest));
-                } else {
-                    closest_hit = Some(hit);
-                }
+        let mut t_max = t_max;
+        for &i in &self.unbounded_shapes {
+            if let Some(hit) = self.shapes[i].intersect(ray, t_min, t_max) {
+                t_max = hit.distance;
+                closest_hit = Some(hit);
             }
         }
         closest_hit
     }
-}
\ No newline at end of file
+}
+
+impl<'this> Intersectable for Scene<'this> {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+        let unbounded_hit = self.intersect_unbounded(ray, t_min, t_max);
+        let t_max = unbounded_hit.as_ref().map_or(t_max, |h| h.distance);
+        self.top_level
+            .intersect(ray, t_min, t_max, |i, ray, t_min, t_max| {
+                self.member(i).intersect(ray, t_min, t_max)
+            })
+            .or(unbounded_hit)
+    }
+
+    fn intersected(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.unbounded_shapes
+            .iter()
+            .any(|&i| self.shapes[i].intersected(ray, t_min, t_max))
+            || self
+                .top_level
+                .intersected(ray, t_min, t_max, |i, ray, t_min, t_max| {
+                    self.member(i).intersected(ray, t_min, t_max)
+                })
+    }
+
+    #[cfg(not(feature = "libm"))]
+    fn intersect_packet(&self, packet: &RayPacket, t_min: f32, t_max: Vec4) -> PacketHits {
+        let mut hits = PacketHits::default();
+        for &i in &self.unbounded_shapes {
+            let t_max = hit_distances(&hits, t_max);
+            merge_hits(&mut hits, self.shapes[i].intersect_packet(packet, t_min, t_max));
+        }
+        let t_max = hit_distances(&hits, t_max);
+        merge_hits(
+            &mut hits,
+            self.top_level
+                .intersect_packet(packet, t_min, t_max, |i, packet, t_min, t_max| {
+                    self.member(i).intersect_packet(packet, t_min, t_max)
+                }),
+        );
+        hits
+    }
+}
+
+#[cfg(test)]
+mod description_tests;
+#[cfg(test)]
+mod gltf_tests;
+#[cfg(test)]
+mod instance_tests;
+#[cfg(test)]
+mod light_tests;
+#[cfg(test)]
+mod parser_tests;
+#[cfg(test)]
+mod ply_tests;
+#[cfg(test)]
+mod renderer_tests;
+#[cfg(test)]
+mod stl_tests;
+#[cfg(test)]
+mod writer_tests;
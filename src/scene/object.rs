@@ -1,17 +1,23 @@
-use crate::primitives::{BoundingBox, Hit, Ray, TriangleIndex, TriangleMesh};
+use crate::primitives::{BoundingBox, Bvh, Hit, Ray, TriangleIndex, TriangleMesh};
+#[cfg(not(feature = "libm"))]
+use crate::primitives::{PacketHits, RayPacket};
 
-use crate::traits::Intersectable;
+use crate::traits::{Bounded, Intersectable};
 
-use glam::Vec3A;
+#[cfg(not(feature = "libm"))]
+use glam::Vec4;
 
 /// Struct representing an object in the scene. An object is essentially a collections
 /// of triangles.
+///
+/// The triangles are organized in a bounding volume hierarchy when the object
+/// is created, which is used to speed up the intersection tests.
 #[derive(Debug)]
 pub struct Object<'mesh> {
     pub identifier: String,
     pub triangles: Vec<TriangleIndex>,
     mesh: &'mesh TriangleMesh,
-    bounding_box: BoundingBox,
+    bvh: Bvh,
 }
 
 impl<'mesh> Object<'mesh> {
@@ -20,43 +26,55 @@ impl<'mesh> Object<'mesh> {
         triangles: Vec<TriangleIndex>,
         mesh: &'mesh TriangleMesh,
     ) -> Self {
-        let bounding_box = Self::bounding_box(&triangles, mesh);
+        let bounding_boxes: Vec<BoundingBox> = triangles
+            .iter()
+            .map(|t_idx| mesh.get_triangle(t_idx).bounding_box())
+            .collect();
+        let bvh = Bvh::new(&bounding_boxes);
         Self {
             identifier,
             mesh,
             triangles,
-            bounding_box,
+            bvh,
         }
     }
 
-    fn bounding_box(triangles: &[TriangleIndex], mesh: &TriangleMesh) -> BoundingBox {
-        let mut min_coordinates = Vec3A::splat(f32::INFINITY);
-        let mut max_coordinates = Vec3A::splat(f32::NEG_INFINITY);
-        triangles.iter().for_each(|t_idx| {
-            let tri = mesh.get_triangle(t_idx);
-            min_coordinates = min_coordinates.min(tri.min());
-            max_coordinates = max_coordinates.max(tri.max());
-        });
-        BoundingBox::new(min_coordinates, max_coordinates)
+    /// Get reference to the triangle mesh the object is part of.
+    #[inline]
+    pub fn mesh(&self) -> &'mesh TriangleMesh {
+        self.mesh
+    }
+}
+
+impl<'mesh> Bounded for Object<'mesh> {
+    /// Returns the bounding box of all triangles in the object.
+    #[inline]
+    fn bounding_box(&self) -> BoundingBox {
+        self.bvh.bounding_box()
     }
 }
 
 impl<'mesh> Intersectable for Object<'mesh> {
     fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
-        if !self.bounding_box.intersect(ray, t_min, t_max) {
-            return None;
-        }
+        self.bvh.intersect(ray, t_min, t_max, |i, ray, t_min, t_max| {
+            self.mesh
+                .intersect_triangle(ray, &self.triangles[i], t_min, t_max)
+        })
+    }
 
-        let mut closest_hit: Option<Hit> = None;
-        for t_idx in &self.triangles {
-            if let Some(hit) = self.mesh.intersect_triangle(ray, t_idx, t_min, t_max) {
-                if let Some(closest) = closest_hit {
-                    closest_hit = Some(hit.closest_hit(closest));
-                } else {
-                    closest_hit = Some(hit);
-                }
-            }
-        }
-        closest_hit
+    fn intersected(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.bvh.intersected(ray, t_min, t_max, |i, ray, t_min, t_max| {
+            self.mesh
+                .triangle_intersected(ray, &self.triangles[i], t_min, t_max)
+        })
+    }
+
+    #[cfg(not(feature = "libm"))]
+    fn intersect_packet(&self, packet: &RayPacket, t_min: f32, t_max: Vec4) -> PacketHits {
+        self.bvh
+            .intersect_packet(packet, t_min, t_max, |i, packet, t_min, t_max| {
+                self.mesh
+                    .intersect_triangle_packet(packet, &self.triangles[i], t_min, t_max)
+            })
     }
 }
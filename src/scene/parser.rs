@@ -1,237 +1,523 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, io::BufRead, sync::Arc};
 
-use crate::primitives::{
-    triangle,
-    trianglemesh::{self, TriangleMesh},
+use crate::{
+    error::{column, ParseError, ParseErrorKind},
+    material::{self, Material, Texture},
+    primitives::{
+        triangle::{
+            self,
+            parser::{FaceVertexData, MISSING_NORMAL},
+            TriangleIndex,
+        },
+        trianglemesh::{
+            self,
+            normals::{self, NormalFace},
+            parser::VertexParseResult,
+            TriangleMesh,
+        },
+        Color, Normal, Position, TexCoord,
+    },
+    resolver::{self, AssetResolver, FileSystemResolver},
+    scene::object::Object,
 };
 
-use crate::{material, scene::object::Object};
+/// Map from object name to the triangles of the object.
+pub type ObjectMap = HashMap<String, Vec<TriangleIndex>>;
 
-use glam::{Vec2, Vec3A};
+/// Name of the object of faces that are not part of an object or group.
+pub const DEFAULT_OBJECT: &str = "default";
 
-use nom::{
-    branch::alt,
-    bytes::complete::{tag, take_till},
-    character::{complete::line_ending, is_newline},
-    multi::separated_list1,
-    sequence::preceded,
-    IResult,
-};
-
-#[derive(Debug)]
-enum ParseLineResult<'a> {
-    VertexPosition(&'a str),
-    VertexNormal(&'a str),
-    VertexTexture(&'a str),
-    ObjectName(&'a str),
-    UseMaterial(&'a str),
-    Face(&'a str),
-    MaterialFile(&'a str),
-    Comment(&'a str),
-    Other(&'a str),
-}
+/// Material index of faces using the default material, until the index of the
+/// default material is known.
+const DEFAULT_MATERIAL: usize = usize::MAX;
 
-/// Get triangle mesh and an object map from an obj file. The obj file is
-/// expected to be in the triangle format.
+/// Get triangle mesh and an object map from an obj file.
+///
+/// Objects are created from `o` statements, faces that are not part of an
+/// object are put in an object named after their group (`g <name>`), or in
+/// the object `default` if they are not part of a group either. Faces
+/// outside of any smoothing group (`s off`) get a flat normal, other faces
+/// use the vertex normals of the file. Faces without vertex normals
+/// (`f v1 v2 v3` or `f v1/vt1 v2/vt2 v3/vt3`) get their geometric face
+/// normal, see `load_obj_with_crease_angle` for smooth normals. Negative
+/// indices in faces are relative to the vertex data defined before the face.
+/// Vertex colors after the positions (`v x y z r g b`) are loaded, vertices
+/// without a color are white then.
+///
+/// The material set with `usemtl` applies to all following faces, also in
+/// later objects, until the next `usemtl`. Faces before the first `usemtl`,
+/// or with a material that is not defined in any of the material libraries
+/// (`mtllib`), get a default material.
+///
+/// Material libraries are loaded relative to `directory`. The file is read
+/// in a single pass, see `read_obj`. Use `load_obj` to load the files from
+/// another source than the file system.
 ///
 /// ### Arguments
 /// - `directory` - The directory where the obj file is located.
 /// - `obj_file` - The name of the obj file.
 ///
 /// ### Returns
-/// A tuple containing the triangle mesh, object map and material map. The
-/// object map maps the object name to the faces (string format) that belong to
-/// that object. The material map maps the material name to the index of the
+/// A tuple containing the triangle mesh, material map and object map. The
+/// object map maps the object name to the triangles that belong to that
+/// object. The material map maps the material name to the index of the
 /// material in the triangle mesh.
 ///
-/// ### Panics
-/// The function panics when:
-/// 1. The obj file cannot be read.
-/// 2. The obj file cannot be parsed.
-/// 3. The material file cannot be read.
-/// 4. The material file cannot be parsed.
-///
+/// An error is returned when the obj file or one of its material files can't
+/// be read or parsed. The error contains the file and line of the problem.
 pub fn get_triangle_mesh_and_obj_map(
     directory: &str,
     obj_file: &str,
-) -> (
-    TriangleMesh,
-    HashMap<String, usize>,
-    HashMap<String, String>,
-) {
-    let obj_path = format!("{}/{}", directory, obj_file);
-    let obj_file = std::fs::read_to_string(obj_path).expect("PARSE_OBJ: Unable to read obj file");
-
-    // Extract the vertex data, material file and object map from the obj file
-    let (_, (v_data, material_file, object_map)) =
-        extract_parts_obj(&obj_file).expect("PARSE_OBJ: Failed to extract parts from obj file");
-
-    // Get vertex data from the vertex data string
-    let (_, (vp, vn, _)) = get_vertex_data(&v_data).unwrap();
-
-    // Load the materials from the material file.
-    let mat_path = format!("{}/{}", directory, material_file);
-    let mat_file = std::fs::read_to_string(mat_path.trim_end())
-        .expect("PARSE_OBJ: Unable to read material file");
-    let (_, (materials, material_map)) = material::parser::materials(&mat_file).unwrap();
-
-    // Create the triangle mesh that stores the vertex data and materials
-    let triangle_mesh = TriangleMesh::new(vp, vn, materials);
-
-    (triangle_mesh, material_map, object_map)
+) -> Result<(TriangleMesh, HashMap<String, usize>, ObjectMap), ParseError> {
+    load_obj(&FileSystemResolver::new(directory), obj_file)
 }
 
-pub fn get_objects<'a, 'b>(
-    triangle_mesh: &'a TriangleMesh,
-    object_map: &'b HashMap<String, String>,
-    material_map: &'b HashMap<String, usize>,
-) -> Vec<Object<'a>> {
-    let mut objects = Vec::new();
-    for (name, faces_str) in object_map {
-        let (_, indices) = triangle::parser::parse_triangle_indices(faces_str, material_map)
-            .expect("PARSE: Failed to parse triangle faces");
-        let object = Object::new(name.to_owned(), indices, triangle_mesh);
-        objects.push(object);
-    }
-    objects
+/// Load an obj file and its material libraries from `resolver`. Material
+/// libraries are resolved relative to the directory of the obj file. See
+/// `get_triangle_mesh_and_obj_map` for the supported statements.
+///
+/// ### Arguments
+/// - `resolver` - The source of the obj file and its material libraries.
+/// - `obj_file` - The path of the obj file in the resolver.
+///
+/// ### Returns
+/// The same as `get_triangle_mesh_and_obj_map`.
+pub fn load_obj(
+    resolver: &dyn AssetResolver,
+    obj_file: &str,
+) -> Result<(TriangleMesh, HashMap<String, usize>, ObjectMap), ParseError> {
+    load_obj_with_crease_angle(resolver, obj_file, None)
 }
 
-/// Parse a line of the input string slice.
-fn line(input: &str) -> IResult<&str, &str> {
-    let (input, line) = take_till(|c| is_newline(c as u8))(input)?;
-    Ok((input, line))
+/// Load an obj file like `load_obj`, with smooth normals for the faces
+/// without vertex normals. The normals are averaged over the faces of the
+/// same smoothing group (`s <group>`) that meet at a vertex at an angle of at
+/// most the crease angle, faces outside of any smoothing group (`s off`) keep
+/// their face normal. See `trianglemesh::normals::generate_normals`.
+///
+/// ### Arguments
+/// - `resolver` - The source of the obj file and its material libraries.
+/// - `obj_file` - The path of the obj file in the resolver.
+/// - `crease_angle` - The crease angle in radians, or `None` for face
+///   normals like `load_obj`.
+///
+/// ### Returns
+/// The same as `get_triangle_mesh_and_obj_map`.
+pub fn load_obj_with_crease_angle(
+    resolver: &dyn AssetResolver,
+    obj_file: &str,
+    crease_angle: Option<f32>,
+) -> Result<(TriangleMesh, HashMap<String, usize>, ObjectMap), ParseError> {
+    let location = resolver.location(obj_file);
+    let reader = resolver
+        .open(obj_file)
+        .map_err(|e| ParseError::io(&location, e))?;
+    let mut loader = ObjLoader::new(resolver, resolver::parent_directory(obj_file));
+    loader.crease_angle = crease_angle;
+    read_obj_with(reader, loader).map_err(|e| e.in_file(&location))
 }
 
-/// Parse the vertex position data (string format) from the input string slice.
-fn parse_vertex_position(input: &str) -> IResult<&str, ParseLineResult> {
-    let (input, pos) = preceded(tag("v "), line)(input)?;
-    Ok((input, ParseLineResult::VertexPosition(pos)))
+/// Create the objects of the object map.
+pub fn get_objects<'a>(triangle_mesh: &'a TriangleMesh, object_map: &ObjectMap) -> Vec<Object<'a>> {
+    object_map
+        .iter()
+        .map(|(name, triangles)| Object::new(name.to_owned(), triangles.clone(), triangle_mesh))
+        .collect()
 }
 
-/// Parse the vertex normal data (string format) from the input string slice.
-fn parse_vertex_normal(input: &str) -> IResult<&str, ParseLineResult> {
-    let (input, pos) = preceded(tag("vn "), line)(input)?;
-    Ok((input, ParseLineResult::VertexNormal(pos)))
+/// Parse the contents of an obj file that is already in memory. Material
+/// libraries are loaded from `resolver`, relative to its root. See
+/// `get_triangle_mesh_and_obj_map` for the supported statements.
+pub fn parse_obj(
+    input: &str,
+    resolver: &dyn AssetResolver,
+) -> Result<(TriangleMesh, HashMap<String, usize>, ObjectMap), ParseError> {
+    let mut loader = ObjLoader::new(resolver, "");
+    for (i, line) in input.lines().enumerate() {
+        loader.parse_line(line, i + 1)?;
+    }
+    Ok(loader.finish())
 }
 
-/// Parse the vertex texture data (string format) from the input string slice.
-fn parse_vertex_texture(input: &str) -> IResult<&str, ParseLineResult> {
-    let (input, pos) = preceded(tag("vt "), line)(input)?;
-    Ok((input, ParseLineResult::VertexTexture(pos)))
+/// Read an obj file from `reader` in a single pass. Each line is parsed
+/// directly into the triangle mesh and object map, so only one line of the
+/// file is kept in memory at a time. Material libraries are loaded from
+/// `resolver`, relative to its root. See `get_triangle_mesh_and_obj_map` for
+/// the supported statements.
+///
+/// ### Arguments
+/// - `reader` - The reader to read the obj file from.
+/// - `resolver` - The source of the material libraries.
+///
+/// ### Returns
+/// The same as `get_triangle_mesh_and_obj_map`. Errors while reading, for
+/// example invalid UTF-8, are returned as `ParseErrorKind::Io` errors at the
+/// start (column 1) of the line that could not be read.
+pub fn read_obj<R: BufRead>(
+    reader: R,
+    resolver: &dyn AssetResolver,
+) -> Result<(TriangleMesh, HashMap<String, usize>, ObjectMap), ParseError> {
+    read_obj_with(reader, ObjLoader::new(resolver, ""))
 }
 
-/// Parse the object name from the input string slice.
-fn parse_object_name(input: &str) -> IResult<&str, ParseLineResult> {
-    let (input, pos) = preceded(tag("o "), line)(input)?;
-    Ok((input, ParseLineResult::ObjectName(pos)))
+/// Read an obj file from `reader` into `loader`.
+fn read_obj_with<R: BufRead>(
+    mut reader: R,
+    mut loader: ObjLoader,
+) -> Result<(TriangleMesh, HashMap<String, usize>, ObjectMap), ParseError> {
+    let mut line = String::new();
+    let mut line_number = 0;
+    loop {
+        line.clear();
+        line_number += 1;
+        let read = reader
+            .read_line(&mut line)
+            .map_err(|e| ParseError::new(ParseErrorKind::Io, line_number, 1, e.to_string()))?;
+        if read == 0 {
+            break;
+        }
+        loader.parse_line(line.trim_end_matches(['\n', '\r']), line_number)?;
+    }
+    Ok(loader.finish())
 }
 
-/// Parse the material name from the input string slice.
-fn parse_use_material(input: &str) -> IResult<&str, ParseLineResult> {
-    let (input, pos) = preceded(tag("usemtl "), line)(input)?;
-    Ok((input, ParseLineResult::UseMaterial(pos)))
+/// State of an obj file while it is parsed line by line.
+struct ObjLoader<'a> {
+    resolver: &'a dyn AssetResolver,
+    /// Directory of the obj file in the resolver.
+    directory: &'a str,
+    positions: Vec<Position>,
+    normals: Vec<Normal>,
+    tex_coords: Vec<TexCoord>,
+    /// Vertex colors, empty if no vertex has a color so far.
+    colors: Vec<Color>,
+    materials: Vec<Material>,
+    material_map: HashMap<String, usize>,
+    /// Textures of the materials by path, so textures used by multiple
+    /// materials are only loaded once.
+    textures: HashMap<String, Arc<Texture>>,
+    /// Objects in the order they are created, with their triangles.
+    objects: Vec<(String, Vec<TriangleIndex>)>,
+    object_indices: HashMap<String, usize>,
+    /// Triangles (object and index in the object) that need a flat normal.
+    /// The normals are added after all normals of the file, so relative
+    /// normal indices are not affected.
+    flat_triangles: Vec<(usize, usize)>,
+    /// Triangles (object and index in the object) without vertex normals,
+    /// with their smoothing group.
+    generated_triangles: Vec<(usize, usize, u32)>,
+    /// Crease angle of the generated normals, `None` for face normals.
+    crease_angle: Option<f32>,
+    current_object: Option<usize>,
+    current_group: Option<String>,
+    /// Object that faces are added to, if it is known since the last `o` or
+    /// `g` statement.
+    face_object: Option<usize>,
+    current_material: usize,
+    /// Smoothing group of the faces, 0 if they are not part of any
+    /// smoothing group. Faces before the first `s` statement are smooth, in
+    /// group 1.
+    smoothing_group: u32,
 }
 
-/// Parse the face data (string format) from the input string slice.
-fn parse_face(input: &str) -> IResult<&str, ParseLineResult> {
-    let (input, pos) = preceded(tag("f "), line)(input)?;
-    Ok((input, ParseLineResult::Face(pos)))
-}
+impl<'a> ObjLoader<'a> {
+    fn new(resolver: &'a dyn AssetResolver, directory: &'a str) -> Self {
+        Self {
+            resolver,
+            directory,
+            positions: Vec::new(),
+            normals: Vec::new(),
+            tex_coords: Vec::new(),
+            colors: Vec::new(),
+            materials: Vec::new(),
+            material_map: HashMap::new(),
+            textures: HashMap::new(),
+            objects: Vec::new(),
+            object_indices: HashMap::new(),
+            flat_triangles: Vec::new(),
+            generated_triangles: Vec::new(),
+            crease_angle: None,
+            current_object: None,
+            current_group: None,
+            face_object: None,
+            current_material: DEFAULT_MATERIAL,
+            smoothing_group: 1,
+        }
+    }
 
-/// Parse the material file name from the input string slice.
-fn parse_material_file(input: &str) -> IResult<&str, ParseLineResult> {
-    let (input, pos) = preceded(tag("mtllib "), line)(input)?;
-    Ok((input, ParseLineResult::MaterialFile(pos)))
-}
+    /// Parse a single line of the obj file, without the line ending.
+    fn parse_line(&mut self, line: &str, line_number: usize) -> Result<(), ParseError> {
+        let keyword = match line.split_whitespace().next() {
+            Some(keyword) if !keyword.starts_with('#') => keyword,
+            _ => return Ok(()),
+        };
 
-/// Parse the comment from the input string slice.
-fn parse_comment(input: &str) -> IResult<&str, ParseLineResult> {
-    let (input, pos) = preceded(tag("#"), line)(input)?;
-    Ok((input, ParseLineResult::Comment(pos)))
-}
+        match keyword {
+            "v" | "vn" | "vt" => {
+                match trianglemesh::parser::parse_vertex_line(line, line_number)? {
+                    VertexParseResult::Position(position, color) => {
+                        // Vertices without a color are white, if any vertex
+                        // has a color.
+                        if color.is_some() || !self.colors.is_empty() {
+                            self.colors.resize(self.positions.len(), Color::ONE);
+                            self.colors.push(color.unwrap_or(Color::ONE));
+                        }
+                        self.positions.push(position);
+                    }
+                    VertexParseResult::Normal(normal) => self.normals.push(normal),
+                    VertexParseResult::TexCoord(tex_coord) => self.tex_coords.push(tex_coord),
+                }
+            }
+            "o" => {
+                let name = statement_argument(line, line_number, keyword)?;
+                self.current_object = Some(self.object_index(name));
+                self.face_object = self.current_object;
+            }
+            "g" => {
+                // Groups need a name, so that they can't be confused with
+                // the default object.
+                let name = statement_argument(line, line_number, keyword)?;
+                self.current_group = Some(name.to_owned());
+                self.face_object = self.current_object;
+            }
+            "s" => self.smoothing_group = parse_smoothing_group(line, line_number)?,
+            "usemtl" => {
+                let name = triangle::parser::parse_material_name(line, line_number)?;
+                self.current_material = *self.material_map.get(name).unwrap_or(&DEFAULT_MATERIAL);
+            }
+            "f" => {
+                let object = match self.face_object {
+                    Some(object) => object,
+                    None => {
+                        let name = self.current_group.take();
+                        let object = self.object_index(name.as_deref().unwrap_or(DEFAULT_OBJECT));
+                        self.current_group = name;
+                        self.face_object = Some(object);
+                        object
+                    }
+                };
+                let vertex_data =
+                    FaceVertexData::new(&self.positions, self.normals.len(), self.tex_coords.len());
+                let triangles = &mut self.objects[object].1;
+                let start = triangles.len();
+                triangle::parser::parse_face(
+                    line,
+                    line_number,
+                    self.current_material,
+                    &vertex_data,
+                    triangles,
+                )?;
+                for (i, triangle) in triangles.iter().enumerate().skip(start) {
+                    if triangle.normal_indices().0 == MISSING_NORMAL {
+                        self.generated_triangles
+                            .push((object, i, self.smoothing_group));
+                    } else if self.smoothing_group == 0 && !triangle.is_flat() {
+                        self.flat_triangles.push((object, i));
+                    }
+                }
+            }
+            "mtllib" => {
+                let files = statement_argument(line, line_number, keyword)?;
+                for file in files.split_whitespace() {
+                    let path = resolver::join_path(self.directory, file);
+                    let (materials, material_map) =
+                        load_materials(self.resolver, &path, &mut self.textures)?;
+                    let offset = self.materials.len();
+                    self.material_map
+                        .extend(material_map.into_iter().map(|(k, v)| (k, v + offset)));
+                    self.materials.extend(materials);
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
 
-/// Parse the other data from the input string slice.
-fn parse_other(input: &str) -> IResult<&str, ParseLineResult> {
-    let (input, pos) = line(input)?;
-    Ok((input, ParseLineResult::Other(pos)))
-}
+    /// Get the index of the object with the given name, the object is created
+    /// if it does not exist yet.
+    fn object_index(&mut self, name: &str) -> usize {
+        if let Some(&index) = self.object_indices.get(name) {
+            return index;
+        }
+        self.objects.push((name.to_owned(), Vec::new()));
+        self.object_indices
+            .insert(name.to_owned(), self.objects.len() - 1);
+        self.objects.len() - 1
+    }
+
+    /// Add the flat and generated normals and the default material, and
+    /// create the triangle mesh and object map.
+    fn finish(mut self) -> (TriangleMesh, HashMap<String, usize>, ObjectMap) {
+        for (object, i) in self.flat_triangles {
+            let triangle = &mut self.objects[object].1[i];
+            let (v1, v2, v3) = triangle.vertex_indices();
+            let (p1, p2, p3) = (self.positions[v1], self.positions[v2], self.positions[v3]);
+            let normal_index = self.normals.len();
+            triangle.set_normal_indices((normal_index, normal_index, normal_index));
+            self.normals
+                .push((p2 - p1).cross(p3 - p1).normalize_or_zero());
+        }
+
+        let faces: Vec<NormalFace> = self
+            .generated_triangles
+            .iter()
+            .map(|&(object, i, group)| (self.objects[object].1[i].vertex_indices(), group))
+            .collect();
+        let (normals, normal_indices) =
+            normals::generate_normals(&self.positions, &faces, self.crease_angle);
+        let offset = self.normals.len();
+        for (&(object, i, _), (n1, n2, n3)) in self.generated_triangles.iter().zip(normal_indices) {
+            self.objects[object].1[i].set_normal_indices((n1 + offset, n2 + offset, n3 + offset));
+        }
+        self.normals.extend(normals);
+
+        // The default material is added after the materials of all libraries.
+        let default_material = self.materials.len();
+        let mut uses_default_material = false;
+        for (_, triangles) in &mut self.objects {
+            for triangle in triangles {
+                if triangle.material_index() == DEFAULT_MATERIAL {
+                    triangle.set_material_index(default_material);
+                    uses_default_material = true;
+                }
+            }
+        }
+        if uses_default_material {
+            self.materials.push(Material::default());
+        }
 
-/// Parse a line of an obj file. Returns the line as ParseLineResult
-fn parse_obj_line(input: &str) -> IResult<&str, ParseLineResult> {
-    let (input, result) = alt((
-        parse_vertex_position,
-        parse_vertex_normal,
-        parse_vertex_texture,
-        parse_object_name,
-        parse_use_material,
-        parse_face,
-        parse_material_file,
-        parse_comment,
-        parse_other,
-    ))(input)?;
-    Ok((input, result))
+        if !self.colors.is_empty() {
+            self.colors.resize(self.positions.len(), Color::ONE);
+        }
+        let mut triangle_mesh = TriangleMesh::with_tex_coords(
+            self.positions,
+            self.normals,
+            self.tex_coords,
+            self.materials,
+        );
+        triangle_mesh.set_vertex_colors(self.colors);
+        let object_map = self.objects.into_iter().collect();
+        (triangle_mesh, self.material_map, object_map)
+    }
 }
 
-/// Extract different parts of the obj file.
+/// Parse a smoothing group statement of the form `s <group>`, where group is a
+/// number or `off`.
 ///
 /// ### Returns
-/// A tuple containing:
-/// - vertex data (String). A string containing the vertex data in the obj file
-///   format.
-/// - material file name (String). The name of the material file to be used.
-/// - object map (HashMap<String, String>) A map from object name to the faces
-///   of the object.
-fn extract_parts_obj(input: &str) -> IResult<&str, (String, String, HashMap<String, String>)> {
-    let mut vertex_data = String::new();
-    let mut material_file = String::new();
-    let mut object_map = HashMap::new();
-
-    let (input, lines) = separated_list1(line_ending, parse_obj_line)(input)?;
-
-    let mut current_object = "";
-    lines.into_iter().for_each(|l| {
-        use ParseLineResult::*;
-        match l {
-            VertexPosition(pos) => vertex_data.push_str(&format!("v {}\n", pos)),
-            VertexNormal(pos) => vertex_data.push_str(&format!("vn {}\n", pos)),
-            VertexTexture(pos) => vertex_data.push_str(&format!("vt {}\n", pos)),
-            ObjectName(name) => {
-                current_object = name;
-                object_map.insert(current_object.to_owned(), String::new());
-            }
-            UseMaterial(material) => {
-                object_map
-                    .get_mut(current_object)
-                    .unwrap()
-                    .push_str(&format!("usemtl {}\n", material));
-            }
-            Face(face) => {
-                object_map
-                    .get_mut(current_object)
-                    .unwrap()
-                    .push_str(&format!("f {}\n", face));
+/// The smoothing group, 0 if the faces after the statement are not part of
+/// any smoothing group, ie for `s off` and `s 0`.
+fn parse_smoothing_group(line: &str, line_number: usize) -> Result<u32, ParseError> {
+    let group = statement_argument(line, line_number, "s")?;
+    match group {
+        "off" => Ok(0),
+        _ => group.parse::<u32>().map_err(|_| {
+            ParseError::new(
+                ParseErrorKind::Syntax,
+                line_number,
+                column(line, group),
+                format!(
+                    "Expected smoothing group number or `off`, found '{}'",
+                    group
+                ),
+            )
+        }),
+    }
+}
+
+/// Load the materials of the mtl file at `path` in the resolver, and the
+/// textures of the materials. Textures are resolved relative to the mtl file.
+pub(crate) fn load_materials(
+    resolver: &dyn AssetResolver,
+    path: &str,
+    textures: &mut HashMap<String, Arc<Texture>>,
+) -> Result<(Vec<Material>, HashMap<String, usize>), ParseError> {
+    let location = resolver.location(path);
+    let mut input = String::new();
+    resolver
+        .open(path)
+        .and_then(|mut reader| reader.read_to_string(&mut input))
+        .map_err(|e| ParseError::io(&location, e))?;
+    let (mut materials, material_map) =
+        material::parser::materials(&input).map_err(|e| e.in_file(&location))?;
+    load_texture_maps(
+        resolver,
+        resolver::parent_directory(path),
+        &mut materials,
+        textures,
+        &input,
+    )
+    .map_err(|e| e.in_file(&location))?;
+    Ok((materials, material_map))
+}
+
+/// Load the textures of the texture maps of `materials`, resolved relative to
+/// `directory`. Textures that are in `textures` already are shared. Images
+/// that can't be decoded are reported at their texture map statement in
+/// `source`, the file the materials were parsed from.
+pub(crate) fn load_texture_maps(
+    resolver: &dyn AssetResolver,
+    directory: &str,
+    materials: &mut [Material],
+    textures: &mut HashMap<String, Arc<Texture>>,
+    source: &str,
+) -> Result<(), ParseError> {
+    for map in materials.iter_mut().flat_map(Material::texture_maps_mut) {
+        // Exporters on Windows may use backslashes as path separators.
+        let texture_path = resolver::join_path(directory, &map.path.replace('\\', "/"));
+        let texture = match textures.get(&texture_path) {
+            Some(texture) => texture.clone(),
+            None => {
+                let texture = Arc::new(load_texture(resolver, &texture_path, source, &map.path)?);
+                textures.insert(texture_path, texture.clone());
+                texture
             }
-            MaterialFile(file) => material_file.push_str(&format!("{}\n", file)),
-            _ => (),
-        }
-    });
-    Ok((input, (vertex_data, material_file, object_map)))
+        };
+        map.texture = Some(texture);
+    }
+    Ok(())
 }
 
-type VertexData = (Vec<Vec3A>, Vec<Vec3A>, Vec<Vec2>);
+/// Load the texture at `path` in the resolver, for the texture map with the
+/// image file `map_path` in `source`. Images that can't be decoded are
+/// reported at the statement of the map.
+fn load_texture(
+    resolver: &dyn AssetResolver,
+    path: &str,
+    source: &str,
+    map_path: &str,
+) -> Result<Texture, ParseError> {
+    let location = resolver.location(path);
+    let data = resolver
+        .read(path)
+        .map_err(|e| ParseError::io(&location, e))?;
+    Texture::from_memory(&data).map_err(|e| {
+        let (line, column) = material::parser::find_texture_map(source, map_path);
+        ParseError::new(
+            ParseErrorKind::InvalidImage,
+            line,
+            column,
+            format!("Can't decode image '{}': {}", location, e),
+        )
+    })
+}
 
-/// Input an obj file and return the vertex data, material file name and object
-/// map.
-///
-/// ### Returns
-/// A tuple containing vertex data:
-/// - vertex positions (Vec<Vec3A>). A vector containing the vertex positions.
-/// - vertex normals (Vec<Vec3A>). A vector containing the vertex normals.
-/// - vetex texture coordinates (Vec<Vec2>). A vector containing the vertex
-fn get_vertex_data(input: &str) -> IResult<&str, VertexData> {
-    if let Ok((_, (vp, vn, vt))) = trianglemesh::parser::parse_vertex_data(input) {
-        Ok((input, (vp, vn, vt)))
-    } else {
-        panic!("Failed to parse vertex data");
+/// Get the argument of a statement of the form `<keyword> <argument>`, ie the
+/// rest of the line after the keyword without surrounding whitespace.
+fn statement_argument<'a>(
+    line: &'a str,
+    line_number: usize,
+    keyword: &str,
+) -> Result<&'a str, ParseError> {
+    let start = line.find(keyword).unwrap_or_default() + keyword.len();
+    let argument = line[start..].trim();
+    if argument.is_empty() {
+        return Err(ParseError::new(
+            ParseErrorKind::Syntax,
+            line_number,
+            column(line, &line[line.len()..]),
+            format!("Expected name after `{}`", keyword),
+        ));
     }
+    Ok(argument)
 }
@@ -0,0 +1,231 @@
+use super::parser::{
+    get_objects, get_triangle_mesh_and_obj_map, load_obj, load_obj_with_crease_angle,
+    DEFAULT_OBJECT,
+};
+
+use crate::{
+    error::ParseErrorKind,
+    material::Material,
+    primitives::TriangleIndex,
+    resolver::{FileSystemResolver, MemoryResolver},
+};
+
+use glam::Vec3A;
+
+fn test_resolver() -> FileSystemResolver {
+    FileSystemResolver::new("test")
+}
+
+#[test]
+fn load_obj_test() {
+    let (mesh, material_map, object_map) =
+        get_triangle_mesh_and_obj_map("test", "test_multiple_mats.obj").unwrap();
+    assert_eq!(mesh.materials().len(), material_map.len());
+
+    let objects = get_objects(&mesh, &object_map);
+    assert_eq!(objects.len(), object_map.len());
+    assert!(objects.iter().all(|o| !o.triangles.is_empty()));
+}
+
+#[test]
+fn missing_file_test() {
+    let error = get_triangle_mesh_and_obj_map("test", "missing.obj").unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::Io);
+    assert_eq!(error.file(), "test/missing.obj");
+}
+
+#[test]
+fn obj_syntax_error_test() {
+    let input = "o Cube\nv 1.0 1.0 1.0\nv 1.0 oops 1.0";
+    let error = super::parser::parse_obj(input, &test_resolver()).unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::Syntax);
+    assert_eq!((error.line(), error.column()), (3, 7));
+    assert_eq!(
+        error.in_file("test/cube.obj").to_string(),
+        "test/cube.obj:3:7: Expected vertex data of the form `v x y z`"
+    );
+
+    let error = super::parser::parse_obj("mtllib missing.mtl", &test_resolver()).unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::Io);
+    assert_eq!(error.file(), "test/missing.mtl");
+}
+
+#[test]
+fn groups_test() {
+    let vertices = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\n";
+
+    // Groups become objects when there are no objects.
+    let input = format!("{vertices}g A\nf 1//1 2//1 3//1\ng B\nf 3//1 2//1 1//1\nf 1//1 2//1 3//1");
+    let (_, _, object_map) = super::parser::parse_obj(&input, &test_resolver()).unwrap();
+    assert_eq!(object_map.len(), 2);
+    assert_eq!(object_map["A"].len(), 1);
+    assert_eq!(object_map["B"].len(), 2);
+
+    // Objects take precedence over groups.
+    let input = format!("{vertices}o Object\ng A\nf 1//1 2//1 3//1\ng B\nf 3//1 2//1 1//1");
+    let (_, _, object_map) = super::parser::parse_obj(&input, &test_resolver()).unwrap();
+    assert_eq!(object_map.len(), 1);
+    assert_eq!(object_map["Object"].len(), 2);
+
+    // Groups without a name would end up in the default object.
+    let input = format!("{vertices}g\nf 1//1 2//1 3//1");
+    let error = super::parser::parse_obj(&input, &test_resolver()).unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::Syntax);
+    assert_eq!((error.line(), error.column()), (5, 2));
+}
+
+#[test]
+fn smoothing_groups_test() {
+    let input = "o A
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vn 0 0 1
+vn 1 0 1
+vn 0 1 1
+s 1
+f 1//1 2//2 3//3
+s off
+f 1//1 2//2 3//3
+f 1//1 2//1 3//1";
+    let (mesh, _, object_map) = super::parser::parse_obj(input, &test_resolver()).unwrap();
+    let triangles = &object_map["A"];
+
+    // Smooth faces keep their vertex normals.
+    assert_eq!(triangles[0].normal_indices(), (0, 1, 2));
+
+    // Faces outside of smoothing groups get a flat normal, unless they
+    // already have one.
+    assert!(triangles[1].is_flat());
+    let (n, _, _) = triangles[1].normal_indices();
+    assert_eq!(mesh.triangle_normals()[n], glam::Vec3A::Z);
+    assert_eq!(triangles[2].normal_indices(), (0, 0, 0));
+
+    let error = super::parser::parse_obj("s smooth", &test_resolver()).unwrap_err();
+    assert_eq!((error.line(), error.column()), (1, 3));
+}
+
+#[test]
+fn relative_indices_test() {
+    // Relative indices refer to the vertex data defined before the face.
+    let input = "o A
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vn 0 0 1
+f -3//-1 -2//-1 -1//-1
+v 0 0 1
+f -4//1 -3//1 -1//1";
+    let (_, _, object_map) = super::parser::parse_obj(input, &test_resolver()).unwrap();
+    let triangles = &object_map["A"];
+    assert_eq!(triangles[0].vertex_indices(), (0, 1, 2));
+    assert_eq!(triangles[1].vertex_indices(), (0, 1, 3));
+
+    let input = format!("{input}\nf 1//1 2//1 5//1");
+    let error = super::parser::parse_obj(&input, &test_resolver()).unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::InvalidIndex);
+    assert_eq!((error.line(), error.column()), (9, 13));
+}
+
+#[test]
+fn obj_state_test() {
+    let input = "mtllib test_scene.mtl
+mtllib test.mtl missing.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vn 0 0 1
+f 1//1 2//1 3//1
+usemtl Red
+o A
+f 1//1 2//1 3//1
+o B
+f 1//1 2//1 3//1
+usemtl Unknown
+f 1//1 2//1 3//1";
+    let error = super::parser::parse_obj(input, &test_resolver()).unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::Io);
+    assert_eq!(error.file(), "test/missing.mtl");
+
+    let input = input.replace(" missing.mtl", "");
+    let (mesh, material_map, object_map) =
+        super::parser::parse_obj(&input, &test_resolver()).unwrap();
+
+    // Materials of both libraries, and the default material.
+    assert_eq!(material_map.len(), 4);
+    assert_eq!(mesh.materials().len(), 6);
+    let default_material = 5;
+    assert_eq!(mesh.materials()[default_material], Material::default());
+
+    // Faces before the first object are in the default object, and the
+    // material carries over to the next object.
+    assert_eq!(
+        object_map[DEFAULT_OBJECT][0].material_index(),
+        default_material
+    );
+    assert_eq!(object_map["A"][0].material_index(), material_map["Red"]);
+    assert_eq!(object_map["B"][0].material_index(), material_map["Red"]);
+    assert_eq!(object_map["B"][1].material_index(), default_material);
+}
+
+#[test]
+fn read_obj_test() {
+    // Reading a file in a single pass gives the same result as parsing it
+    // from memory.
+    let input = std::fs::read_to_string("test/test_scene.obj").unwrap();
+    let expected = super::parser::parse_obj(&input, &test_resolver()).unwrap();
+    let actual = super::parser::read_obj(input.as_bytes(), &test_resolver()).unwrap();
+    assert_eq!(actual.0, expected.0);
+    assert_eq!(actual.1, expected.1);
+    assert_eq!(actual.2, expected.2);
+
+    // Line endings are not part of the lines.
+    let input = "o A\r\nv 0 0 0\r\nv 1 0 0\r\nv 0 1 0\r\nvn 0 0 1\r\nf 1//1 2//1 3//1\r\n";
+    let (_, _, object_map) = super::parser::read_obj(input.as_bytes(), &test_resolver()).unwrap();
+    assert_eq!(object_map["A"].len(), 1);
+
+    let error =
+        super::parser::read_obj(&b"v 0 0 0\nv \xff 0 0\n"[..], &test_resolver()).unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::Io);
+    assert_eq!((error.line(), error.column()), (2, 1));
+}
+
+#[test]
+fn generated_normals_test() {
+    // A square and a triangle folded up along its edge x = 1, without normals.
+    let input = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 1 0 1\nvt 0 0\n\
+                 f 1/1 2/1 3/1 4/1\nf 2 5 3\ns off\nf 1 3 4\n";
+    let resolver = MemoryResolver::new().with_file("fold.obj", input);
+    let (mesh, _, object_map) = load_obj(&resolver, "fold.obj").unwrap();
+    let triangles = &object_map[DEFAULT_OBJECT];
+    assert_eq!(triangles.len(), 4);
+    assert!(triangles.iter().all(|t| t.is_flat()));
+    assert_eq!(mesh.triangle_normals(), &vec![Vec3A::Z, Vec3A::NEG_X]);
+    assert_eq!(triangles[0].tex_coord_indices(), Some((0, 0, 0)));
+
+    // Smooth normals are shared at the fold, except by the face without a
+    // smoothing group.
+    let crease_angle = Some(100f32.to_radians());
+    let (mesh, _, object_map) =
+        load_obj_with_crease_angle(&resolver, "fold.obj", crease_angle).unwrap();
+    let triangles = &object_map[DEFAULT_OBJECT];
+    // The normal of a triangle at the vertex at (1, 0, 0).
+    let normal_at_fold = |triangle: &TriangleIndex| {
+        let (v1, v2, _) = triangle.vertex_indices();
+        let (n1, n2, n3) = triangle.normal_indices();
+        let n = match (v1, v2) {
+            (1, _) => n1,
+            (_, 1) => n2,
+            _ => n3,
+        };
+        mesh.triangle_normals()[n]
+    };
+    assert!(!triangles[0].is_flat());
+    assert_eq!(normal_at_fold(&triangles[0]), normal_at_fold(&triangles[2]));
+    assert!(normal_at_fold(&triangles[0]).x < 0.0);
+    assert!(triangles[3].is_flat());
+    assert_eq!(
+        mesh.triangle_normals()[triangles[3].normal_indices().0],
+        Vec3A::Z
+    );
+}
@@ -0,0 +1,658 @@
+use std::{collections::HashMap, io::BufRead, path::Path};
+
+use glam::{Vec2, Vec3A};
+
+use crate::{
+    error::{column, ParseError, ParseErrorKind},
+    material::Material,
+    primitives::{
+        triangle::triangulation::triangulate, Normal, Position, TexCoord, TriangleIndex,
+        TriangleMesh,
+    },
+    resolver::AssetResolver,
+    scene::parser::ObjectMap,
+};
+
+/// Load a ply file from `resolver`. The ply file is loaded as a single
+/// object, named after the file without its extension.
+///
+/// ASCII, binary little endian and binary big endian files are supported.
+/// The vertex positions (`x`, `y`, `z`), normals (`nx`, `ny`, `nz`), colors
+/// (`red`, `green`, `blue`) and texture coordinates (`u`, `v` or `s`, `t`)
+/// of the `vertex` element are loaded, and the `vertex_indices` of the
+/// `face` element. Polygonal faces are triangulated, see `triangulate`.
+/// Other elements and properties are skipped.
+///
+/// Faces get flat normals if the vertices have no normals. The mesh has a
+/// single diffuse material, which is white if the vertices have colors, so
+/// the vertex colors are used as they are.
+///
+/// ### Arguments
+/// - `resolver` - The source of the ply file.
+/// - `path` - The path of the ply file in the resolver.
+///
+/// ### Returns
+/// The triangle mesh and an object map with the object of the file. An
+/// error is returned if the file can't be read or parsed. Errors in the body
+/// of binary files have line 0.
+pub fn load_ply(
+    resolver: &dyn AssetResolver,
+    path: &str,
+) -> Result<(TriangleMesh, ObjectMap), ParseError> {
+    let location = resolver.location(path);
+    let reader = resolver
+        .open(path)
+        .map_err(|e| ParseError::io(&location, e))?;
+    let name = Path::new(path)
+        .file_stem()
+        .map_or(path.into(), |stem| stem.to_string_lossy());
+    read_ply(reader, &name).map_err(|e| e.in_file(&location))
+}
+
+/// Read a ply file from `reader`. See `load_ply`.
+///
+/// ### Arguments
+/// - `reader` - The reader to read the ply file from.
+/// - `name` - The name of the object of the file.
+pub fn read_ply<R: BufRead>(
+    mut reader: R,
+    name: &str,
+) -> Result<(TriangleMesh, ObjectMap), ParseError> {
+    let header = read_header(&mut reader)?;
+    let mut body = Body {
+        reader,
+        format: header.format,
+        line: String::new(),
+        position: 0,
+        line_number: header.line_count,
+    };
+
+    let vertex_count = header
+        .elements
+        .iter()
+        .find(|e| e.name == "vertex")
+        .map_or(0, |e| e.count);
+    let mut vertices = Vertices::default();
+    let mut faces = Vec::new();
+    for element in &header.elements {
+        match element.name.as_str() {
+            "vertex" => read_vertices(&mut body, element, &mut vertices)?,
+            "face" => read_faces(&mut body, element, vertex_count, &mut faces)?,
+            _ => {
+                for _ in 0..element.count {
+                    body.start_element(element)?;
+                    for property in &element.properties {
+                        body.skip_property(property, element)?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(vertices.into_mesh(name, &faces))
+}
+
+/// Encoding of the body of a ply file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// Type of a property value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    /// Get the type with the given name, both the original names (`uchar`)
+    /// and the names with sizes (`uint8`) are supported.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "char" | "int8" => Some(Self::Int8),
+            "uchar" | "uint8" => Some(Self::UInt8),
+            "short" | "int16" => Some(Self::Int16),
+            "ushort" | "uint16" => Some(Self::UInt16),
+            "int" | "int32" => Some(Self::Int32),
+            "uint" | "uint32" => Some(Self::UInt32),
+            "float" | "float32" => Some(Self::Float32),
+            "double" | "float64" => Some(Self::Float64),
+            _ => None,
+        }
+    }
+
+    /// Get the size of a value of the type in binary files.
+    fn size(self) -> usize {
+        match self {
+            Self::Int8 | Self::UInt8 => 1,
+            Self::Int16 | Self::UInt16 => 2,
+            Self::Int32 | Self::UInt32 | Self::Float32 => 4,
+            Self::Float64 => 8,
+        }
+    }
+
+    /// Get the value that stands for full intensity in colors of the type,
+    /// ie the largest value of integer types and 1 for floating point types.
+    fn color_scale(self) -> f32 {
+        match self {
+            Self::Int8 => i8::MAX as f32,
+            Self::UInt8 => u8::MAX as f32,
+            Self::Int16 => i16::MAX as f32,
+            Self::UInt16 => u16::MAX as f32,
+            Self::Int32 => i32::MAX as f32,
+            Self::UInt32 => u32::MAX as f32,
+            Self::Float32 | Self::Float64 => 1.0,
+        }
+    }
+}
+
+/// Type of a property, either a single value or a list of values preceded by
+/// their count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PropertyType {
+    Scalar(ScalarType),
+    List(ScalarType, ScalarType),
+}
+
+#[derive(Debug)]
+struct Property {
+    name: String,
+    kind: PropertyType,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+#[derive(Debug)]
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+    /// Number of lines of the header, including `end_header`.
+    line_count: usize,
+}
+
+/// Read the header of a ply file, up to and including `end_header`.
+fn read_header<R: BufRead>(reader: &mut R) -> Result<Header, ParseError> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut buffer = Vec::new();
+    let mut line_number = 0;
+    loop {
+        buffer.clear();
+        line_number += 1;
+        let read = reader
+            .read_until(b'\n', &mut buffer)
+            .map_err(|e| ParseError::new(ParseErrorKind::Io, line_number, 0, e.to_string()))?;
+        if read == 0 {
+            return Err(ParseError::new(
+                ParseErrorKind::Syntax,
+                line_number,
+                0,
+                "Expected `end_header` before the end of the file",
+            ));
+        }
+        let line = std::str::from_utf8(&buffer)
+            .map_err(|e| ParseError::new(ParseErrorKind::Syntax, line_number, 0, e.to_string()))?
+            .trim_end_matches(['\n', '\r']);
+        let mut words = line.split_whitespace();
+        let keyword = words.next().unwrap_or_default();
+        let error = |part: &str, description: String| {
+            ParseError::new(
+                ParseErrorKind::Syntax,
+                line_number,
+                column(line, part),
+                description,
+            )
+        };
+
+        if line_number == 1 {
+            if keyword != "ply" {
+                return Err(error(
+                    line,
+                    "Expected `ply` at the start of the file".into(),
+                ));
+            }
+            continue;
+        }
+        match keyword {
+            "format" => {
+                let name = words.next().unwrap_or(&line[line.len()..]);
+                format = Some(match name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => {
+                        return Err(error(
+                            name,
+                            format!("Expected ply format, found '{}'", name),
+                        ))
+                    }
+                });
+            }
+            "element" => {
+                let (Some(name), Some(count)) = (words.next(), words.next()) else {
+                    return Err(error(
+                        &line[line.len()..],
+                        "Expected element of the form `element <name> <count>`".into(),
+                    ));
+                };
+                let count = count.parse().map_err(|_| {
+                    error(count, format!("Expected element count, found '{}'", count))
+                })?;
+                elements.push(Element {
+                    name: name.to_owned(),
+                    count,
+                    properties: Vec::new(),
+                });
+            }
+            "property" => {
+                let words: Vec<&str> = words.collect();
+                let scalar_type = |name: &str| {
+                    ScalarType::from_name(name)
+                        .ok_or_else(|| error(name, format!("Unknown property type '{}'", name)))
+                };
+                let (kind, name) = match words[..] {
+                    ["list", count_type, item_type, name] => (
+                        PropertyType::List(scalar_type(count_type)?, scalar_type(item_type)?),
+                        name,
+                    ),
+                    [scalar, name] if scalar != "list" => {
+                        (PropertyType::Scalar(scalar_type(scalar)?), name)
+                    }
+                    _ => {
+                        return Err(error(
+                            line,
+                            "Expected property of the form `property <type> <name>` or \
+                             `property list <count type> <type> <name>`"
+                                .into(),
+                        ))
+                    }
+                };
+                let Some(element) = elements.last_mut() else {
+                    return Err(error(line, "Property outside of an element".into()));
+                };
+                element.properties.push(Property {
+                    name: name.to_owned(),
+                    kind,
+                });
+            }
+            "end_header" => break,
+            // Comments, object information and empty lines.
+            _ => (),
+        }
+    }
+
+    let format = format.ok_or_else(|| {
+        ParseError::new(
+            ParseErrorKind::Syntax,
+            line_number,
+            0,
+            "Expected `format` in the header",
+        )
+    })?;
+    Ok(Header {
+        format,
+        elements,
+        line_count: line_number,
+    })
+}
+
+/// Reader for the values of the body of a ply file.
+struct Body<R> {
+    reader: R,
+    format: Format,
+    /// Current line of an ASCII file.
+    line: String,
+    /// Position of the next value in the current line.
+    position: usize,
+    line_number: usize,
+}
+
+impl<R: BufRead> Body<R> {
+    /// Start reading the next instance of an element. Every instance is on
+    /// its own line in ASCII files, empty lines are skipped.
+    fn start_element(&mut self, element: &Element) -> Result<(), ParseError> {
+        if self.format != Format::Ascii {
+            return Ok(());
+        }
+        loop {
+            self.line.clear();
+            self.position = 0;
+            self.line_number += 1;
+            let read = self.reader.read_line(&mut self.line).map_err(|e| {
+                ParseError::new(ParseErrorKind::Io, self.line_number, 0, e.to_string())
+            })?;
+            if read == 0 {
+                return Err(self.end_of_file(element));
+            }
+            if !self.line.trim().is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Read the next value of the given type.
+    fn value(&mut self, scalar_type: ScalarType, element: &Element) -> Result<f64, ParseError> {
+        match self.format {
+            Format::Ascii => {
+                let rest = &self.line[self.position..];
+                let start = rest.len() - rest.trim_start().len();
+                let rest = &rest[start..];
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                let token = &rest[..end];
+                self.position += start + end;
+                if token.is_empty() {
+                    return Err(ParseError::new(
+                        ParseErrorKind::Syntax,
+                        self.line_number,
+                        column(&self.line, token),
+                        format!("Expected more values for element '{}'", element.name),
+                    ));
+                }
+                token.parse().map_err(|_| {
+                    ParseError::new(
+                        ParseErrorKind::Syntax,
+                        self.line_number,
+                        column(&self.line, token),
+                        format!("Expected number, found '{}'", token),
+                    )
+                })
+            }
+            Format::BinaryLittleEndian | Format::BinaryBigEndian => {
+                let mut bytes = [0; 8];
+                let bytes = &mut bytes[..scalar_type.size()];
+                self.reader
+                    .read_exact(bytes)
+                    .map_err(|_| self.end_of_file(element))?;
+                if self.format == Format::BinaryBigEndian {
+                    bytes.reverse();
+                }
+                let value = match scalar_type {
+                    ScalarType::Int8 => bytes[0] as i8 as f64,
+                    ScalarType::UInt8 => bytes[0] as f64,
+                    ScalarType::Int16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    ScalarType::UInt16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    ScalarType::Int32 => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                    ScalarType::UInt32 => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                    ScalarType::Float32 => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+                    ScalarType::Float64 => f64::from_le_bytes(bytes.try_into().unwrap()),
+                };
+                Ok(value)
+            }
+        }
+    }
+
+    /// Read the number of values of a list property.
+    fn list_count(
+        &mut self,
+        count_type: ScalarType,
+        element: &Element,
+    ) -> Result<usize, ParseError> {
+        let count = self.value(count_type, element)?;
+        if count < 0.0 || count.fract() != 0.0 {
+            return Err(ParseError::new(
+                ParseErrorKind::Syntax,
+                self.line_number,
+                0,
+                format!(
+                    "Invalid list length {} in element '{}'",
+                    count, element.name
+                ),
+            ));
+        }
+        Ok(count as usize)
+    }
+
+    /// Read and discard the value or values of a property.
+    fn skip_property(&mut self, property: &Property, element: &Element) -> Result<(), ParseError> {
+        match property.kind {
+            PropertyType::Scalar(scalar_type) => {
+                self.value(scalar_type, element)?;
+            }
+            PropertyType::List(count_type, item_type) => {
+                for _ in 0..self.list_count(count_type, element)? {
+                    self.value(item_type, element)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn end_of_file(&self, element: &Element) -> ParseError {
+        ParseError::new(
+            ParseErrorKind::Syntax,
+            if self.format == Format::Ascii {
+                self.line_number
+            } else {
+                0
+            },
+            0,
+            format!(
+                "Unexpected end of file, expected {} '{}' elements",
+                element.count, element.name
+            ),
+        )
+    }
+}
+
+/// Meaning of a property of the vertex element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VertexProperty {
+    Position(usize),
+    Normal(usize),
+    Color(usize),
+    TexCoord(usize),
+    Other,
+}
+
+impl VertexProperty {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "x" => Self::Position(0),
+            "y" => Self::Position(1),
+            "z" => Self::Position(2),
+            "nx" => Self::Normal(0),
+            "ny" => Self::Normal(1),
+            "nz" => Self::Normal(2),
+            "red" | "diffuse_red" => Self::Color(0),
+            "green" | "diffuse_green" => Self::Color(1),
+            "blue" | "diffuse_blue" => Self::Color(2),
+            "u" | "s" | "texture_u" | "texture_s" => Self::TexCoord(0),
+            "v" | "t" | "texture_v" | "texture_t" => Self::TexCoord(1),
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Vertex data of a ply file. Normals, colors and texture coordinates are
+/// either empty or have one value per vertex.
+#[derive(Debug, Default)]
+struct Vertices {
+    positions: Vec<Position>,
+    normals: Vec<Normal>,
+    colors: Vec<Vec3A>,
+    tex_coords: Vec<TexCoord>,
+}
+
+impl Vertices {
+    /// Triangulate the faces and create the triangle mesh and object map.
+    fn into_mesh(mut self, name: &str, faces: &[Vec<usize>]) -> (TriangleMesh, ObjectMap) {
+        let has_normals = !self.normals.is_empty();
+        let has_tex_coords = !self.tex_coords.is_empty();
+        let mut triangles = Vec::new();
+        for face in faces {
+            let polygon: Vec<Position> = face.iter().map(|&i| self.positions[i]).collect();
+            for (a, b, c) in triangulate(&polygon) {
+                let vertices = (face[a], face[b], face[c]);
+                let mut triangle = if has_normals {
+                    TriangleIndex::with_normals(vertices, vertices, 0)
+                } else {
+                    let (p1, p2, p3) = (polygon[a], polygon[b], polygon[c]);
+                    self.normals
+                        .push((p2 - p1).cross(p3 - p1).normalize_or_zero());
+                    TriangleIndex::new(vertices, self.normals.len() - 1, 0)
+                };
+                if has_tex_coords {
+                    triangle.set_tex_coord_indices(Some(vertices));
+                }
+                triangles.push(triangle);
+            }
+        }
+
+        let material = Material {
+            diffuse_color: if self.colors.is_empty() {
+                Vec3A::splat(0.8)
+            } else {
+                Vec3A::ONE
+            },
+            ..Material::default()
+        };
+        let mut mesh = TriangleMesh::with_tex_coords(
+            self.positions,
+            self.normals,
+            self.tex_coords,
+            vec![material],
+        );
+        mesh.set_vertex_colors(self.colors);
+        (mesh, HashMap::from([(name.to_owned(), triangles)]))
+    }
+}
+
+/// Maximum number of element instances or list items memory is reserved for
+/// before they are read. The counts are read from the file, so a short file
+/// that declares a huge count must not reserve memory for all of it.
+const MAX_RESERVED: usize = 1 << 16;
+
+/// Read the instances of the vertex element.
+fn read_vertices<R: BufRead>(
+    body: &mut Body<R>,
+    element: &Element,
+    vertices: &mut Vertices,
+) -> Result<(), ParseError> {
+    let roles: Vec<VertexProperty> = element
+        .properties
+        .iter()
+        .map(|p| match p.kind {
+            PropertyType::Scalar(_) => VertexProperty::from_name(&p.name),
+            PropertyType::List(..) => VertexProperty::Other,
+        })
+        .collect();
+    let has = |role: fn(usize) -> VertexProperty, count: usize| {
+        (0..count).all(|i| roles.contains(&role(i)))
+    };
+    if !has(VertexProperty::Position, 3) {
+        return Err(ParseError::new(
+            ParseErrorKind::Unsupported,
+            0,
+            0,
+            "Vertex element without `x`, `y` and `z` properties",
+        ));
+    }
+    let (has_normals, has_colors, has_tex_coords) = (
+        has(VertexProperty::Normal, 3),
+        has(VertexProperty::Color, 3),
+        has(VertexProperty::TexCoord, 2),
+    );
+
+    vertices.positions.reserve(element.count.min(MAX_RESERVED));
+    for _ in 0..element.count {
+        body.start_element(element)?;
+        let (mut position, mut normal, mut color, mut tex_coord) =
+            (Vec3A::ZERO, Vec3A::ZERO, Vec3A::ZERO, Vec2::ZERO);
+        for (property, role) in element.properties.iter().zip(&roles) {
+            let PropertyType::Scalar(scalar_type) = property.kind else {
+                body.skip_property(property, element)?;
+                continue;
+            };
+            let value = body.value(scalar_type, element)? as f32;
+            match *role {
+                VertexProperty::Position(i) => position[i] = value,
+                VertexProperty::Normal(i) => normal[i] = value,
+                VertexProperty::Color(i) => color[i] = value / scalar_type.color_scale(),
+                VertexProperty::TexCoord(i) => tex_coord[i] = value,
+                VertexProperty::Other => (),
+            }
+        }
+        vertices.positions.push(position);
+        if has_normals {
+            vertices.normals.push(normal.normalize_or_zero());
+        }
+        if has_colors {
+            vertices.colors.push(color);
+        }
+        if has_tex_coords {
+            vertices.tex_coords.push(tex_coord);
+        }
+    }
+    Ok(())
+}
+
+/// Read the vertex indices of the instances of the face element.
+fn read_faces<R: BufRead>(
+    body: &mut Body<R>,
+    element: &Element,
+    vertex_count: usize,
+    faces: &mut Vec<Vec<usize>>,
+) -> Result<(), ParseError> {
+    let Some(indices_property) = element.properties.iter().position(|p| {
+        matches!(p.kind, PropertyType::List(..))
+            && (p.name == "vertex_indices" || p.name == "vertex_index")
+    }) else {
+        return Err(ParseError::new(
+            ParseErrorKind::Unsupported,
+            0,
+            0,
+            "Face element without `vertex_indices` list",
+        ));
+    };
+
+    faces.reserve(element.count.min(MAX_RESERVED));
+    for _ in 0..element.count {
+        body.start_element(element)?;
+        for (i, property) in element.properties.iter().enumerate() {
+            let PropertyType::List(count_type, item_type) = property.kind else {
+                body.skip_property(property, element)?;
+                continue;
+            };
+            if i != indices_property {
+                body.skip_property(property, element)?;
+                continue;
+            }
+            let count = body.list_count(count_type, element)?;
+            let mut face = Vec::with_capacity(count.min(MAX_RESERVED));
+            for _ in 0..count {
+                let index = body.value(item_type, element)?;
+                if index < 0.0 || index >= vertex_count as f64 {
+                    return Err(ParseError::new(
+                        ParseErrorKind::InvalidIndex,
+                        if body.format == Format::Ascii {
+                            body.line_number
+                        } else {
+                            0
+                        },
+                        0,
+                        format!(
+                            "Vertex index {} is out of range, there are {} vertices",
+                            index, vertex_count
+                        ),
+                    ));
+                }
+                face.push(index as usize);
+            }
+            faces.push(face);
+        }
+    }
+    Ok(())
+}
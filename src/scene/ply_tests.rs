@@ -0,0 +1,207 @@
+use super::ply::{load_ply, read_ply};
+
+use crate::{
+    error::ParseErrorKind,
+    primitives::{Ray, TriangleMesh},
+    resolver::MemoryResolver,
+    scene::parser::ObjectMap,
+};
+
+use glam::{Vec2, Vec3A};
+
+const HEADER: &str = "element vertex 5
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+property uchar red
+property uchar green
+property uchar blue
+property float u
+property float v
+element face 2
+property list uchar int vertex_indices
+element edge 1
+property int vertex1
+property int vertex2
+end_header
+";
+
+/// Vertices of a unit square in the z = 0 plane and one vertex above it.
+/// Every vertex has a position, a normal, a color and texture coordinates.
+const VERTICES: [([f32; 6], [u8; 3], [f32; 2]); 5] = [
+    ([0.0, 0.0, 0.0, 0.0, 0.0, 1.0], [255, 0, 0], [0.0, 0.0]),
+    ([1.0, 0.0, 0.0, 0.0, 0.0, 1.0], [0, 255, 0], [1.0, 0.0]),
+    ([1.0, 1.0, 0.0, 0.0, 0.0, 1.0], [0, 0, 255], [1.0, 1.0]),
+    ([0.0, 1.0, 0.0, 0.0, 0.0, 1.0], [255, 255, 255], [0.0, 1.0]),
+    ([0.0, 0.0, 1.0, 0.0, -1.0, 0.0], [0, 0, 0], [0.5, 0.5]),
+];
+/// A quad and a triangle.
+const FACES: [&[i32]; 2] = [&[0, 1, 2, 3], &[0, 4, 1]];
+
+fn ascii_ply() -> String {
+    let mut ply = format!("ply\nformat ascii 1.0\ncomment Test file\n{}", HEADER);
+    for (floats, color, tex_coord) in VERTICES {
+        let values: Vec<String> = floats
+            .iter()
+            .map(f32::to_string)
+            .chain(color.iter().map(u8::to_string))
+            .chain(tex_coord.iter().map(f32::to_string))
+            .collect();
+        ply += &format!("{}\n", values.join(" "));
+    }
+    for face in FACES {
+        let indices: Vec<String> = face.iter().map(i32::to_string).collect();
+        ply += &format!("{} {}\n", face.len(), indices.join(" "));
+    }
+    ply + "0 4\n"
+}
+
+fn binary_ply(big_endian: bool) -> Vec<u8> {
+    let format = if big_endian {
+        "binary_big_endian"
+    } else {
+        "binary_little_endian"
+    };
+    let mut ply = format!("ply\nformat {} 1.0\n{}", format, HEADER).into_bytes();
+    let float = |f: f32| {
+        if big_endian {
+            f.to_be_bytes()
+        } else {
+            f.to_le_bytes()
+        }
+    };
+    let int = |i: i32| {
+        if big_endian {
+            i.to_be_bytes()
+        } else {
+            i.to_le_bytes()
+        }
+    };
+    for (floats, color, tex_coord) in VERTICES {
+        floats.iter().for_each(|&f| ply.extend(float(f)));
+        ply.extend(color);
+        tex_coord.iter().for_each(|&f| ply.extend(float(f)));
+    }
+    for face in FACES {
+        ply.push(face.len() as u8);
+        face.iter().for_each(|&i| ply.extend(int(i)));
+    }
+    ply.extend(int(0));
+    ply.extend(int(4));
+    ply
+}
+
+fn read(ply: &[u8]) -> (TriangleMesh, ObjectMap) {
+    read_ply(ply, "test").unwrap()
+}
+
+#[test]
+fn ascii_ply_test() {
+    let (mesh, object_map) = read(ascii_ply().as_bytes());
+    let triangles = &object_map["test"];
+    assert_eq!(triangles.len(), 3);
+    assert_eq!(mesh.vertex_positions().len(), 5);
+    assert_eq!(mesh.triangle_normals()[4], Vec3A::NEG_Y);
+    assert_eq!(mesh.tex_coords()[2], Vec2::ONE);
+    assert_eq!(mesh.vertex_colors()[1], Vec3A::Y);
+    assert_eq!(mesh.materials()[0].diffuse_color, Vec3A::ONE);
+
+    // The vertex colors are interpolated at the hit point.
+    let ray = Ray::new(Vec3A::new(0.75, 0.25, 1.0), Vec3A::NEG_Z);
+    let hit = triangles
+        .iter()
+        .find_map(|t| mesh.intersect_triangle(&ray, t, 0.0, 10.0))
+        .unwrap();
+    let color = hit.color.unwrap();
+    assert!((color - Vec3A::new(0.25, 0.5, 0.25)).length() < 1e-5);
+    assert!((hit.tex_coord.unwrap() - Vec2::new(0.75, 0.25)).length() < 1e-5);
+}
+
+#[test]
+fn binary_ply_test() {
+    let (ascii_mesh, ascii_objects) = read(ascii_ply().as_bytes());
+    for big_endian in [false, true] {
+        let (mesh, object_map) = read(&binary_ply(big_endian));
+        assert_eq!(mesh, ascii_mesh);
+        assert_eq!(object_map, ascii_objects);
+    }
+}
+
+#[test]
+fn ply_without_normals_test() {
+    let ply = "ply
+format ascii 1.0
+element vertex 3
+property double x
+property double y
+property double z
+element face 1
+property list uchar uint vertex_index
+end_header
+0 0 0
+0 1 0
+1 0 0
+3 0 1 2
+";
+    let resolver = MemoryResolver::new().with_file("scans/triangle.ply", ply);
+    let (mesh, object_map) = load_ply(&resolver, "scans/triangle.ply").unwrap();
+    let triangle = object_map["triangle"][0];
+    assert!(triangle.is_flat());
+    assert_eq!(
+        mesh.triangle_normals()[triangle.normal_indices().0],
+        Vec3A::NEG_Z
+    );
+    assert!(mesh.vertex_colors().is_empty());
+    assert!(triangle.tex_coord_indices().is_none());
+}
+
+#[test]
+fn ply_error_test() {
+    let error = read_ply("ply\nformat ascii 2.0 oops\n".as_bytes(), "test").unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::Syntax);
+    assert_eq!(error.line(), 3);
+
+    let error = read_ply("ply\nformat text 1.0\n".as_bytes(), "test").unwrap_err();
+    assert_eq!((error.line(), error.column()), (2, 8));
+
+    // Face referencing vertex 7 on line 28.
+    let ply = ascii_ply().replace("3 0 4 1", "3 0 7 1");
+    let error = read_ply(ply.as_bytes(), "test").unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::InvalidIndex);
+    assert_eq!(error.line(), 28);
+
+    let mut ply = binary_ply(false);
+    ply.truncate(ply.len() - 6);
+    let error = read_ply(ply.as_slice(), "test").unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::Syntax);
+    assert_eq!(error.line(), 0);
+
+    // Huge counts in the header of a short file are not allocated up front.
+    let ply = ascii_ply().replace("element vertex 5", "element vertex 1000000000000");
+    let error = read_ply(ply.as_bytes(), "test").unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::Syntax);
+    let mut ply = b"ply
+format binary_little_endian 1.0
+element vertex 0
+property float x
+property float y
+property float z
+element face 1
+property list uint int vertex_indices
+end_header
+"
+    .to_vec();
+    ply.extend(u32::MAX.to_le_bytes());
+    let error = read_ply(ply.as_slice(), "test").unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::Syntax);
+
+    let resolver = MemoryResolver::new();
+    let error = load_ply(&resolver, "missing.ply").unwrap_err();
+    assert_eq!(
+        (error.kind(), error.file()),
+        (ParseErrorKind::Io, "missing.ply")
+    );
+}
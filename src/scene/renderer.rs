@@ -1,14 +1,27 @@
-use std::sync::{atomic::AtomicU32, Mutex};
+use std::{f32::consts::PI, sync::Mutex};
 
-use crate::{primitives::Ray, traits::Intersectable};
+use crate::{
+    material::{IlluminationModel, Material},
+    primitives::{Hit, Ray, PACKET_SIZE},
+    traits::Intersectable,
+};
 
-use super::{Camera, Hit, Scene};
+use super::{Camera, Scene};
 
-use glam::Vec3A;
+use glam::{Vec3A, Vec4};
 use image::RgbImage;
 use itertools::Itertools;
 use rayon::prelude::*;
 
+/// Minimum distance along a ray for an intersection to count.
+const T_MIN: f32 = 0.01;
+/// Maximum distance along a ray for an intersection to count.
+const T_MAX: f32 = 100.0;
+
+/// Offsets of the pixels of a tile relative to its top left pixel, in the same
+/// order as the rays of a camera ray packet.
+const TILE_OFFSETS: [(u32, u32); PACKET_SIZE] = [(0, 0), (1, 0), (0, 1), (1, 1)];
+
 pub struct SceneRenderer<'scene> {
     camera: &'scene Camera,
     scene: &'scene Scene<'scene>,
@@ -39,26 +52,45 @@ impl<'scene> SceneRenderer<'scene> {
         self.recursion_depth = recursion_depth;
     }
 
+    /// Renders the scene. The image is split into 2x2 pixel tiles, and the
+    /// primary rays of a tile are traced together as a ray packet.
     pub fn render(&self) -> RgbImage {
         let (width, height) = self.camera.get_dimensions();
         let image: Mutex<image::RgbImage> = Mutex::new(RgbImage::new(width, height));
         (0..width)
-            .cartesian_product(0..height)
+            .step_by(2)
+            .cartesian_product((0..height).step_by(2))
             .par_bridge()
             .for_each(|(x, y)| {
-                let pixel = self.render_pixel(x, y);
+                let tile = self.render_tile(x, y);
                 let mut image = image.lock().unwrap();
-                image.put_pixel(x, y, Self::vec3_to_rgb(pixel));
+                for ((dx, dy), pixel) in TILE_OFFSETS.iter().zip(tile) {
+                    let (px, py) = (x + dx, y + dy);
+                    if px < width && py < height {
+                        image.put_pixel(px, py, Self::vec3_to_rgb(pixel));
+                    }
+                }
             });
         image.into_inner().unwrap()
     }
 
+    /// Renders the 2x2 pixel tile whose top left pixel is (x, y). Pixels
+    /// outside of the image are rendered as well, and discarded by the caller.
     #[inline]
-    fn render_pixel(&self, x: u32, y: u32) -> Vec3A {
-        (0..self.sample_count)
-            .map(|_| self.trace(&self.camera.get_jittered_ray(x, y), 0, Vec3A::ONE))
-            .sum::<Vec3A>()
-            / self.sample_count as f32
+    fn render_tile(&self, x: u32, y: u32) -> [Vec3A; PACKET_SIZE] {
+        let mut colors = [Vec3A::ZERO; PACKET_SIZE];
+        for _ in 0..self.sample_count {
+            let packet = self.camera.get_jittered_ray_packet(x, y);
+            let hits = self
+                .scene
+                .intersect_packet(&packet, T_MIN, Vec4::splat(T_MAX));
+            for (color, hit) in colors.iter_mut().zip(hits) {
+                if let Some(hit) = hit {
+                    *color += self.shade(&hit, 0, Vec3A::ONE);
+                }
+            }
+        }
+        colors.map(|c| c / self.sample_count as f32)
     }
 
     fn trace(&self, ray: &Ray, depth: u32, throughput: Vec3A) -> Vec3A {
@@ -66,19 +98,82 @@ impl<'scene> SceneRenderer<'scene> {
             return Vec3A::ZERO;
         }
 
+        match self.scene.intersect(ray, T_MIN, T_MAX) {
+            Some(hit) => self.shade(&hit, depth, throughput),
+            None => Vec3A::ZERO,
+        }
+    }
+
+    /// Computes the color contributed by the surface at the hit, including
+    /// the light that is bounced towards it. The colors of the material are
+    /// sampled from its textures at the texture coordinates of the hit.
+    fn shade(&self, hit: &Hit, depth: u32, throughput: Vec3A) -> Vec3A {
         let mesh = self.scene.triangle_mesh();
-        let mut throughput: Vec3A = throughput;
-        let mut color = Vec3A::ZERO;
-
-        if let Some(hit) = self.scene.intersect(ray, 0.01, 100.0) {
-            let material = hit.material(mesh);
-            color += material.emissive_color * throughput * 5.0;
-            throughput *= material.diffuse_color;
-            color += self.trace(&hit.random_outgoing_ray(mesh), depth + 1, throughput);
+        let material = hit.material(mesh);
+        let tex_coord = hit.tex_coord();
+
+        // Partially transparent surfaces let a fraction of the rays pass
+        // through unchanged.
+        if material.transparency_at(tex_coord) < rand::random::<f32>() {
+            let ray = hit.spawn_ray(hit.incoming.direction);
+            return self.trace(&ray, depth + 1, throughput);
+        }
+
+        // Normal and bump maps only change how the surface is shaded, so
+        // they are applied here instead of for every intersection.
+        let hit = &mesh.apply_bump_map(hit.clone());
+
+        let mut color = material.emissive_color_at(tex_coord) * throughput * 5.0;
+        if material.illumination() == IlluminationModel::Constant {
+            color += material.diffuse_color_at_hit(hit) * throughput;
+        } else {
+            color += self.direct_light(hit, material) * throughput;
+            if let Some((ray, weight)) = material.scatter(hit) {
+                color += self.trace(&ray, depth + 1, throughput * weight);
+            }
         }
         color
     }
 
+    /// Computes the light from the punctual lights of the scene that is
+    /// reflected diffusely at the hit. Lights are skipped if the path to
+    /// them is blocked.
+    fn direct_light(&self, hit: &Hit, material: &Material) -> Vec3A {
+        let lights = self.scene.lights();
+        if lights.is_empty() {
+            return Vec3A::ZERO;
+        }
+        let reflectance = material.diffuse_reflectance_at_hit(hit);
+        if reflectance == Vec3A::ZERO {
+            return Vec3A::ZERO;
+        }
+        let normal = if hit.normal.dot(hit.incoming.direction) > 0.0 {
+            -hit.normal
+        } else {
+            hit.normal
+        };
+
+        let irradiance = lights
+            .iter()
+            .filter_map(|light| {
+                let (direction, distance, light_color) = light.illuminate(hit.hit_point)?;
+                let cos = normal.dot(direction);
+                if cos <= 0.0 {
+                    return None;
+                }
+                let shadow_ray = hit.spawn_ray(direction);
+                if self
+                    .scene
+                    .intersected(&shadow_ray, T_MIN, distance.min(T_MAX))
+                {
+                    return None;
+                }
+                Some(light_color * cos)
+            })
+            .fold(Vec3A::ZERO, |sum, light| sum + light);
+        irradiance * reflectance / PI
+    }
+
     #[inline]
     fn vec3_to_rgb(color: Vec3A) -> image::Rgb<u8> {
         let r = (color.x * 255.0) as u8;
@@ -0,0 +1,43 @@
+use super::{camera::CameraBuilder, Scene, SceneRenderer};
+
+use crate::{
+    material::Material,
+    primitives::{shape::Plane, TriangleMesh},
+};
+
+use glam::Vec3A;
+use image::Rgb;
+
+/// Unlit material (`illum 0`) of the given color and dissolve.
+fn unlit_material(color: Vec3A, transparency: f32) -> Material {
+    Material {
+        diffuse_color: color,
+        transparency,
+        illumination_model: 0,
+        ..Material::default()
+    }
+}
+
+#[test]
+fn transparency_test() {
+    let camera = CameraBuilder::new()
+        .with_position(Vec3A::new(0.0, 0.0, 5.0))
+        .with_target(Vec3A::ZERO)
+        .with_width(2)
+        .with_height(2)
+        .build();
+
+    // A red plane in front of a green plane.
+    for (transparency, expected) in [(1.0, Rgb([255, 0, 0])), (0.0, Rgb([0, 255, 0]))] {
+        let mesh = TriangleMesh::new(Vec::new(), Vec::new(), Vec::new());
+        let mut scene = Scene::new(&mesh, Vec::new());
+        let red = unlit_material(Vec3A::X, transparency);
+        scene.add_shape(Plane::new(Vec3A::Z, Vec3A::Z, red));
+        let green = unlit_material(Vec3A::Y, 1.0);
+        scene.add_shape(Plane::new(Vec3A::ZERO, Vec3A::Z, green));
+
+        // Transparent surfaces let the rays pass through.
+        let image = SceneRenderer::new(&camera, &scene).render();
+        assert!(image.pixels().all(|&pixel| pixel == expected));
+    }
+}
@@ -0,0 +1,286 @@
+use std::{collections::HashMap, path::Path};
+
+use glam::Vec3A;
+
+use crate::{
+    error::{column, ParseError, ParseErrorKind},
+    material::Material,
+    primitives::{
+        triangle::triangulation::triangulate, Normal, Position, TriangleIndex, TriangleMesh,
+    },
+    resolver::AssetResolver,
+    scene::parser::ObjectMap,
+};
+
+/// Size of the header of a binary stl file, followed by the number of facets.
+const BINARY_HEADER_SIZE: usize = 80;
+/// Size of a facet of a binary stl file: normal, three vertices and the
+/// attribute byte count.
+const BINARY_FACET_SIZE: usize = 50;
+
+/// Load an stl file from `resolver`. Both ASCII and binary stl files are
+/// supported, the encoding is detected from the content of the file.
+///
+/// Vertices shared by facets are welded, so every position is stored once.
+/// Every facet gets a flat normal, the normal of the file or the normal of
+/// the vertices in counter-clockwise order if the file has a zero normal.
+/// Facets whose vertices are welded together are dropped.
+///
+/// Every `solid` of an ASCII file is an object named after the solid, solids
+/// without a name and binary files are named after the file without its
+/// extension.
+///
+/// ### Arguments
+/// - `resolver` - The source of the stl file.
+/// - `path` - The path of the stl file in the resolver.
+/// - `material` - The material of all facets, a light grey diffuse material
+///   if `None`. stl files don't have materials.
+///
+/// ### Returns
+/// The triangle mesh and an object map with the objects of the file. An
+/// error is returned if the file can't be read or parsed. Errors in binary
+/// files have line 0.
+pub fn load_stl(
+    resolver: &dyn AssetResolver,
+    path: &str,
+    material: Option<Material>,
+) -> Result<(TriangleMesh, ObjectMap), ParseError> {
+    let location = resolver.location(path);
+    let data = resolver
+        .read(path)
+        .map_err(|e| ParseError::io(&location, e))?;
+    let name = Path::new(path)
+        .file_stem()
+        .map_or(path.into(), |stem| stem.to_string_lossy());
+    parse_stl(&data, &name, material).map_err(|e| e.in_file(&location))
+}
+
+/// Parse the content of an stl file. See `load_stl`.
+///
+/// ### Arguments
+/// - `data` - The content of the stl file.
+/// - `name` - The name of binary files and solids without a name.
+/// - `material` - The material of all facets, see `load_stl`.
+pub fn parse_stl(
+    data: &[u8],
+    name: &str,
+    material: Option<Material>,
+) -> Result<(TriangleMesh, ObjectMap), ParseError> {
+    let mut builder = MeshBuilder::default();
+    if is_binary(data) {
+        parse_binary(data, name, &mut builder)?;
+    } else {
+        let text = std::str::from_utf8(data)
+            .map_err(|e| ParseError::new(ParseErrorKind::Syntax, 0, 0, e.to_string()))?;
+        parse_ascii(text, name, &mut builder)?;
+    }
+
+    let material = material.unwrap_or_else(|| Material {
+        diffuse_color: Vec3A::splat(0.8),
+        ..Material::default()
+    });
+    let mesh = TriangleMesh::new(builder.positions, builder.normals, vec![material]);
+    Ok((mesh, builder.object_map))
+}
+
+/// Check whether `data` is a binary stl file. Binary files may start with
+/// `solid` as well, so files whose size matches their facet count are
+/// binary, and files that don't start with `solid` are assumed to be binary.
+fn is_binary(data: &[u8]) -> bool {
+    if let Some(count) = data.get(BINARY_HEADER_SIZE..BINARY_HEADER_SIZE + 4) {
+        let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+        if count
+            .checked_mul(BINARY_FACET_SIZE)
+            .is_some_and(|size| BINARY_HEADER_SIZE + 4 + size == data.len())
+        {
+            return true;
+        }
+    }
+    let start = data
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(data.len());
+    !data[start..].starts_with(b"solid")
+}
+
+/// Welds the vertices of the facets and collects the triangles per object.
+#[derive(Debug, Default)]
+struct MeshBuilder {
+    positions: Vec<Position>,
+    normals: Vec<Normal>,
+    /// Index of every position, by the bits of its coordinates.
+    position_indices: HashMap<[u32; 3], usize>,
+    object_map: ObjectMap,
+}
+
+impl MeshBuilder {
+    /// Get the index of a position, adding the position if it is new.
+    fn weld(&mut self, position: Position) -> usize {
+        // Adding zero turns -0 into 0, so both are welded.
+        let key = (position + Vec3A::ZERO).to_array().map(f32::to_bits);
+        *self.position_indices.entry(key).or_insert_with(|| {
+            self.positions.push(position);
+            self.positions.len() - 1
+        })
+    }
+
+    /// Add a facet to an object. Facets with more than three vertices are
+    /// triangulated.
+    fn add_facet(&mut self, object: &str, normal: Normal, vertices: &[Position]) {
+        let indices: Vec<usize> = vertices.iter().map(|&v| self.weld(v)).collect();
+        let mut triangles = Vec::new();
+        for (a, b, c) in triangulate(vertices) {
+            let (v1, v2, v3) = (indices[a], indices[b], indices[c]);
+            if v1 == v2 || v2 == v3 || v1 == v3 {
+                continue;
+            }
+            let normal = if normal.length_squared() > 0.0 {
+                normal.normalize()
+            } else {
+                let (p1, p2, p3) = (vertices[a], vertices[b], vertices[c]);
+                (p2 - p1).cross(p3 - p1).normalize_or_zero()
+            };
+            self.normals.push(normal);
+            triangles.push(TriangleIndex::new((v1, v2, v3), self.normals.len() - 1, 0));
+        }
+        self.object_map
+            .entry(object.to_owned())
+            .or_default()
+            .extend(triangles);
+    }
+}
+
+/// Parse a binary stl file into `builder`.
+fn parse_binary(data: &[u8], name: &str, builder: &mut MeshBuilder) -> Result<(), ParseError> {
+    let error = |description: String| ParseError::new(ParseErrorKind::Syntax, 0, 0, description);
+    let Some(count) = data.get(BINARY_HEADER_SIZE..BINARY_HEADER_SIZE + 4) else {
+        return Err(error(format!(
+            "Expected binary stl header of {} bytes, found {} bytes",
+            BINARY_HEADER_SIZE + 4,
+            data.len()
+        )));
+    };
+    let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+    let facets = &data[BINARY_HEADER_SIZE + 4..];
+    if facets.len() / BINARY_FACET_SIZE < count {
+        return Err(error(format!(
+            "Unexpected end of file, expected {} facets, found {}",
+            count,
+            facets.len() / BINARY_FACET_SIZE
+        )));
+    }
+
+    builder.object_map.entry(name.to_owned()).or_default();
+    for facet in facets.chunks_exact(BINARY_FACET_SIZE).take(count) {
+        let mut vectors = facet[..48].chunks_exact(12).map(|vector| {
+            let mut coordinates = vector
+                .chunks_exact(4)
+                .map(|f| f32::from_le_bytes(f.try_into().unwrap()));
+            Vec3A::from_array([(); 3].map(|_| coordinates.next().unwrap()))
+        });
+        let normal = vectors.next().unwrap();
+        let vertices: Vec<Position> = vectors.collect();
+        builder.add_facet(name, normal, &vertices);
+    }
+    Ok(())
+}
+
+/// Parse an ASCII stl file into `builder`.
+fn parse_ascii(text: &str, name: &str, builder: &mut MeshBuilder) -> Result<(), ParseError> {
+    let mut solid: Option<String> = None;
+    let mut normal: Option<Normal> = None;
+    let mut in_loop = false;
+    let mut vertices = Vec::new();
+    let mut line_number = 0;
+
+    for (index, line) in text.lines().enumerate() {
+        line_number = index + 1;
+        let error = |part: &str, description: String| {
+            ParseError::new(
+                ParseErrorKind::Syntax,
+                line_number,
+                column(line, part),
+                description,
+            )
+        };
+        let unexpected = |keyword: &str| error(keyword, format!("Unexpected '{}'", keyword));
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+
+        match keyword {
+            "solid" if solid.is_none() => {
+                // The name is the rest of the line and may contain spaces.
+                let object = line.trim_start()["solid".len()..].trim();
+                let object = if object.is_empty() { name } else { object };
+                builder.object_map.entry(object.to_owned()).or_default();
+                solid = Some(object.to_owned());
+            }
+            "endsolid" if solid.is_some() && normal.is_none() => solid = None,
+            "facet" if solid.is_some() && normal.is_none() => {
+                if words.next() != Some("normal") {
+                    return Err(error(
+                        line,
+                        "Expected facet of the form `facet normal <x> <y> <z>`".into(),
+                    ));
+                }
+                normal = Some(parse_vector(line, words, &error)?);
+            }
+            "outer" if normal.is_some() && !in_loop => {
+                if words.next() != Some("loop") {
+                    return Err(error(line, "Expected `outer loop`".into()));
+                }
+                in_loop = true;
+            }
+            "vertex" if in_loop => vertices.push(parse_vector(line, words, &error)?),
+            "endloop" if in_loop => {
+                if vertices.len() < 3 {
+                    return Err(error(
+                        keyword,
+                        format!(
+                            "Facet with {} vertices, expected at least 3",
+                            vertices.len()
+                        ),
+                    ));
+                }
+                in_loop = false;
+            }
+            "endfacet" if normal.is_some() && !in_loop && !vertices.is_empty() => {
+                let object = solid.as_deref().unwrap_or(name);
+                builder.add_facet(object, normal.take().unwrap(), &vertices);
+                vertices.clear();
+            }
+            _ => return Err(unexpected(keyword)),
+        }
+    }
+
+    if solid.is_some() {
+        return Err(ParseError::new(
+            ParseErrorKind::Syntax,
+            line_number,
+            0,
+            "Expected `endsolid` before the end of the file",
+        ));
+    }
+    Ok(())
+}
+
+/// Parse the three coordinates of a `facet normal` or `vertex` line.
+fn parse_vector<'a>(
+    line: &str,
+    mut words: impl Iterator<Item = &'a str>,
+    error: &impl Fn(&str, String) -> ParseError,
+) -> Result<Vec3A, ParseError> {
+    let mut vector = Vec3A::ZERO;
+    for i in 0..3 {
+        let word = words.next().unwrap_or(&line[line.len()..]);
+        vector[i] = word
+            .parse()
+            .map_err(|_| error(word, format!("Expected number, found '{}'", word)))?;
+    }
+    if let Some(word) = words.next() {
+        return Err(error(word, format!("Unexpected '{}'", word)));
+    }
+    Ok(vector)
+}
@@ -0,0 +1,115 @@
+use super::stl::{load_stl, parse_stl};
+
+use crate::{error::ParseErrorKind, material::Material, resolver::MemoryResolver};
+
+use glam::Vec3A;
+
+/// Two facets of a unit square in the z = 0 plane, sharing two vertices.
+const SQUARE: [[f32; 9]; 2] = [
+    [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0, 1.0, 0.0, -0.0, 1.0, 0.0],
+];
+
+/// Binary stl file of the square with zero normals. The header starts with
+/// `solid` like the header of some exporters.
+fn binary_square() -> Vec<u8> {
+    let mut stl = b"solid exported by some CAD program".to_vec();
+    stl.resize(80, 0);
+    stl.extend((SQUARE.len() as u32).to_le_bytes());
+    for facet in SQUARE {
+        stl.extend([0.0f32; 3].iter().flat_map(|f| f.to_le_bytes()));
+        stl.extend(facet.iter().flat_map(|f| f.to_le_bytes()));
+        stl.extend(0u16.to_le_bytes());
+    }
+    stl
+}
+
+#[test]
+fn ascii_stl_test() {
+    let stl = "solid Square plate
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 1 1 0
+    endloop
+  endfacet
+  facet normal 0 0 2
+    outer loop
+      vertex 0 0 0
+      vertex 1 1 0
+      vertex -0 1 0
+    endloop
+  endfacet
+endsolid Square plate
+solid
+  facet normal 0 0 0
+    outer loop
+      vertex 0 0 0
+      vertex 0 0 1
+      vertex 1 0 0
+    endloop
+  endfacet
+endsolid
+";
+    let resolver = MemoryResolver::new().with_file("parts/bracket.stl", stl);
+    let (mesh, object_map) = load_stl(&resolver, "parts/bracket.stl", None).unwrap();
+    assert_eq!(object_map.len(), 2);
+    assert_eq!(mesh.vertex_positions().len(), 5);
+
+    let square = &object_map["Square plate"];
+    assert_eq!(square.len(), 2);
+    assert_eq!(square[0].vertex_indices(), (0, 1, 2));
+    assert_eq!(square[1].vertex_indices(), (0, 2, 3));
+    assert_eq!(
+        mesh.triangle_normals()[square[1].normal_indices().0],
+        Vec3A::Z
+    );
+
+    // The solid without a name is named after the file, its zero normal is
+    // computed from the vertices.
+    let triangle = object_map["bracket"][0];
+    assert!(triangle.is_flat());
+    assert_eq!(
+        mesh.triangle_normals()[triangle.normal_indices().0],
+        Vec3A::Y
+    );
+    assert_eq!(mesh.materials()[0].diffuse_color, Vec3A::splat(0.8));
+}
+
+#[test]
+fn binary_stl_test() {
+    let material = Material {
+        diffuse_color: Vec3A::new(0.2, 0.3, 0.8),
+        ..Material::default()
+    };
+    let (mesh, object_map) = parse_stl(&binary_square(), "square", Some(material.clone())).unwrap();
+    assert_eq!(mesh.vertex_positions().len(), 4);
+    assert_eq!(object_map["square"].len(), 2);
+    assert!(mesh.triangle_normals().iter().all(|&n| n == Vec3A::Z));
+    assert_eq!(mesh.materials(), &vec![material]);
+}
+
+#[test]
+fn stl_error_test() {
+    let stl = "solid
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 zero
+";
+    let error = parse_stl(stl.as_bytes(), "test", None).unwrap_err();
+    assert_eq!(error.kind(), ParseErrorKind::Syntax);
+    assert_eq!((error.line(), error.column()), (4, 18));
+
+    let error = parse_stl(b"solid\nendfacet\n", "test", None).unwrap_err();
+    assert_eq!((error.line(), error.column()), (2, 1));
+
+    let error = parse_stl(b"solid test\n", "test", None).unwrap_err();
+    assert_eq!(error.line(), 1);
+
+    let mut stl = binary_square();
+    stl.truncate(stl.len() - 1);
+    stl[0] = b'x';
+    let error = parse_stl(&stl, "test", None).unwrap_err();
+    assert_eq!((error.kind(), error.line()), (ParseErrorKind::Syntax, 0));
+}
@@ -0,0 +1,168 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{material::writer::write_mtl, primitives::TriangleMesh};
+
+use super::object::Object;
+
+/// Get a name for every material of a mesh, for example to write the mesh
+/// with `write_obj`. Materials in `material_map` keep their name, the name of
+/// the first one in alphabetical order if a material has several names.
+/// Other materials, like the default material added by the obj parser, are
+/// named `material_<index>`.
+///
+/// ### Arguments
+/// - `material_count` - The number of materials of the mesh.
+/// - `material_map` - The names of the materials, as returned by the parsers.
+///
+/// ### Returns
+/// The names, in the order of the materials.
+pub fn material_names(material_count: usize, material_map: &HashMap<String, usize>) -> Vec<String> {
+    let mut names: Vec<Option<&String>> = vec![None; material_count];
+    for (name, &index) in material_map {
+        if let Some(slot) = names.get_mut(index) {
+            match slot {
+                Some(other) if *other <= name => (),
+                _ => *slot = Some(name),
+            }
+        }
+    }
+    names
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| match name {
+            Some(name) => name.clone(),
+            None => {
+                let mut name = format!("material_{}", i);
+                while material_map.contains_key(&name) {
+                    name.push('_');
+                }
+                name
+            }
+        })
+        .collect()
+}
+
+/// Write a triangle mesh and its objects as an obj file, so that the obj
+/// parser reads back the same mesh and objects. All vertex data of the mesh
+/// is written, followed by an object (`o`) per object with a face per
+/// triangle. Vertex colors are written after the positions (`v x y z r g b`).
+///
+/// Object and material names are written as they are, so they must not be
+/// empty or start or end with whitespace. The materials are written with
+/// `write_mtl`, see `save_obj`.
+///
+/// ### Arguments
+/// - `writer` - The writer to write the obj file to.
+/// - `mesh` - The mesh the objects are part of.
+/// - `objects` - The objects to write.
+/// - `material_names` - The name of every material of the mesh, see
+///   `material_names`.
+/// - `mtl_file` - The material library to refer to (`mtllib`), relative to
+///   the obj file. Without a material library all faces get the default
+///   material when the file is read.
+///
+/// ### Panics
+/// If a triangle uses a material without a name.
+pub fn write_obj<W: Write>(
+    mut writer: W,
+    mesh: &TriangleMesh,
+    objects: &[Object],
+    material_names: &[String],
+    mtl_file: Option<&str>,
+) -> io::Result<()> {
+    if let Some(mtl_file) = mtl_file {
+        writeln!(writer, "mtllib {}", mtl_file)?;
+    }
+    let colors = mesh.vertex_colors();
+    for (i, p) in mesh.vertex_positions().iter().enumerate() {
+        match colors.get(i) {
+            Some(c) => writeln!(writer, "v {} {} {} {} {} {}", p.x, p.y, p.z, c.x, c.y, c.z)?,
+            None => writeln!(writer, "v {} {} {}", p.x, p.y, p.z)?,
+        }
+    }
+    for t in mesh.tex_coords() {
+        writeln!(writer, "vt {} {}", t.x, t.y)?;
+    }
+    for n in mesh.triangle_normals() {
+        writeln!(writer, "vn {} {} {}", n.x, n.y, n.z)?;
+    }
+
+    let mut current_material = None;
+    for object in objects {
+        writeln!(writer, "o {}", object.identifier)?;
+        for triangle in &object.triangles {
+            let material = triangle.material_index();
+            if current_material != Some(material) {
+                writeln!(writer, "usemtl {}", material_names[material])?;
+                current_material = Some(material);
+            }
+
+            // Indices start at 1 in obj files.
+            let (v1, v2, v3) = triangle.vertex_indices();
+            let (n1, n2, n3) = triangle.normal_indices();
+            match triangle.tex_coord_indices() {
+                Some((t1, t2, t3)) => writeln!(
+                    writer,
+                    "f {}/{}/{} {}/{}/{} {}/{}/{}",
+                    v1 + 1,
+                    t1 + 1,
+                    n1 + 1,
+                    v2 + 1,
+                    t2 + 1,
+                    n2 + 1,
+                    v3 + 1,
+                    t3 + 1,
+                    n3 + 1
+                )?,
+                None => writeln!(
+                    writer,
+                    "f {}//{} {}//{} {}//{}",
+                    v1 + 1,
+                    n1 + 1,
+                    v2 + 1,
+                    n2 + 1,
+                    v3 + 1,
+                    n3 + 1
+                )?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Save a triangle mesh and its objects as an obj file, and the materials of
+/// the mesh as an mtl file next to it, with the same name and the extension
+/// `mtl`. See `write_obj` and `write_mtl`.
+///
+/// ### Arguments
+/// - `path` - The path of the obj file.
+/// - `mesh` - The mesh the objects are part of.
+/// - `objects` - The objects to write.
+/// - `material_map` - The names of the materials, see `material_names`.
+pub fn save_obj(
+    path: impl AsRef<Path>,
+    mesh: &TriangleMesh,
+    objects: &[Object],
+    material_map: &HashMap<String, usize>,
+) -> io::Result<()> {
+    let path = path.as_ref();
+    let mtl_path = path.with_extension("mtl");
+    let mtl_file = mtl_path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Expected a file path"))?
+        .to_string_lossy();
+    let names = material_names(mesh.materials().len(), material_map);
+
+    let mut writer = BufWriter::new(File::create(&mtl_path)?);
+    write_mtl(&mut writer, mesh.materials(), &names)?;
+    writer.flush()?;
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_obj(&mut writer, mesh, objects, &names, Some(&mtl_file))?;
+    writer.flush()
+}
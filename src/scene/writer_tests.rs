@@ -0,0 +1,117 @@
+use std::{collections::HashMap, io::Cursor};
+
+use super::parser::{get_objects, load_obj, parse_obj, ObjectMap};
+use super::writer::{material_names, write_obj};
+
+use crate::{
+    material::writer::write_mtl,
+    primitives::TriangleMesh,
+    resolver::{FileSystemResolver, MemoryResolver},
+};
+
+use glam::{Vec2, Vec3A};
+use image::{ImageOutputFormat, Rgba, RgbaImage};
+
+/// Write an obj file with its materials and read it back.
+fn round_trip(
+    mesh: &TriangleMesh,
+    material_map: &HashMap<String, usize>,
+    object_map: &ObjectMap,
+    resolver: MemoryResolver,
+) -> (TriangleMesh, HashMap<String, usize>, ObjectMap) {
+    let names = material_names(mesh.materials().len(), material_map);
+    let mut mtl = Vec::new();
+    write_mtl(&mut mtl, mesh.materials(), &names).unwrap();
+    let mut obj = Vec::new();
+    let objects = get_objects(mesh, object_map);
+    write_obj(&mut obj, mesh, &objects, &names, Some("model.mtl")).unwrap();
+
+    let resolver = resolver
+        .with_file("model.mtl", mtl)
+        .with_file("model.obj", obj);
+    load_obj(&resolver, "model.obj").unwrap()
+}
+
+#[test]
+fn round_trip_test_files() {
+    for file in [
+        "monkey.obj",
+        "test.obj",
+        "test_multiple_mats.obj",
+        "test_scene.obj",
+    ] {
+        let (mesh, material_map, object_map) =
+            load_obj(&FileSystemResolver::new("test"), file).unwrap();
+        let (mesh2, material_map2, object_map2) =
+            round_trip(&mesh, &material_map, &object_map, MemoryResolver::new());
+        assert_eq!(mesh2, mesh, "{}", file);
+        assert_eq!(object_map2, object_map, "{}", file);
+        // Unnamed materials get a name when they are written.
+        for (name, index) in &material_map {
+            assert_eq!(material_map2.get(name), Some(index), "{}", file);
+        }
+    }
+}
+
+#[test]
+fn round_trip_test() {
+    let mut png = Vec::new();
+    RgbaImage::from_pixel(2, 2, Rgba([255, 128, 0, 255]))
+        .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+        .unwrap();
+    let resolver = MemoryResolver::new()
+        .with_file("textures/wood.png", png)
+        .with_file(
+            "wood.mtl",
+            "newmtl Wood floor
+Kd 0.1 0.2 0.3
+Pr 0.35
+Ps 0.1 0.2 0.3
+anisor 0.25
+illum 7
+map_Kd -s 2 2 1 -o 0.5 0 0 -clamp on textures/wood.png
+map_Bump -bm 0.2 textures/wood.png
+",
+        );
+    let input = "mtllib wood.mtl
+v 0 0 0 1 0 0
+v 1 0 0 0 1 0
+v 0 1 0
+v 1 1 0.5
+vt 0 0
+vt 1 0
+vt 0 1
+vn 0 0 1
+o Default
+s off
+f 2//1 4//1 3//1
+o Floor
+s 1
+usemtl Wood floor
+f 1/1/1 2/2/1 3/3/1
+";
+    let (mesh, material_map, object_map) = parse_obj(input, &resolver).unwrap();
+    assert_eq!(mesh.vertex_colors()[2], Vec3A::ONE);
+    assert_eq!(mesh.tex_coords()[1], Vec2::X);
+    assert_eq!(mesh.materials().len(), 2);
+    assert!(mesh.materials()[0].diffuse_map.as_ref().unwrap().clamp);
+
+    let (mesh2, material_map2, object_map2) =
+        round_trip(&mesh, &material_map, &object_map, resolver);
+    assert_eq!(mesh2, mesh);
+    assert_eq!(object_map2, object_map);
+    assert_eq!(material_map2["Wood floor"], 0);
+    assert_eq!(material_map2["material_1"], 1);
+}
+
+#[test]
+fn material_names_test() {
+    let material_map = [("b", 0), ("a", 0), ("material_1", 2)]
+        .into_iter()
+        .map(|(name, i)| (name.to_owned(), i))
+        .collect();
+    assert_eq!(
+        material_names(3, &material_map),
+        vec!["a", "material_1_", "material_1"]
+    );
+}
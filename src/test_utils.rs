@@ -0,0 +1,7 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Random number generator with a fixed seed, so the tests using random
+/// numbers are reproducible.
+pub(crate) fn test_rng() -> StdRng {
+    StdRng::seed_from_u64(0x5eed)
+}
@@ -1,4 +1,6 @@
-use super::primitives::{Hit, Ray};
+use super::primitives::{BoundingBox, Hit, PacketHits, Ray, RayPacket};
+
+use glam::Vec4;
 
 pub trait Intersectable {
     /// Returns the closest intersection between the ray and the object.
@@ -24,6 +26,8 @@ pub trait Intersectable {
     /// Returns true if the ray intersects the object.
     ///
     /// Default implementation is to check if intersect() returns Some.
+    /// Implementations should override it to return as soon as any
+    /// intersection is found, since the closest one is not needed.
     ///
     /// # Arguments
     /// - `ray` - The ray to check for intersection.
@@ -35,4 +39,30 @@ pub trait Intersectable {
     fn intersected(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
         self.intersect(ray, t_min, t_max).is_some()
     }
+
+    /// Returns the closest intersection for every ray in the packet.
+    ///
+    /// Default implementation intersects the rays one at a time with
+    /// intersect(). Implementations can override it to test all rays at once
+    /// using SIMD instructions.
+    ///
+    /// # Arguments
+    /// - `packet` - The rays to check for intersection.
+    /// - `t_min` - The minimum distance along the rays to check for
+    ///   intersection.
+    /// - `t_max` - The maximum distance along each of the rays to check for
+    ///   intersection.
+    fn intersect_packet(&self, packet: &RayPacket, t_min: f32, t_max: Vec4) -> PacketHits {
+        let t_max = t_max.to_array();
+        let mut hits = PacketHits::default();
+        for (lane, ray) in packet.rays().iter().enumerate() {
+            hits[lane] = self.intersect(ray, t_min, t_max[lane]);
+        }
+        hits
+    }
+}
+
+pub trait Bounded {
+    /// Returns the axis aligned bounding box enclosing the object.
+    fn bounding_box(&self) -> BoundingBox;
 }